@@ -0,0 +1,102 @@
+//! Throughput benchmarks for loading records.
+//!
+//! Run them in release mode with `cargo bench --bench batch`; pass a name to run only the benchmarks
+//! whose name contains it, e.g. `cargo bench --bench batch -- natural_keys`.
+
+use serde_json::{json, Value};
+use std::env;
+use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use rustbase::memory::structure::Store;
+use rustbase::memory::database::Database;
+use rustbase::memory::ids::IdStrategy;
+use rustbase::memory::constraints::ReferentialAction;
+
+const RECORDS: usize = 1_000_000;
+
+fn records(count: usize) -> Vec<HashMap<String, Value>> {
+    (0..count).map(|i| HashMap::from([
+        ("name".to_string(), json!(format!("user {}", i))),
+        ("email".to_string(), json!(format!("user{}@example.com", i))),
+        ("age".to_string(), json!(i % 90)),
+    ])).collect()
+}
+
+fn report(label: &str, count: usize, started: Instant) {
+    let elapsed = started.elapsed();
+    println!("{:<40} {:>9} records in {:>8.3}s, {:>10.0} records/s", label, count, elapsed.as_secs_f64(), count as f64 / elapsed.as_secs_f64());
+}
+
+fn keyed_database(strategy: IdStrategy) -> Database {
+    let mut db = Database::new("batch_bench_db").unwrap();
+    db.add_store("users".to_string(), HashSet::new()).unwrap();
+    db.set_id_strategy("users", strategy).unwrap();
+    db
+}
+
+fn add_record_one_at_a_time() {
+    let mut store = Store::new().unwrap();
+    let batch = records(RECORDS);
+    let started = Instant::now();
+    for record in batch {
+        store.add_record(record).unwrap();
+    }
+    report("Store::add_record", RECORDS, started);
+}
+
+fn add_records_batch() {
+    let mut store = Store::new().unwrap();
+    let batch = records(RECORDS);
+    let started = Instant::now();
+    store.add_records(batch).unwrap();
+    report("Store::add_records", RECORDS, started);
+}
+
+fn add_records_with_natural_keys() {
+    let mut db = keyed_database(IdStrategy::NaturalKey { attribute: "email".to_string() });
+    let batch = records(RECORDS);
+    let started = Instant::now();
+    db.get_store_mut("users").unwrap().add_records(batch).unwrap();
+    report("Store::add_records, natural keys", RECORDS, started);
+}
+
+fn add_records_with_generated_uuids() {
+    let mut db = keyed_database(IdStrategy::Uuid { attribute: "uuid".to_string() });
+    let batch = records(RECORDS);
+    let started = Instant::now();
+    db.get_store_mut("users").unwrap().add_records(batch).unwrap();
+    report("Store::add_records, generated UUIDs", RECORDS, started);
+}
+
+fn database_add_records_with_foreign_key() {
+    let mut db = Database::new("batch_bench_db").unwrap();
+    db.add_store("teams".to_string(), HashSet::new()).unwrap();
+    db.add_store("users".to_string(), HashSet::new()).unwrap();
+    db.add_foreign_key("users", "team_id", "teams", ReferentialAction::Cascade).unwrap();
+    let team = db.add_record("teams", HashMap::from([("name".to_string(), json!("core"))])).unwrap();
+    let mut batch = records(RECORDS);
+    for record in &mut batch {
+        record.insert("team_id".to_string(), json!(team));
+    }
+
+    let started = Instant::now();
+    db.add_records("users", batch).unwrap();
+    report("Database::add_records, foreign key", RECORDS, started);
+}
+
+fn main() {
+    let benchmarks: [(&str, fn()); 5] = [
+        ("add_record_one_at_a_time", add_record_one_at_a_time),
+        ("add_records_batch", add_records_batch),
+        ("add_records_with_natural_keys", add_records_with_natural_keys),
+        ("add_records_with_generated_uuids", add_records_with_generated_uuids),
+        ("database_add_records_with_foreign_key", database_add_records_with_foreign_key),
+    ];
+    // Cargo passes `--bench` and any filter given after `--`.
+    let filters: Vec<String> = env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+    for (name, benchmark) in benchmarks {
+        if filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str())) {
+            benchmark();
+        }
+    }
+}
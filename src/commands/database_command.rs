@@ -1,12 +1,19 @@
 use std::io;
+use std::collections::HashMap;
 use std::io::{BufRead, Write};
-use crate::memory::database::Database;
+use rustbase::memory::database::Database;
 use crate::commands::help::{print_database_help};
-use crate::commands::database_functions::{export_database, handle_delete_store, handle_list_stores, handle_move_to_store, handle_new_store, handle_save_database};
+use crate::commands::database_functions::{export_database, handle_delete_store, handle_list_stores,
+                                          handle_move_to_store, handle_new_store, handle_save_database,
+                                          handle_join, handle_foreign_key, handle_drop_foreign_key,
+                                          handle_rename_store, handle_copy_store, handle_describe_store,
+                                          handle_stats, handle_watch, handle_unwatch, handle_trash,
+                                          handle_restore_store, handle_empty_trash};
 
 pub fn run_database_command_loop(mut database: Database) -> io::Result<()> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    let mut watches = HashMap::new();
 
     loop {
         print!("{}> ", database.name);
@@ -17,18 +24,34 @@ pub fn run_database_command_loop(mut database: Database) -> io::Result<()> {
 
         if input.is_empty() { continue; }
 
+        // Sweep expired records, and trash past its retention, between commands
+        database.purge_expired();
+        database.purge_trash();
+
         let parts: Vec<&str> = input.split_whitespace().collect();
         let command = parts[0].to_lowercase();
 
         match command.as_str() {
             "exit" => break,
             "help" => print_database_help(),
-            "save" => handle_save_database(&mut database),
+            "save" => handle_save_database(&mut database, &parts),
             "list_stores" => handle_list_stores(&mut database),
             "new_store" => handle_new_store(&mut database, &parts)?,
             "delete_store" => handle_delete_store(&mut database, &parts)?,
             "store" => handle_move_to_store(&mut database, &parts)?,
             "export_sql" => export_database(&mut database),
+            "join" => handle_join(&mut database, &parts)?,
+            "foreign_key" => handle_foreign_key(&mut database, &parts)?,
+            "drop_foreign_key" => handle_drop_foreign_key(&mut database, &parts)?,
+            "rename_store" => handle_rename_store(&mut database, &parts)?,
+            "copy_store" => handle_copy_store(&mut database, &parts)?,
+            "describe" => handle_describe_store(&mut database, &parts)?,
+            "stats" => handle_stats(&mut database)?,
+            "watch" => handle_watch(&mut database, &mut watches, &parts)?,
+            "unwatch" => handle_unwatch(&mut database, &mut watches, &parts)?,
+            "trash" => handle_trash(&mut database)?,
+            "restore_store" => handle_restore_store(&mut database, &parts)?,
+            "empty_trash" => handle_empty_trash(&mut database)?,
             _ => println!("Unknown command: {}. Type 'help' for a list of commands.", command)
         }
     }
@@ -1,6 +1,12 @@
 use std::io;
-use std::collections::HashSet;
-use crate::memory::database::Database;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use rustbase::memory::database::Database;
+use rustbase::memory::join::JoinKind;
+use rustbase::memory::changes::{ChangeEvent, SubscriptionId};
+use rustbase::memory::storage::SnapshotFormat;
+use rustbase::memory::constraints::ReferentialAction;
+use crate::commands::store_functions::parse_condition;
 use crate::commands::store_commands::run_store_command_loop;
 
 pub fn handle_new_store(database: &mut Database, parts: &[&str]) -> io::Result<()> {
@@ -21,8 +27,12 @@ pub fn handle_delete_store(database: &mut Database, parts: &[&str]) -> io::Resul
         println!("Usage: delete_store <name>");
         return Ok(());
     }
-    database.delete_store(parts[1]);
-    println!("Store '{}' deleted.", parts[1]);
+    let soft = database.get_store(parts[1]).is_some_and(|store| store.soft_deletes());
+    match database.delete_store(parts[1]) {
+        Ok(()) if soft => println!("Store '{}' moved to the trash. Use 'restore_store {}' to bring it back.", parts[1], parts[1]),
+        Ok(()) => println!("Store '{}' deleted.", parts[1]),
+        Err(e) => println!("Failed to delete store '{}': {}", parts[1], e),
+    }
     Ok(())
 }
 
@@ -37,8 +47,31 @@ pub fn handle_list_stores(database: &mut Database) {
     }
 }
 
-pub fn handle_save_database(database: &mut Database) {
-    database.store().unwrap();
+/// Parses a snapshot format name (`json` or `binary`).
+pub fn parse_snapshot_format(text: &str) -> Option<SnapshotFormat> {
+    match text.to_lowercase().as_str() {
+        "json" => Some(SnapshotFormat::Json),
+        "binary" => Some(SnapshotFormat::Binary),
+        _ => None,
+    }
+}
+
+pub fn handle_save_database(database: &mut Database, parts: &[&str]) {
+    let format = match parts.get(1) {
+        Some(text) => match parse_snapshot_format(text) {
+            Some(format) => format,
+            None => {
+                println!("Invalid format: '{}'. Use 'json' or 'binary'.", text);
+                return;
+            }
+        },
+        None => SnapshotFormat::Json,
+    };
+
+    match database.store_as(format) {
+        Ok(()) => println!("Database saved to '{}'.", database.snapshot_path(format).display()),
+        Err(e) => println!("Failed to save database: {}", e),
+    }
 }
 
 pub fn export_database(database: &mut Database) {
@@ -59,12 +92,309 @@ pub fn handle_move_to_store(database: &mut Database, parts: &[&str]) -> io::Resu
         println!("Store '{}' created.", store_name);
     }
 
-    // Get a mutable reference to the store and run the command loop
-    if let Some(store) = database.get_store_mut(store_name) {
-        run_store_command_loop(store)?;
-    } else {
-        println!("Store '{}' not found after creation. This should not happen.", store_name);
+    run_store_command_loop(database, store_name)?;
+
+    Ok(())
+}
+
+/// Handles the "join" command: Joins two stores on attribute equality and displays the merged records.
+pub fn handle_join(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 4 {
+        println!("Usage: join <left_store> <right_store> <left_attribute>=<right_attribute> [inner|left]");
+        println!("Example: join users orders id=user_id left");
+        return Ok(());
+    }
+
+    let (left_attribute, right_attribute) = match parts[3].split_once('=') {
+        Some((left, right)) if !left.is_empty() && !right.is_empty() => (left, right),
+        _ => {
+            println!("Invalid join condition: '{}'. Use '<left_attribute>=<right_attribute>'.", parts[3]);
+            return Ok(());
+        }
+    };
+
+    let kind_str = parts.get(4).map(|kind| kind.to_lowercase()).unwrap_or_else(|| "inner".to_string());
+    let kind = match kind_str.as_str() {
+        "inner" => JoinKind::Inner,
+        "left" => JoinKind::Left,
+        _ => {
+            println!("Invalid join kind: '{}'. Use 'inner' or 'left'.", kind_str);
+            return Ok(());
+        }
+    };
+
+    match database.join(parts[1], parts[2], left_attribute, right_attribute, kind) {
+        Ok(joined) => {
+            let mut records: Vec<_> = joined.get_all_records()?.into_iter().collect();
+            if records.is_empty() {
+                println!("No records produced by the {} join.", kind_str);
+            } else {
+                records.sort_by_key(|(id, _)| *id);
+                println!("Join results ({} join of '{}' and '{}'):", kind_str, parts[1], parts[2]);
+                for (_, record) in records {
+                    let mut record_str: Vec<String> = record
+                        .iter()
+                        .map(|(k, v)| format!("{}: {}", k, v))
+                        .collect();
+                    record_str.sort();
+                    println!("{{{}}}", record_str.join(", "));
+                }
+            }
+        }
+        Err(e) => println!("Join failed: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "foreign_key" command: Declares that a store attribute references another store's record IDs.
+pub fn handle_foreign_key(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 3 {
+        println!("Usage: foreign_key <store>.<attribute> <referenced_store> [restrict|cascade|set_null]");
+        println!("Example: foreign_key orders.user_id users cascade");
+        return Ok(());
     }
 
+    let (store, attribute) = match parts[1].split_once('.') {
+        Some((store, attribute)) if !store.is_empty() && !attribute.is_empty() => (store, attribute),
+        _ => {
+            println!("Invalid attribute: '{}'. Use '<store>.<attribute>'.", parts[1]);
+            return Ok(());
+        }
+    };
+
+    let action_str = parts.get(3).map(|action| action.to_lowercase()).unwrap_or_else(|| "restrict".to_string());
+    let on_delete = match action_str.as_str() {
+        "restrict" => ReferentialAction::Restrict,
+        "cascade" => ReferentialAction::Cascade,
+        "set_null" => ReferentialAction::SetNull,
+        _ => {
+            println!("Invalid referential action: '{}'. Use 'restrict', 'cascade' or 'set_null'.", action_str);
+            return Ok(());
+        }
+    };
+
+    match database.add_foreign_key(store, attribute, parts[2], on_delete) {
+        Ok(()) => println!("Foreign key {}.{} -> {} ({}) added.", store, attribute, parts[2], action_str),
+        Err(e) => println!("Failed to add foreign key: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "drop_foreign_key" command: Removes the foreign key declared on a store attribute.
+pub fn handle_drop_foreign_key(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    let target = parts.get(1).and_then(|target| target.split_once('.'));
+    let Some((store, attribute)) = target else {
+        println!("Usage: drop_foreign_key <store>.<attribute>");
+        return Ok(());
+    };
+
+    match database.drop_foreign_key(store, attribute) {
+        Ok(()) => println!("Foreign key on {}.{} dropped.", store, attribute),
+        Err(e) => println!("Failed to drop foreign key: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "rename_store" command: Renames a store and updates the foreign keys referencing it.
+pub fn handle_rename_store(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 3 {
+        println!("Usage: rename_store <name> <new_name>");
+        return Ok(());
+    }
+
+    match database.rename_store(parts[1], parts[2]) {
+        Ok(()) => println!("Store '{}' renamed to '{}'.", parts[1], parts[2]),
+        Err(e) => println!("Failed to rename store: {}", e),
+    }
+    Ok(())
+}
+
+/// Handles the "copy_store" command: Copies a store, optionally only the records matching a condition.
+pub fn handle_copy_store(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 3 {
+        println!("Usage: copy_store <source> <destination> [where <attribute> <operator> <value>]");
+        println!("Example: copy_store users adults where age ge 18");
+        return Ok(());
+    }
+
+    let condition = match parts.get(3) {
+        None => None,
+        Some(keyword) if keyword.eq_ignore_ascii_case("where") => match parse_condition(&parts[4..]) {
+            Some(condition) => Some(condition),
+            None => return Ok(()),
+        },
+        Some(_) => {
+            println!("Unexpected input: '{}'. Use 'where <attribute> <operator> <value>'.", parts[3..].join(" "));
+            return Ok(());
+        }
+    };
+
+    match database.copy_store(parts[1], parts[2], condition) {
+        Ok(count) => println!("Copied {} record(s) from '{}' to '{}'.", count, parts[1], parts[2]),
+        Err(e) => println!("Failed to copy store: {}", e),
+    }
+    Ok(())
+}
+
+/// Formats a byte count with a binary unit (B, KiB, MiB, GiB).
+fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", size, units[unit]) }
+}
+
+/// Handles the "describe" command: Shows the observed types, fill rate, distinct count and range of each attribute.
+pub fn handle_describe_store(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: describe <store>");
+        return Ok(());
+    }
+
+    let Some(store) = database.get_store(parts[1]) else {
+        println!("Store '{}' not found.", parts[1]);
+        return Ok(());
+    };
+
+    let total = store.record_count();
+    println!("Store '{}': {} record(s), {} attribute(s)", parts[1], total, store.attribute_count());
+    println!("{:<20} {:<28} {:<16} {:<9} {:<16} max", "attribute", "types", "fill", "distinct", "min");
+    for summary in store.describe() {
+        let types: Vec<String> = summary.types.iter().map(|(name, count)| format!("{}({})", name, count)).collect();
+        let percent = if total == 0 { 0.0 } else { summary.present as f64 * 100.0 / total as f64 };
+        let show = |value: &Option<serde_json::Value>| value.as_ref().map_or("-".to_string(), |v| v.to_string());
+        println!(
+            "{:<20} {:<28} {:<16} {:<9} {:<16} {}",
+            summary.name,
+            if types.is_empty() { "-".to_string() } else { types.join(", ") },
+            format!("{}/{} ({:.0}%)", summary.present, total, percent),
+            summary.distinct,
+            show(&summary.min),
+            show(&summary.max),
+        );
+    }
+    for trigger in store.triggers() {
+        let events: Vec<&str> = trigger.events.iter().map(|event| event.name()).collect();
+        println!("Trigger '{}': {:?} {} -> {:?}", trigger.name, trigger.timing, events.join(","), trigger.action);
+    }
+    Ok(())
+}
+
+/// Handles the "stats" command: Shows record counts, approximate memory and snapshot sizes.
+pub fn handle_stats(database: &mut Database) -> io::Result<()> {
+    let stats = match database.stats() {
+        Ok(stats) => stats,
+        Err(e) => {
+            println!("Failed to compute statistics: {}", e);
+            return Ok(());
+        }
+    };
+
+    println!("Database '{}': {} store(s)", database.name, stats.stores.len());
+    println!("{:<20} {:>10} {:>11} {:>14} {:>14}", "store", "records", "attributes", "memory (~)", "snapshot");
+    for store in &stats.stores {
+        println!(
+            "{:<20} {:>10} {:>11} {:>14} {:>14}",
+            store.name, store.records, store.attributes,
+            format_bytes(store.memory_bytes as u64), format_bytes(store.snapshot_bytes as u64),
+        );
+    }
+    let records: usize = stats.stores.iter().map(|store| store.records).sum();
+    let memory: usize = stats.stores.iter().map(|store| store.memory_bytes).sum();
+    println!("Total: {} record(s), ~{} in memory", records, format_bytes(memory as u64));
+
+    if stats.snapshot_files.is_empty() {
+        println!("Not saved yet.");
+    }
+    for (path, size) in &stats.snapshot_files {
+        println!("On disk: {} ({})", path, format_bytes(*size));
+    }
+    Ok(())
+}
+
+/// Formats a change event as one line, with the record's attributes sorted.
+fn format_change(event: &ChangeEvent) -> String {
+    let format_record = |record: &Option<HashMap<String, Value>>| match record {
+        Some(record) => Value::Object(record.clone().into_iter().collect()).to_string(),
+        None => "-".to_string(),
+    };
+    match event.id {
+        Some(id) => format!(
+            "[{} #{}] {} {}: {} -> {}",
+            event.store, event.sequence, event.kind.name(), id, format_record(&event.old), format_record(&event.new)
+        ),
+        None => format!("[{} #{}] {}", event.store, event.sequence, event.kind.name()),
+    }
+}
+
+/// Handles the "watch" command: Prints every later change to a store, or to all stores, as it is made.
+pub fn handle_watch(database: &mut Database, watches: &mut HashMap<String, SubscriptionId>, parts: &[&str]) -> io::Result<()> {
+    let store = parts.get(1).copied();
+    let key = store.unwrap_or("*").to_string();
+    if watches.contains_key(&key) {
+        println!("Already watching {}.", store.map_or("all stores".to_string(), |store| format!("'{}'", store)));
+        return Ok(());
+    }
+    if let Some(store) = store.filter(|store| database.get_store(store).is_none()) {
+        println!("Store '{}' not found; it will be watched once created.", store);
+    }
+
+    let id = database.on_change(store, |event| println!("{}", format_change(event)));
+    watches.insert(key, id);
+    println!("Watching {}. Use 'unwatch' to stop.", store.map_or("all stores".to_string(), |store| format!("'{}'", store)));
+    Ok(())
+}
+
+/// Handles the "unwatch" command: Stops printing the changes of a store, or of all stores.
+pub fn handle_unwatch(database: &mut Database, watches: &mut HashMap<String, SubscriptionId>, parts: &[&str]) -> io::Result<()> {
+    let key = parts.get(1).copied().unwrap_or("*");
+    match watches.remove(key) {
+        Some(id) => {
+            database.unsubscribe(id);
+            println!("Stopped watching {}.", if key == "*" { "all stores".to_string() } else { format!("'{}'", key) });
+        }
+        None => println!("Not watching {}.", if key == "*" { "all stores".to_string() } else { format!("'{}'", key) }),
+    }
+    Ok(())
+}
+
+/// Handles the "trash" command: Lists the dropped stores in the trash.
+pub fn handle_trash(database: &mut Database) -> io::Result<()> {
+    let trashed = database.trashed_stores();
+    if trashed.is_empty() {
+        println!("No stores in the trash.");
+        return Ok(());
+    }
+    println!("Stores in the trash:");
+    for (name, trashed) in trashed {
+        println!("- {} ({} record(s), dropped at {})", name, trashed.store.record_count(), trashed.deleted_at);
+    }
+    Ok(())
+}
+
+/// Handles the "restore_store" command: Brings a dropped store back from the trash.
+pub fn handle_restore_store(database: &mut Database, parts: &[&str]) -> io::Result<()> {
+    let Some(name) = parts.get(1) else {
+        println!("Usage: restore_store <name>");
+        return Ok(());
+    };
+    match database.restore_store(name) {
+        Ok(()) => println!("Store '{}' restored.", name),
+        Err(e) => println!("Failed to restore store '{}': {}", name, e),
+    }
+    Ok(())
+}
+
+/// Handles the "empty_trash" command: Permanently removes every trashed store and record.
+pub fn handle_empty_trash(database: &mut Database) -> io::Result<()> {
+    let removed = database.empty_trash();
+    println!("Trash emptied: {} store(s) and record(s) removed for good.", removed);
     Ok(())
 }
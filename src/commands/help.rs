@@ -3,6 +3,13 @@ pub fn print_help() {
     println!("  exit                - exit the program");
     println!("  help                - display this help menu");
     println!("  database <name>     - switch to a database or load it");
+    println!("  convert <name> <json|binary> - rewrite the snapshot of a database in another format, replacing it");
+    println!("  list_databases      - list the databases saved in the data directory");
+    println!("  drop_database <name> - delete every snapshot of a database");
+    println!("  rename_database <name> <new_name> - rename a saved database");
+    println!();
+    println!("The data directory is set with --data-dir <dir>, the RUSTBASE_DATA_DIR environment variable");
+    println!("or a 'data_dir = <dir>' line in ./rustbase.conf or ~/.rustbase.conf (default: the working directory).");
 }
 
 pub fn print_database_help() {
@@ -10,18 +17,36 @@ pub fn print_database_help() {
     println!("  help                            - Show this help message");
     println!("  exit                            - Exit this level");
     println!("  new_store <name> <attributes>   - Create a new store (attributes comma-separated)");
-    println!("  delete_store <name>             - Delete a store");
+    println!("  delete_store <name>             - Delete a store (a store with soft deletes goes to the trash)");
+    println!("  trash                           - List the dropped stores in the trash");
+    println!("  restore_store <name>            - Bring a dropped store back from the trash");
+    println!("  empty_trash                     - Permanently remove every trashed store and record");
+    println!("  rename_store <name> <new_name>  - Rename a store and the foreign keys referencing it");
+    println!("  copy_store <src> <dst> [where <attr> <op> <value>] - Copy a store, optionally only the matching records");
     println!("  list_stores                     - List all stores");
-    println!("  save                            - Save the database to a JSON file");
+    println!("  describe <store>                - Show each attribute's observed types, fill rate, distinct count and min/max");
+    println!("  stats                           - Show record counts, approximate memory and snapshot size per store");
+    println!("  watch [store]                   - Print every later change to a store (or all stores) as it is made");
+    println!("  unwatch [store]                 - Stop printing the changes of a store (or all stores)");
+    println!("  save [json|binary]              - Save the database to a JSON (default) or binary snapshot");
     println!("  export_sql                      - Export the database to a SQL script");
     println!("  store <name>                    - Change to a store or it is created automatically if it does not exist");
+    println!("  foreign_key <store>.<attr> <ref> [restrict|cascade|set_null] - Make an attribute reference another store's record IDs");
+    println!("  drop_foreign_key <store>.<attr> - Remove the foreign key declared on an attribute");
+    println!("  join <left> <right> <l>=<r> [inner|left] - Join two stores on attribute equality (Ex: join users orders id=user_id left)");
 }
 
 pub fn print_store_help() {
     println!("Available store commands:");
     println!(" help                                     - Show this help message");
     println!(" exit                                     - Exit this level");
-    println!(" new_record <record_map>                  - Create a new record (record map comma-separated Ex: name:John Doe, age: 30) ");
+    println!(" new_record [ttl=<duration>] <record_map> - Create a new record (record map comma-separated Ex: name:John Doe, age: 30) ");
+    println!("                                            Values may be quoted (name:\"Doe, John\"), typed (zip:str=01234) or JSON (tags:[1,2]);");
+    println!("                                            a JSON object is also accepted (Ex: new_record {{\"name\": \"John\"}})");
+    println!(" update_record <record_id> [version=<n>] <record_map> - Replace a record, only if still at version n when given");
+    println!(" insert_at <record_id|key> <record_map>   - Create a new record at a chosen ID or key; later records get IDs past it");
+    println!(" upsert <record_id|key=<key>|by=<attribute>> <record_map> - Replace the record with this ID or key, or holding the");
+    println!("                                            record's value in this attribute, or create it if there is none");
     println!(" delete_record <record_id>                - Delete a record using it's id");
     println!(" list_records                             - List all records");
     println!(" get_record <record_id>                   - Get a particular record using it's id");
@@ -30,4 +55,28 @@ pub fn print_store_help() {
     println!(" operators                                - Display the operators of any query");
     println!(" query <attribute> <operator> <values>    - Query records from the store using a particular operator");
     println!(" sort <attribute> <asc/desc>              - Sort the different record in ascending or descending order");
+    println!(" aggregate <function> [attribute] [by <attributes>] [where <attribute> <operator> <value>]");
+    println!("                                          - Compute count, sum, avg, min, max or count_distinct (Ex: aggregate count by city where age gt 25)");
+    println!(" import_csv <file> [delimiter]            - Append the rows of a CSV file with a header row (types inferred per column)");
+    println!(" export_csv <file> [options]              - Write the store as CSV (options: delimiter=; quote=always columns=a,b id=true)");
+    println!(" import_ndjson <file>                     - Stream a JSON Lines file (one object per line) into the store");
+    println!(" export_ndjson <file>                     - Stream the store's records to a JSON Lines file");
+    println!(" rename_attribute <attribute> <new_name>  - Rename an attribute in every record");
+    println!(" drop_attribute <attribute>               - Remove an attribute from every record");
+    println!(" infer_schema                             - Infer types, nullability and candidate unique keys, and show type conflicts");
+    println!(" lock_schema [convert|reject]             - Lock the inferred schema in, converting or deleting outliers first");
+    println!(" unlock_schema                            - Remove the locked schema");
+    println!(" set_ttl <duration|none>                  - Make new records expire after a duration (Ex: 90, 30s, 15m, 2h, 1d)");
+    println!(" ttl <record_id>                          - Show how long a record has left before it expires");
+    println!(" versioning <on|off>                      - Track creation time, update time and version of each record");
+    println!(" keep_history <on|off>                    - Keep prior versions of records when they are updated or deleted");
+    println!(" history <record_id>                      - List every known version of a record");
+    println!(" as_of <record_id> <millis|version=<n>>   - Show a record as it was at a Unix time in milliseconds or a version");
+    println!(" restore <record_id> <version>            - Bring a record back to a prior version, as a new version");
+    println!(" soft_delete <on|off> [retention]         - Move deleted records to the trash, purged after the retention if given");
+    println!(" trash                                    - List the deleted records in the trash");
+    println!(" restore_record <record_id>               - Bring a deleted record back from the trash");
+    println!(" empty_trash                              - Permanently remove the deleted records in the trash");
+    println!(" id_strategy <counter|uuid [attr]|ulid [attr]|key <attr>> - Identify records by counter, generated UUID/ULID or a natural key");
+    println!("                                            record commands then take the key wherever they take a <record_id>");
 }
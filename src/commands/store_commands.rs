@@ -1,17 +1,38 @@
 use std::io;
 use std::io::{BufRead, Write};
-use crate::memory::structure::Store;
+use rustbase::memory::structure::Store;
+use rustbase::memory::database::Database;
 use crate::commands::help::print_store_help;
 use crate::commands::store_functions::{handle_delete_record, handle_filter, handle_filters,
                                        handle_get_record, handle_list_records, handle_new_record,
-                                       handle_print_operators, handle_query, handle_sort};
+                                       handle_print_operators, handle_query, handle_sort,
+                                       handle_aggregate, handle_import_csv, handle_export_csv,
+                                       handle_import_ndjson, handle_export_ndjson, handle_rename_attribute,
+                                       handle_drop_attribute, handle_infer_schema, handle_lock_schema,
+                                       handle_unlock_schema, handle_set_ttl, handle_ttl,
+                                       handle_update_record, handle_versioning, handle_keep_history,
+                                       handle_history, handle_as_of, handle_restore, handle_soft_delete,
+                                       handle_trash, handle_restore_record, handle_empty_trash,
+                                       handle_id_strategy, handle_insert_at, handle_upsert};
 
-pub fn run_store_command_loop(store: &mut Store) -> io::Result<()> {
+/// Looks up the store the command loop is working on, for reading.
+fn store_ref<'a>(database: &'a Database, store_name: &str) -> io::Result<&'a Store> {
+    database.get_store(store_name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store_name)))
+}
+
+/// Looks up the store the command loop is working on, for writing.
+fn store_mut<'a>(database: &'a mut Database, store_name: &str) -> io::Result<&'a mut Store> {
+    database.get_store_mut(store_name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store_name)))
+}
+
+pub fn run_store_command_loop(database: &mut Database, store_name: &str) -> io::Result<()> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
     loop {
-        print!("{}> ", store.name);
+        print!("{}> ", store_name);
         stdout.flush()?;
         let mut input = String::new();
         stdin.lock().read_line(&mut input)?;
@@ -19,21 +40,50 @@ pub fn run_store_command_loop(store: &mut Store) -> io::Result<()> {
 
         if input.is_empty() { continue; }
 
+        // Sweep expired records, and trash past its retention, between commands
+        database.purge_expired();
+        database.purge_trash();
+
         let parts: Vec<&str> = input.split_whitespace().collect();
         let command = parts[0].to_lowercase();
 
         match command.as_str() {
             "exit" => break,
             "help" => print_store_help(),
-            "new_record" => handle_new_record(store, &parts)?,
-            "delete_record" => handle_delete_record(store, &parts)?,
-            "list_records" => handle_list_records(store)?,
-            "get_record" => handle_get_record(store, &parts)?,
-            "filter" => handle_filter(store, &parts)?,
-            "filters" => handle_filters(store, &parts)?,
+            "new_record" => handle_new_record(database, store_name, input[parts[0].len()..].trim())?,
+            "update_record" => handle_update_record(database, store_name, input[parts[0].len()..].trim())?,
+            "insert_at" => handle_insert_at(database, store_name, input[parts[0].len()..].trim())?,
+            "upsert" => handle_upsert(database, store_name, input[parts[0].len()..].trim())?,
+            "delete_record" => handle_delete_record(database, store_name, &parts)?,
+            "list_records" => handle_list_records(store_ref(database, store_name)?)?,
+            "get_record" => handle_get_record(store_ref(database, store_name)?, &parts)?,
+            "filter" => handle_filter(store_ref(database, store_name)?, &parts)?,
+            "filters" => handle_filters(store_ref(database, store_name)?, &parts)?,
             "operators" => handle_print_operators(),
-            "query" => handle_query(store, &parts)?,
-            "sort" => handle_sort(store, &parts)?,
+            "query" => handle_query(store_ref(database, store_name)?, &parts)?,
+            "sort" => handle_sort(store_ref(database, store_name)?, &parts)?,
+            "aggregate" => handle_aggregate(store_ref(database, store_name)?, &parts)?,
+            "import_csv" => handle_import_csv(database, store_name, &parts)?,
+            "export_csv" => handle_export_csv(store_ref(database, store_name)?, &parts)?,
+            "import_ndjson" => handle_import_ndjson(database, store_name, &parts)?,
+            "export_ndjson" => handle_export_ndjson(store_ref(database, store_name)?, &parts)?,
+            "rename_attribute" => handle_rename_attribute(database, store_name, &parts)?,
+            "drop_attribute" => handle_drop_attribute(database, store_name, &parts)?,
+            "infer_schema" => handle_infer_schema(store_ref(database, store_name)?)?,
+            "lock_schema" => handle_lock_schema(database, store_name, &parts)?,
+            "unlock_schema" => handle_unlock_schema(store_mut(database, store_name)?)?,
+            "set_ttl" => handle_set_ttl(store_mut(database, store_name)?, &parts)?,
+            "ttl" => handle_ttl(store_ref(database, store_name)?, &parts)?,
+            "versioning" => handle_versioning(store_mut(database, store_name)?, &parts)?,
+            "keep_history" => handle_keep_history(store_mut(database, store_name)?, &parts)?,
+            "history" => handle_history(store_ref(database, store_name)?, &parts)?,
+            "as_of" => handle_as_of(store_ref(database, store_name)?, &parts)?,
+            "restore" => handle_restore(database, store_name, &parts)?,
+            "soft_delete" => handle_soft_delete(store_mut(database, store_name)?, &parts)?,
+            "trash" => handle_trash(store_ref(database, store_name)?)?,
+            "restore_record" => handle_restore_record(database, store_name, &parts)?,
+            "empty_trash" => handle_empty_trash(store_mut(database, store_name)?)?,
+            "id_strategy" => handle_id_strategy(database, store_name, &parts)?,
             _ => println!("Unknown command: {}. Type 'help' for a list of commands.", command)
         }
     }
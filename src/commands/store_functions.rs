@@ -1,77 +1,282 @@
 use std::io;
-use serde_json::{json, Value};
-use crate::memory::structure::Store;
+use std::time::Duration;
 use std::collections::{HashMap, HashSet};
-use crate::memory::complex::QueryOperator;
+use serde_json::{json, Value};
+use rustbase::memory::structure::Store;
+use rustbase::memory::database::Database;
+use rustbase::memory::record_parser::{infer_value, parse_record};
+use rustbase::memory::csv::{CsvOptions, QuoteStyle};
+use rustbase::memory::complex::{AggregateFunction, QueryOperator};
+use rustbase::memory::schema::{FieldType, OutlierPolicy, SchemaInference};
+use rustbase::memory::ids::{key_of, IdStrategy};
+use rustbase::memory::upsert::{UpsertKey, Upserted};
+use rustbase::memory::triggers::AfterTriggerError;
 
-pub fn handle_new_record(store: &mut Store, parts: &[&str]) -> io::Result<()> {
-    if parts.len() < 2 {
-        println!("Usage: new_record <attribute:value,...>");
-        println!("Example: new_record name:John Doe, age:30");
-        return Ok(());
+/// Parses a duration written as seconds or with an `ms`, `s`, `m`, `h` or `d` suffix (e.g. `90`, `15m`).
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let amount: u64 = text[..split].parse().ok()?;
+    let unit_millis = match &text[split..] {
+        "ms" => 1,
+        "" | "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => return None,
+    };
+    amount.checked_mul(unit_millis).map(Duration::from_millis)
+}
+
+/// Prints the error of a mutation, telling a mutation that was made but whose `After` triggers failed
+/// from one that was not made at all.
+fn print_mutation_error(failure: &str, e: &io::Error) {
+    match AfterTriggerError::of(e) {
+        Some(after) => println!("Warning: {}.", after),
+        None => println!("{}: {}", failure, e),
+    }
+}
+
+/// Handles the "new_record" command: Parses `attr:value` pairs or a JSON object and adds the record.
+///
+/// `input` is the raw text following the command, so quoted values keep their spacing. A leading
+/// `ttl=<duration>` gives the record a time-to-live.
+pub fn handle_new_record(database: &mut Database, store_name: &str, input: &str) -> io::Result<()> {
+    let mut input = input;
+    let mut ttl = None;
+    if let Some(rest) = input.strip_prefix("ttl=") {
+        let (duration, record) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        match parse_duration(duration) {
+            Some(duration) => ttl = Some(duration),
+            None => {
+                println!("Invalid TTL: '{}'. Use seconds or a duration like 30s, 15m, 2h or 1d.", duration);
+                return Ok(());
+            }
+        }
+        input = record.trim();
     }
 
-    // Parse the record map from the input (parts[1] onwards)
-    let record_input = parts[1..].join(" ");
-    let record_pairs: Vec<&str> = record_input.split(',').map(|s| s.trim()).collect();
+    if input.is_empty() {
+        println!("Usage: new_record [ttl=<duration>] <attribute:value,...> | new_record [ttl=<duration>] <json_object>");
+        println!("Example: new_record name:\"Doe, John\", age:30, zip:str=01234, tags:[\"a\",\"b\"]");
+        return Ok(());
+    }
 
-    let mut record: HashMap<String, Value> = HashMap::new();
-    for pair in record_pairs {
-        let parts: Vec<&str> = pair.splitn(2, ':').collect();
-        if parts.len() != 2 {
-            println!("Invalid record format: '{}'. Use 'attribute:value'.", pair);
+    let record = match parse_record(input) {
+        Ok(record) => record,
+        Err(e) => {
+            println!("Invalid record: {}.", e);
             return Ok(());
         }
+    };
 
-        let key = parts[0].trim().to_string();
-        let value_str = parts[1].trim();
+    // Add the record to the store, checking its foreign keys
+    match database.add_record_with_ttl(store_name, record, ttl) {
+        Ok(id) => println!("Record added with ID: {}", id),
+        Err(e) => print_mutation_error("Failed to add record", &e),
+    }
 
-        // Attempt to infer the value type (string, number, boolean)
-        let value = if let Ok(num) = value_str.parse::<i64>() {
-            json!(num)
-        } else if let Ok(float) = value_str.parse::<f64>() {
-            json!(float)
-        } else if value_str.to_lowercase() == "true" || value_str.to_lowercase() == "false" {
-            json!(value_str.to_lowercase() == "true")
-        } else {
-            json!(value_str) // Default to string
-        };
+    Ok(())
+}
+
+/// Handles the "update_record" command: Replaces a record, optionally only if it is still at a given version.
+pub fn handle_update_record(database: &mut Database, store_name: &str, input: &str) -> io::Result<()> {
+    let usage = "Usage: update_record <record_id> [version=<n>] <attribute:value,...> | update_record <record_id> [version=<n>] <json_object>";
+    let (id, mut input) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    if id.is_empty() {
+        println!("{}", usage);
+        return Ok(());
+    }
+    let Some(record_id) = database.get_store(store_name).and_then(|store| resolve_record_id(store, id)) else { return Ok(()) };
 
-        record.insert(key, value);
+    let mut expected_version = None;
+    if let Some(rest) = input.trim_start().strip_prefix("version=") {
+        let (version, record) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        match version.parse::<u64>() {
+            Ok(version) => expected_version = Some(version),
+            Err(_) => {
+                println!("Invalid version: '{}'. Must be a positive integer.", version);
+                return Ok(());
+            }
+        }
+        input = record;
+    }
+    let input = input.trim();
+    if input.is_empty() {
+        println!("{}", usage);
+        return Ok(());
     }
 
-    // Add the record to the store
-    match store.add_record(record) {
-        Ok(id) => println!("Record added with ID: {}", id),
-        Err(e) => println!("Failed to add record: {}", e),
+    let record = match parse_record(input) {
+        Ok(record) => record,
+        Err(e) => {
+            println!("Invalid record: {}.", e);
+            return Ok(());
+        }
+    };
+
+    let result = match expected_version {
+        Some(version) => database.update_record_if_version(store_name, record_id, version, record).map(Some),
+        None => database.update_record(store_name, record_id, record).map(|()| None),
+    };
+    match result {
+        Ok(Some(version)) => println!("Record {} updated to version {}.", record_id, version),
+        Ok(None) => println!("Record {} updated successfully.", record_id),
+        Err(e) => print_mutation_error(&format!("Failed to update record {}", record_id), &e),
     }
 
     Ok(())
 }
 
-pub fn handle_delete_record(store: &mut Store, parts: &[&str]) -> io::Result<()> {
-    if parts.len() < 2 {
-        println!("Usage: delete_record <record_id>");
+/// Handles the "insert_at" command: Adds a record at the given ID, or under the given key when the
+/// store identifies its records by a key.
+pub fn handle_insert_at(database: &mut Database, store_name: &str, input: &str) -> io::Result<()> {
+    let usage = "Usage: insert_at <record_id|key> <attribute:value,...> | insert_at <record_id|key> <json_object>";
+    let (id, input) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    if id.is_empty() || input.trim().is_empty() {
+        println!("{}", usage);
+        return Ok(());
+    }
+    let Some(store) = database.get_store(store_name) else { return Ok(()) };
+    let key_attribute = store.id_strategy().key_attribute().map(str::to_string);
+
+    let mut record = match parse_record(input.trim()) {
+        Ok(record) => record,
+        Err(e) => {
+            println!("Invalid record: {}.", e);
+            return Ok(());
+        }
+    };
+
+    let result = match key_attribute {
+        Some(attribute) => {
+            if !carry_key(&mut record, &attribute, id) {
+                return Ok(());
+            }
+            database.add_record(store_name, record)
+        }
+        None => match id.parse::<i64>() {
+            Ok(record_id) => database.insert_record_at(store_name, record_id, record).map(|()| record_id),
+            Err(_) => {
+                println!("{}", usage);
+                return Ok(());
+            }
+        },
+    };
+    match result {
+        Ok(record_id) => println!("Record added with ID: {}", record_id),
+        Err(e) => print_mutation_error("Failed to add record", &e),
+    }
+    Ok(())
+}
+
+/// Gives a record the key typed on the command line, unless it brings the same one.
+///
+/// Keys are compared as `key_of` gives them, as the text typed or as the value it reads as, so a
+/// record bringing `id:1.0` matches the key `1`.
+///
+/// # Returns
+/// `false`, after saying so, if the record brings a different key.
+fn carry_key(record: &mut HashMap<String, Value>, attribute: &str, key: &str) -> bool {
+    match record.get(attribute).map(key_of) {
+        None => {
+            record.insert(attribute.to_string(), json!(key));
+            true
+        }
+        Some(Some(carried)) if carried == key || Some(&carried) == key_of(&infer_value(key)).as_ref() => true,
+        Some(_) => {
+            println!("The record's {} {} does not match the key '{}'.", attribute, record[attribute], key);
+            false
+        }
+    }
+}
+
+/// Handles the "upsert" command: Replaces the record found by ID, key or an attribute's value, or adds it.
+///
+/// `by=<attribute>` finds the record holding the record's value in the attribute and `key=<key>` the
+/// record with this key; a bare argument is the record's ID, or its key when the store identifies its
+/// records by a key.
+pub fn handle_upsert(database: &mut Database, store_name: &str, input: &str) -> io::Result<()> {
+    let usage = "Usage: upsert <record_id|key=<key>|by=<attribute>> <attribute:value,...> | upsert <record_id|key=<key>|by=<attribute>> <json_object>";
+    let (text, input) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    if text.is_empty() || input.trim().is_empty() {
+        println!("{}", usage);
+        println!("Example: upsert by=email email:ann@example.com, name:Ann");
         return Ok(());
     }
+    let Some(store) = database.get_store(store_name) else { return Ok(()) };
 
-    let record_id: i64 = match parts[1].parse() {
-        Ok(id) => id,
-        Err(_) => {
-            println!("Invalid record ID: '{}'. Must be an integer.", parts[1]);
+    let mut record = match parse_record(input.trim()) {
+        Ok(record) => record,
+        Err(e) => {
+            println!("Invalid record: {}.", e);
             return Ok(());
         }
     };
 
-    match store.delete_record(record_id) {
+    let key_attribute = store.id_strategy().key_attribute();
+    let key = match (text.strip_prefix("by="), text.strip_prefix("key=").unwrap_or(text), key_attribute) {
+        (Some(attribute), _, _) if !attribute.is_empty() => UpsertKey::Attribute(attribute.to_string()),
+        (Some(_), _, _) => {
+            println!("{}", usage);
+            return Ok(());
+        }
+        (None, key, Some(attribute)) => {
+            if !carry_key(&mut record, attribute, key) {
+                return Ok(());
+            }
+            UpsertKey::Attribute(attribute.to_string())
+        }
+        (None, _, None) if text.starts_with("key=") => {
+            println!("Store '{}' identifies its records by ID, not by a key.", store_name);
+            return Ok(());
+        }
+        (None, id, None) => match id.parse::<i64>() {
+            Ok(id) => UpsertKey::Id(id),
+            Err(_) => {
+                println!("Invalid record ID: '{}'. Must be an integer, or use by=<attribute>.", id);
+                return Ok(());
+            }
+        },
+    };
+
+    match database.upsert_record(store_name, &key, record) {
+        Ok(Upserted::Inserted(id)) => println!("Record added with ID: {}", id),
+        Ok(Upserted::Updated(id)) => println!("Record {} updated successfully.", id),
+        Err(e) => print_mutation_error("Failed to upsert record", &e),
+    }
+    Ok(())
+}
+
+/// Resolves a record ID typed on the command line, or the record's key under a key strategy, see `Store::record_id`.
+fn resolve_record_id(store: &Store, text: &str) -> Option<i64> {
+    let id = store.record_id(text);
+    if id.is_none() {
+        match store.id_strategy().key_attribute() {
+            None => println!("Invalid record ID: '{}'. Must be an integer.", text),
+            Some(attribute) => println!("No record has {} '{}'.", attribute, text),
+        }
+    }
+    id
+}
+
+pub fn handle_delete_record(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: delete_record <record_id>");
+        return Ok(());
+    }
+
+    let Some(record_id) = database.get_store(store_name).and_then(|store| resolve_record_id(store, parts[1])) else { return Ok(()) };
+
+    match database.delete_record(store_name, record_id) {
         Ok(()) => println!("Record {} deleted successfully.", record_id),
-        Err(e) => println!("Failed to delete record {}: {}", record_id, e),
+        Err(e) => print_mutation_error(&format!("Failed to delete record {}", record_id), &e),
     }
 
     Ok(())
 }
 
-pub fn handle_list_records(store: &mut Store) -> io::Result<()> {
+pub fn handle_list_records(store: &Store) -> io::Result<()> {
     let records = store.get_all_records()?;
 
     if records.is_empty() {
@@ -92,19 +297,13 @@ pub fn handle_list_records(store: &mut Store) -> io::Result<()> {
 }
 
 /// Retrieves and displays a specific record by its ID.
-pub fn handle_get_record(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+pub fn handle_get_record(store: &Store, parts: &[&str]) -> io::Result<()> {
     if parts.len() < 2 {
         println!("Usage: get_record <record_id>");
         return Ok(());
     }
 
-    let record_id: i64 = match parts[1].parse() {
-        Ok(id) => id,
-        Err(_) => {
-            println!("Invalid record ID: '{}'. Must be an integer.", parts[1]);
-            return Ok(());
-        }
-    };
+    let Some(record_id) = resolve_record_id(store, parts[1]) else { return Ok(()) };
 
     match store.get_record(record_id) {
         Ok(record) => {
@@ -113,6 +312,9 @@ pub fn handle_get_record(store: &mut Store, parts: &[&str]) -> io::Result<()> {
                 .map(|(k, v)| format!("{}: {}", k, v))
                 .collect();
             println!("Record {}: {{{}}}", record_id, record_str.join(", "));
+            if let Some(metadata) = store.metadata(record_id) {
+                println!("  version {}, created at {}, updated at {}", metadata.version, metadata.created_at, metadata.updated_at);
+            }
         }
         Err(e) => println!("Failed to get record {}: {}", record_id, e),
     }
@@ -120,15 +322,34 @@ pub fn handle_get_record(store: &mut Store, parts: &[&str]) -> io::Result<()> {
     Ok(())
 }
 
+/// Handles the "operators" command: Displays available query operators.
 pub fn handle_print_operators() {
-    use QueryOperator::*;
-
-    let operators = [Eq, Neq, Gt, Lt, Ge, Le, Contains];
-    println!("{:#?}", operators);
+    println!("Available query operators:");
+    println!("  eq          - Equal to");
+    println!("  neq         - Not equal to");
+    println!("  gt          - Greater than");
+    println!("  lt          - Less than");
+    println!("  ge          - Greater than or equal to");
+    println!("  le          - Less than or equal to");
+    println!("  contains    - Checks if a string contains a substring");
+    println!("  icontains   - Checks if a string contains a substring, ignoring case");
+    println!("  starts_with - Checks if a string starts with a prefix");
+    println!("  ends_with   - Checks if a string ends with a suffix");
+    println!("  like        - Matches a SQL LIKE pattern (% any sequence, _ any character)");
+    println!("  regex       - Matches a regular expression");
+    println!("  in          - Equal to one of comma-separated values (Ex: city in Paris,London)");
+    println!("  not_in      - Equal to none of comma-separated values");
+    println!("  between     - Between two comma-separated bounds, inclusive (Ex: age between 18,30)");
+    println!("  exists      - The attribute is present (no value)");
+    println!("  not_exists  - The attribute is absent (no value)");
+    println!("  is_null     - The attribute is null (no value)");
+    println!("  any_of      - An array attribute holds one of comma-separated values (Ex: tags any_of red,blue)");
+    println!("  contains_element - An array attribute holds the value (Ex: tags contains_element red)");
+    println!("Attributes may be paths into nested values: address.city, tags[0] or /meta/score");
 }
 
 /// Handles the "filter" command: Filters records by a single attribute and value.
-pub fn handle_filter(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+pub fn handle_filter(store: &Store, parts: &[&str]) -> io::Result<()> {
     if parts.len() < 3 {
         println!("Usage: filter <attribute> <value>");
         println!("Example: filter name John");
@@ -160,7 +381,7 @@ pub fn handle_filter(store: &mut Store, parts: &[&str]) -> io::Result<()> {
 }
 
 /// Handles the "filters" command: Filters records by multiple attributes and values.
-pub fn handle_filters(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+pub fn handle_filters(store: &Store, parts: &[&str]) -> io::Result<()> {
     if parts.len() < 3 {
         println!("Usage: filters <attributes> <values>");
         println!("Example: filters name,age John,30");
@@ -196,22 +417,97 @@ pub fn handle_filters(store: &mut Store, parts: &[&str]) -> io::Result<()> {
     Ok(())
 }
 
-/// Handles the "operators" command: Displays available query operators.
-pub fn handle_operators() -> io::Result<()> {
-    println!("Available query operators:");
-    println!("  eq       - Equal to");
-    println!("  neq      - Not equal to");
-    println!("  gt       - Greater than");
-    println!("  lt       - Less than");
-    println!("  ge       - Greater than or equal to");
-    println!("  le       - Less than or equal to");
-    println!("  contains - Checks if a string contains a substring");
-    Ok(())
+/// Parses a query operator name such as `eq` or `gt`.
+fn parse_operator(operator_str: &str) -> Option<QueryOperator> {
+    match operator_str {
+        "eq" => Some(QueryOperator::Eq),
+        "neq" => Some(QueryOperator::Neq),
+        "gt" => Some(QueryOperator::Gt),
+        "lt" => Some(QueryOperator::Lt),
+        "ge" => Some(QueryOperator::Ge),
+        "le" => Some(QueryOperator::Le),
+        "contains" => Some(QueryOperator::Contains),
+        "in" => Some(QueryOperator::In),
+        "not_in" => Some(QueryOperator::NotIn),
+        "between" => Some(QueryOperator::Between),
+        "starts_with" => Some(QueryOperator::StartsWith),
+        "ends_with" => Some(QueryOperator::EndsWith),
+        "icontains" => Some(QueryOperator::IContains),
+        "like" => Some(QueryOperator::Like),
+        "regex" => Some(QueryOperator::Regex),
+        "exists" => Some(QueryOperator::Exists),
+        "not_exists" => Some(QueryOperator::NotExists),
+        "is_null" => Some(QueryOperator::IsNull),
+        "any_of" => Some(QueryOperator::AnyOf),
+        "contains_element" => Some(QueryOperator::ContainsElement),
+        _ => None,
+    }
+}
+
+/// Returns whether a query operator compares against a value (`exists`, `not_exists` and `is_null` do not).
+fn operator_takes_value(operator: &QueryOperator) -> bool {
+    !matches!(operator, QueryOperator::Exists | QueryOperator::NotExists | QueryOperator::IsNull)
+}
+
+/// Parses the raw value of a query according to its operator.
+///
+/// List operators take comma-separated values, string operators take the text as-is
+/// (so `starts_with 01` is not turned into a number) and the others use type inference.
+/// A value in double quotes is taken as the string between them.
+fn parse_query_value(operator: &QueryOperator, value_str: &str) -> Value {
+    match operator {
+        QueryOperator::In | QueryOperator::NotIn | QueryOperator::Between | QueryOperator::AnyOf => {
+            Value::Array(value_str.split(',').map(|s| parse_scalar(s.trim())).collect())
+        }
+        QueryOperator::Contains | QueryOperator::IContains | QueryOperator::StartsWith
+        | QueryOperator::EndsWith | QueryOperator::Like | QueryOperator::Regex => json!(unquote(value_str).unwrap_or(value_str)),
+        QueryOperator::Exists | QueryOperator::NotExists | QueryOperator::IsNull => Value::Null,
+        _ => parse_scalar(value_str),
+    }
+}
+
+/// Infers the type of a single query value, keeping a double-quoted value as a string.
+fn parse_scalar(raw: &str) -> Value {
+    match unquote(raw) {
+        Some(text) => json!(text),
+        None => infer_value(raw),
+    }
+}
+
+/// Returns the text between the double quotes around a value, if it has them.
+fn unquote(raw: &str) -> Option<&str> {
+    raw.strip_prefix('"').and_then(|rest| rest.strip_suffix('"'))
+}
+
+/// Parses an `<attribute> <operator> [value]` condition, printing what is wrong with it on failure.
+///
+/// The value may contain spaces, so it is the rest of `parts`.
+pub fn parse_condition<'a>(parts: &[&'a str]) -> Option<(&'a str, QueryOperator, Value)> {
+    if parts.len() < 2 {
+        println!("Usage: ... where <attribute> <operator> <value>");
+        return None;
+    }
+
+    let operator_str = parts[1].to_lowercase();
+    let Some(operator) = parse_operator(&operator_str) else {
+        println!("Invalid operator: '{}'. Type 'operators' for a list.", operator_str);
+        return None;
+    };
+
+    if !operator_takes_value(&operator) {
+        return Some((parts[0], operator, Value::Null));
+    }
+    if parts.len() < 3 {
+        println!("Usage: ... where <attribute> <operator> <value>");
+        return None;
+    }
+    let value = parse_query_value(&operator, &parts[2..].join(" "));
+    Some((parts[0], operator, value))
 }
 
 /// Handles the "query" command: Queries records using an attribute, operator, and value.
-pub fn handle_query(store: &mut Store, parts: &[&str]) -> io::Result<()> {
-    if parts.len() < 4 {
+pub fn handle_query(store: &Store, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 3 {
         println!("Usage: query <attribute> <operator> <value>");
         println!("Example: query age gt 25");
         return Ok(());
@@ -219,33 +515,24 @@ pub fn handle_query(store: &mut Store, parts: &[&str]) -> io::Result<()> {
 
     let attribute = parts[1];
     let operator_str = parts[2].to_lowercase();
-    let value_str = parts[3];
-
-    // Parse the value with type inference
-    let value = if let Ok(num) = value_str.parse::<i64>() {
-        json!(num)
-    } else if let Ok(float) = value_str.parse::<f64>() {
-        json!(float)
-    } else if value_str.to_lowercase() == "true" || value_str.to_lowercase() == "false" {
-        json!(value_str.to_lowercase() == "true")
-    } else {
-        json!(value_str)
-    };
 
-    let operator = match operator_str.as_str() {
-        "eq" => QueryOperator::Eq,
-        "neq" => QueryOperator::Neq,
-        "gt" => QueryOperator::Gt,
-        "lt" => QueryOperator::Lt,
-        "ge" => QueryOperator::Ge,
-        "le" => QueryOperator::Le,
-        "contains" => QueryOperator::Contains,
-        _ => {
+    let operator = match parse_operator(&operator_str) {
+        Some(operator) => operator,
+        None => {
             println!("Invalid operator: '{}'. Type 'operators' for a list.", operator_str);
             return Ok(());
         }
     };
 
+    if operator_takes_value(&operator) && parts.len() < 4 {
+        println!("Usage: query <attribute> <operator> <value>");
+        return Ok(());
+    }
+
+    // The value may contain spaces (e.g. a LIKE pattern), so take the rest of the line
+    let value_str = parts[3..].join(" ");
+    let value = parse_query_value(&operator, &value_str);
+
     match store.query(attribute, operator, value) {
         Ok(results) => {
             if results.is_empty() {
@@ -268,7 +555,7 @@ pub fn handle_query(store: &mut Store, parts: &[&str]) -> io::Result<()> {
 }
 
 /// Handles the "sort" command: Sorts records by an attribute in ascending or descending order.
-pub fn handle_sort(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+pub fn handle_sort(store: &Store, parts: &[&str]) -> io::Result<()> {
     if parts.len() < 3 {
         println!("Usage: sort <attribute> <asc/desc>");
         println!("Example: sort age asc");
@@ -306,3 +593,557 @@ pub fn handle_sort(store: &mut Store, parts: &[&str]) -> io::Result<()> {
 
     Ok(())
 }
+
+/// Handles the "aggregate" command: Computes count, sum, avg, min, max or count_distinct,
+/// optionally grouped by attributes and restricted by a query condition.
+pub fn handle_aggregate(store: &Store, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: aggregate <function> [attribute] [by <attributes>] [where <attribute> <operator> [value]]");
+        println!("Example: aggregate count by city where age gt 25");
+        return Ok(());
+    }
+
+    let function_str = parts[1].to_lowercase();
+    let function = match function_str.as_str() {
+        "count" => AggregateFunction::Count,
+        "sum" => AggregateFunction::Sum,
+        "avg" => AggregateFunction::Avg,
+        "min" => AggregateFunction::Min,
+        "max" => AggregateFunction::Max,
+        "count_distinct" => AggregateFunction::CountDistinct,
+        _ => {
+            println!("Invalid aggregate function: '{}'. Use count, sum, avg, min, max or count_distinct.", function_str);
+            return Ok(());
+        }
+    };
+
+    let mut index = 2;
+    let attribute = match parts.get(index) {
+        Some(part) if !part.eq_ignore_ascii_case("by") && !part.eq_ignore_ascii_case("where") => {
+            index += 1;
+            Some(*part)
+        }
+        _ => None,
+    };
+
+    let mut group_by: Vec<&str> = Vec::new();
+    if parts.get(index).is_some_and(|part| part.eq_ignore_ascii_case("by")) {
+        match parts.get(index + 1) {
+            Some(attributes) => group_by = attributes.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect(),
+            None => {
+                println!("Missing attributes after 'by'.");
+                return Ok(());
+            }
+        }
+        index += 2;
+    }
+
+    // The condition comes last, so its value may take the rest of the line, as in "query".
+    let mut condition = None;
+    if parts.get(index).is_some_and(|part| part.eq_ignore_ascii_case("where")) {
+        let Some(parsed) = parse_condition(&parts[index + 1..]) else { return Ok(()) };
+        condition = Some(parsed);
+        index = parts.len();
+    }
+
+    if index < parts.len() {
+        println!("Unexpected input: '{}'.", parts[index..].join(" "));
+        return Ok(());
+    }
+
+    match store.aggregate(function, attribute, &group_by, condition) {
+        Ok(results) => {
+            let label = match attribute {
+                Some(attr) => format!("{}({})", function_str, attr),
+                None => function_str.clone(),
+            };
+            if results.is_empty() {
+                println!("No records to aggregate in store '{}'.", store.name);
+            } else if group_by.is_empty() {
+                for (_, aggregate) in results {
+                    println!("{} = {}", label, aggregate);
+                }
+            } else {
+                println!("Aggregate results ({} by {}):", label, group_by.join(", "));
+                for (key, aggregate) in results {
+                    let key_str: Vec<String> = group_by
+                        .iter()
+                        .zip(key.iter())
+                        .map(|(k, v)| format!("{}: {}", k, v))
+                        .collect();
+                    println!("{{{}}} => {}", key_str.join(", "), aggregate);
+                }
+            }
+        }
+        Err(e) => println!("Aggregate failed: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Parses a CSV delimiter argument, accepting `tab` or `\t` for a tab character.
+fn parse_delimiter(text: &str) -> Option<char> {
+    match text {
+        "tab" | "\\t" => Some('\t'),
+        _ => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c != '"' && c != '\n' && c != '\r' => Some(c),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Handles the "import_csv" command: Appends the rows of a CSV file with a header row to the store.
+pub fn handle_import_csv(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: import_csv <file> [delimiter]");
+        println!("Example: import_csv users.csv ;");
+        return Ok(());
+    }
+
+    let delimiter = match parts.get(2) {
+        Some(text) => match parse_delimiter(text) {
+            Some(delimiter) => delimiter,
+            None => {
+                println!("Invalid delimiter: '{}'. Use a single character or 'tab'.", text);
+                return Ok(());
+            }
+        },
+        None => ',',
+    };
+
+    match database.import_csv(store_name, parts[1], delimiter) {
+        Ok(count) => println!("Imported {} records from '{}'.", count, parts[1]),
+        Err(e) => print_mutation_error("Import failed", &e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "export_csv" command: Writes the store to a CSV file.
+///
+/// Options are given as `delimiter=<char>`, `quote=always|necessary`, `columns=<a,b,...>` and `id=true`.
+pub fn handle_export_csv(store: &Store, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: export_csv <file> [delimiter=<char>] [quote=always|necessary] [columns=<a,b,...>] [id=true]");
+        println!("Example: export_csv users.csv delimiter=; columns=name,age");
+        return Ok(());
+    }
+
+    let mut options = CsvOptions::default();
+    for option in &parts[2..] {
+        let (key, value) = option.split_once('=').unwrap_or((option, ""));
+        match (key.to_lowercase().as_str(), value) {
+            ("delimiter", text) => match parse_delimiter(text) {
+                Some(delimiter) => options.delimiter = delimiter,
+                None => {
+                    println!("Invalid delimiter: '{}'. Use a single character or 'tab'.", text);
+                    return Ok(());
+                }
+            },
+            ("quote", "always") => options.quote_style = QuoteStyle::Always,
+            ("quote", "necessary") => options.quote_style = QuoteStyle::Necessary,
+            ("columns", columns) => options.columns = Some(columns.split(',').map(|s| s.trim().to_string()).collect()),
+            ("id", include) => options.include_id = include.eq_ignore_ascii_case("true"),
+            _ => {
+                println!("Invalid option: '{}'.", option);
+                return Ok(());
+            }
+        }
+    }
+
+    match store.export_csv(parts[1], &options) {
+        Ok(count) => println!("Exported {} records to '{}'.", count, parts[1]),
+        Err(e) => println!("Export failed: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "import_ndjson" command: Streams a JSON Lines file into the store.
+pub fn handle_import_ndjson(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: import_ndjson <file>");
+        return Ok(());
+    }
+
+    match database.import_ndjson(store_name, parts[1]) {
+        Ok(count) => println!("Imported {} records from '{}'.", count, parts[1]),
+        Err(e) => println!("Import failed: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "export_ndjson" command: Streams the store's records to a JSON Lines file.
+pub fn handle_export_ndjson(store: &Store, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: export_ndjson <file>");
+        return Ok(());
+    }
+
+    match store.export_ndjson(parts[1]) {
+        Ok(count) => println!("Exported {} records to '{}'.", count, parts[1]),
+        Err(e) => println!("Export failed: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Handles the "rename_attribute" command: Renames an attribute in every record of the store.
+pub fn handle_rename_attribute(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 3 {
+        println!("Usage: rename_attribute <attribute> <new_name>");
+        return Ok(());
+    }
+
+    match database.rename_attribute(store_name, parts[1], parts[2]) {
+        Ok(count) => println!("Attribute '{}' renamed to '{}' in {} record(s).", parts[1], parts[2], count),
+        Err(e) => println!("Failed to rename attribute: {}", e),
+    }
+    Ok(())
+}
+
+/// Handles the "drop_attribute" command: Removes an attribute from every record of the store.
+pub fn handle_drop_attribute(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    if parts.len() < 2 {
+        println!("Usage: drop_attribute <attribute>");
+        return Ok(());
+    }
+
+    match database.drop_attribute(store_name, parts[1]) {
+        Ok(count) => println!("Attribute '{}' dropped from {} record(s).", parts[1], count),
+        Err(e) => println!("Failed to drop attribute: {}", e),
+    }
+    Ok(())
+}
+
+/// Prints an inferred schema with the type conflicts of each attribute.
+fn print_schema_inference(inference: &SchemaInference) {
+    println!("Schema inferred from {} record(s):", inference.records);
+    println!("{:<20} {:<8} {:<9} {:<8} conflicts", "attribute", "type", "nullable", "unique");
+    for field in &inference.fields {
+        let (type_name, nullable) = match field.schema {
+            Some(schema) => (schema.field_type.name(), if schema.nullable { "yes" } else { "no" }),
+            None => (FieldType::Any.name(), "yes"),
+        };
+
+        let conflicts = if field.outliers.is_empty() {
+            "-".to_string()
+        } else {
+            let shares: Vec<String> = field.type_counts.iter()
+                .map(|(name, count)| format!("{:.0}% {}", *count as f64 * 100.0 / field.present as f64, name))
+                .collect();
+            let mut ids: Vec<String> = field.outliers.iter().take(10).map(|id| id.to_string()).collect();
+            if field.outliers.len() > ids.len() {
+                ids.push("...".to_string());
+            }
+            format!("{} (outlier records: {})", shares.join(", "), ids.join(", "))
+        };
+
+        println!(
+            "{:<20} {:<8} {:<9} {:<8} {}",
+            field.name, type_name, nullable, if field.unique_candidate { "yes" } else { "no" }, conflicts
+        );
+    }
+}
+
+/// Handles the "infer_schema" command: Infers a typed schema from the store's records and shows its conflicts.
+pub fn handle_infer_schema(store: &Store) -> io::Result<()> {
+    print_schema_inference(&store.infer_schema());
+    match store.schema() {
+        Some(_) => println!("A schema is locked onto this store."),
+        None => println!("Use 'lock_schema [convert|reject]' to lock this schema in."),
+    }
+    Ok(())
+}
+
+/// Handles the "lock_schema" command: Infers a schema, deals with its outliers and locks it onto the store.
+pub fn handle_lock_schema(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    let policy = match parts.get(1).map(|policy| policy.to_lowercase()) {
+        None => None,
+        Some(policy) if policy == "convert" => Some(OutlierPolicy::Convert),
+        Some(policy) if policy == "reject" => Some(OutlierPolicy::Reject),
+        Some(policy) => {
+            println!("Invalid outlier policy: '{}'. Use 'convert' or 'reject'.", policy);
+            return Ok(());
+        }
+    };
+
+    match database.lock_schema(store_name, policy) {
+        Ok(inference) => {
+            print_schema_inference(&inference);
+            let outliers = inference.outliers().len();
+            match policy {
+                Some(OutlierPolicy::Convert) if outliers > 0 => println!("Converted {} outlying value(s).", outliers),
+                Some(OutlierPolicy::Reject) if outliers > 0 => println!("Rejected the records holding {} outlying value(s).", outliers),
+                _ => {}
+            }
+            println!("Schema locked onto '{}'.", store_name);
+        }
+        Err(e) => println!("Failed to lock schema: {}", e),
+    }
+    Ok(())
+}
+
+/// Handles the "unlock_schema" command: Removes the schema locked onto the store.
+pub fn handle_unlock_schema(store: &mut Store) -> io::Result<()> {
+    store.unlock_schema();
+    println!("Schema unlocked.");
+    Ok(())
+}
+
+/// Handles the "set_ttl" command: Sets or clears the default time-to-live of new records.
+pub fn handle_set_ttl(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+    let Some(text) = parts.get(1) else {
+        match store.default_ttl() {
+            Some(ttl) => println!("Default TTL: {}s", ttl.as_secs_f64()),
+            None => println!("Records do not expire by default."),
+        }
+        println!("Usage: set_ttl <duration|none>");
+        return Ok(());
+    };
+
+    if text.eq_ignore_ascii_case("none") {
+        store.set_default_ttl(None);
+        println!("New records no longer expire by default.");
+        return Ok(());
+    }
+    match parse_duration(text) {
+        Some(ttl) => {
+            store.set_default_ttl(Some(ttl));
+            println!("New records now expire after {}s by default.", ttl.as_secs_f64());
+        }
+        None => println!("Invalid TTL: '{}'. Use seconds or a duration like 30s, 15m, 2h or 1d.", text),
+    }
+    Ok(())
+}
+
+/// Handles the "versioning" command: Turns the record timestamps and versions of the store on or off.
+pub fn handle_versioning(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+    match parts.get(1).map(|mode| mode.to_lowercase()).as_deref() {
+        Some("on") => {
+            store.set_metadata_tracking(true);
+            println!("Records now carry creation time, update time and version.");
+        }
+        Some("off") => {
+            store.set_metadata_tracking(false);
+            println!("Record timestamps and versions are no longer tracked.");
+        }
+        _ => {
+            println!("Record versioning is {}.", if store.tracks_metadata() { "on" } else { "off" });
+            println!("Usage: versioning <on|off>");
+        }
+    }
+    Ok(())
+}
+
+/// Handles the "keep_history" command: Turns the keeping of prior record versions on or off.
+pub fn handle_keep_history(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+    match parts.get(1).map(|mode| mode.to_lowercase()).as_deref() {
+        Some("on") => {
+            store.set_history(true);
+            println!("Prior versions of records are now kept.");
+        }
+        Some("off") => {
+            store.set_history(false);
+            println!("Record history discarded and no longer kept.");
+        }
+        _ => {
+            println!("Record history is {}.", if store.keeps_history() { "on" } else { "off" });
+            println!("Usage: keep_history <on|off>");
+        }
+    }
+    Ok(())
+}
+
+/// Formats a record as `{attribute: value, ...}`.
+fn format_record(record: &HashMap<String, Value>) -> String {
+    let fields: Vec<String> = record.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Handles the "history" command: Lists every known version of a record.
+pub fn handle_history(store: &Store, parts: &[&str]) -> io::Result<()> {
+    let Some(text) = parts.get(1) else {
+        println!("Usage: history <record_id>");
+        return Ok(());
+    };
+    let Some(id) = resolve_record_id(store, text) else { return Ok(()) };
+
+    let entries = store.record_history(id);
+    if entries.is_empty() {
+        println!("No history for record {}.", id);
+        return Ok(());
+    }
+    for entry in entries {
+        let until = entry.valid_to.map_or("now".to_string(), |valid_to| valid_to.to_string());
+        println!("Version {} ({} - {}): {}", entry.version, entry.valid_from, until, format_record(&entry.record));
+    }
+    Ok(())
+}
+
+/// Handles the "as_of" command: Shows a record as it was at a time or a version.
+pub fn handle_as_of(store: &Store, parts: &[&str]) -> io::Result<()> {
+    let (Some(text), Some(point)) = (parts.get(1), parts.get(2)) else {
+        println!("Usage: as_of <record_id> <unix_millis> | as_of <record_id> version=<n>");
+        return Ok(());
+    };
+    let Some(id) = resolve_record_id(store, text) else { return Ok(()) };
+
+    let record = match point.strip_prefix("version=") {
+        Some(version) => match version.parse::<u64>() {
+            Ok(version) => store.record_at_version(id, version),
+            Err(_) => {
+                println!("Invalid version: '{}'. Must be a positive integer.", version);
+                return Ok(());
+            }
+        },
+        None => match point.parse::<u64>() {
+            Ok(at) => store.record_as_of(id, at),
+            Err(_) => {
+                println!("Invalid time: '{}'. Use Unix milliseconds.", point);
+                return Ok(());
+            }
+        },
+    };
+    match record {
+        Some(record) => println!("Record {} as of {}: {}", id, point, format_record(&record)),
+        None => println!("Record {} did not exist as of {}.", id, point),
+    }
+    Ok(())
+}
+
+/// Handles the "restore" command: Brings a record back to one of its prior versions.
+pub fn handle_restore(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    let (Some(text), Some(version)) = (parts.get(1), parts.get(2).and_then(|version| version.parse::<u64>().ok())) else {
+        println!("Usage: restore <record_id> <version>");
+        return Ok(());
+    };
+    let Some(id) = database.get_store(store_name).and_then(|store| resolve_record_id(store, text)) else { return Ok(()) };
+
+    match database.restore_version(store_name, id, version) {
+        Ok(new_version) => println!("Record {} restored to version {} as version {}.", id, version, new_version),
+        Err(e) => print_mutation_error(&format!("Failed to restore record {}", id), &e),
+    }
+    Ok(())
+}
+
+/// Handles the "soft_delete" command: Turns soft deletes on or off, optionally with a trash retention period.
+pub fn handle_soft_delete(store: &mut Store, parts: &[&str]) -> io::Result<()> {
+    let retention = match parts.get(2) {
+        Some(text) => match parse_duration(text) {
+            Some(retention) => Some(retention),
+            None => {
+                println!("Invalid retention: '{}'. Use seconds or a duration like 30s, 15m, 2h or 1d.", text);
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    match parts.get(1).map(|mode| mode.to_lowercase()).as_deref() {
+        Some("on") => {
+            store.set_soft_delete(true);
+            store.set_trash_retention(retention);
+            match retention {
+                Some(retention) => println!("Deleted records now go to the trash for {}s.", retention.as_secs_f64()),
+                None => println!("Deleted records now go to the trash until it is emptied."),
+            }
+        }
+        Some("off") => {
+            store.set_soft_delete(false);
+            println!("Deleted records are now removed for good; the trash is kept.");
+        }
+        _ => {
+            match (store.soft_deletes(), store.trash_retention()) {
+                (true, Some(retention)) => println!("Soft deletes are on; the trash is kept for {}s.", retention.as_secs_f64()),
+                (true, None) => println!("Soft deletes are on; the trash is kept until emptied."),
+                (false, _) => println!("Soft deletes are off."),
+            }
+            println!("Usage: soft_delete <on|off> [retention]");
+        }
+    }
+    Ok(())
+}
+
+/// Handles the "trash" command: Lists the deleted records in the trash.
+pub fn handle_trash(store: &Store) -> io::Result<()> {
+    let trashed = store.trashed_records();
+    if trashed.is_empty() {
+        println!("The trash is empty.");
+        return Ok(());
+    }
+    for (id, trashed) in trashed {
+        println!("Record {} (deleted at {}): {}", id, trashed.deleted_at, format_record(&trashed.record));
+    }
+    Ok(())
+}
+
+/// Handles the "restore_record" command: Brings a deleted record back from the trash.
+pub fn handle_restore_record(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    let Some(text) = parts.get(1) else {
+        println!("Usage: restore_record <record_id>");
+        return Ok(());
+    };
+    let Some(id) = database.get_store(store_name).and_then(|store| resolve_record_id(store, text)) else { return Ok(()) };
+    match database.restore_record(store_name, id) {
+        Ok(()) => println!("Record {} restored.", id),
+        Err(e) => println!("Failed to restore record {}: {}", id, e),
+    }
+    Ok(())
+}
+
+/// Handles the "empty_trash" command: Permanently removes the deleted records in the trash.
+pub fn handle_empty_trash(store: &mut Store) -> io::Result<()> {
+    println!("{} record(s) removed for good.", store.empty_trash());
+    Ok(())
+}
+
+/// Handles the "id_strategy" command: Shows or changes how the store identifies its records.
+pub fn handle_id_strategy(database: &mut Database, store_name: &str, parts: &[&str]) -> io::Result<()> {
+    let strategy = match (parts.get(1).map(|name| name.to_lowercase()).as_deref(), parts.get(2)) {
+        (Some("counter"), _) => IdStrategy::Counter,
+        (Some("uuid"), attribute) => IdStrategy::Uuid { attribute: attribute.unwrap_or(&"uuid").to_string() },
+        (Some("ulid"), attribute) => IdStrategy::Ulid { attribute: attribute.unwrap_or(&"ulid").to_string() },
+        (Some("key"), Some(attribute)) => IdStrategy::NaturalKey { attribute: attribute.to_string() },
+        _ => {
+            if let Some(store) = database.get_store(store_name) {
+                match store.id_strategy().key_attribute() {
+                    Some(attribute) => println!("Records are identified by {} in '{}'.", store.id_strategy().name(), attribute),
+                    None => println!("Records are identified by counter."),
+                }
+            }
+            println!("Usage: id_strategy <counter | uuid [attribute] | ulid [attribute] | key <attribute>>");
+            return Ok(());
+        }
+    };
+
+    let name = strategy.name();
+    match database.set_id_strategy(store_name, strategy) {
+        Ok(()) => println!("Records are now identified by {}.", name),
+        Err(e) => println!("Failed to change the ID strategy: {}", e),
+    }
+    Ok(())
+}
+
+/// Handles the "ttl" command: Shows how long a record has left before it expires.
+pub fn handle_ttl(store: &Store, parts: &[&str]) -> io::Result<()> {
+    let Some(text) = parts.get(1) else {
+        println!("Usage: ttl <record_id>");
+        return Ok(());
+    };
+    let Some(id) = resolve_record_id(store, text) else { return Ok(()) };
+
+    if store.get_record(id).is_err() {
+        println!("Record {} not found.", id);
+        return Ok(());
+    }
+    match store.time_to_live(id) {
+        Some(ttl) => println!("Record {} expires in {:.1}s.", id, ttl.as_secs_f64()),
+        None => println!("Record {} never expires.", id),
+    }
+    Ok(())
+}
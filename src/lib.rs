@@ -0,0 +1,5 @@
+//! Rustbase: an in-memory database of schemaless stores, saved as JSON or binary snapshots.
+//!
+//! The storage engine lives in [`memory`]; the interactive CLI of the `rustbase` binary is built on it.
+
+pub mod memory;
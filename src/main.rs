@@ -1,12 +1,20 @@
-mod memory;
 mod commands;
 
 use crate::commands::help::print_help;
-use crate::memory::database::Database;
+use rustbase::memory::catalog::Catalog;
 use std::io::{stdin, stdout, BufRead, Write};
 use crate::commands::database_command::run_database_command_loop;
+use crate::commands::database_functions::parse_snapshot_format;
 
 fn main() {
+    let catalog = match Catalog::from_environment() {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            eprintln!("Failed to open the data directory: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     let stdin = stdin();
     let mut stdout = stdout();
 
@@ -25,13 +33,17 @@ fn main() {
         match command.as_str() {
             "exit" => break,
             "help" => print_help(),
-            "database" => handle_database(&parts),
+            "database" => handle_database(&catalog, &parts),
+            "convert" => handle_convert(&catalog, &parts),
+            "list_databases" => handle_list_databases(&catalog),
+            "drop_database" => handle_drop_database(&catalog, &parts),
+            "rename_database" => handle_rename_database(&catalog, &parts),
             _ => print_help()
         }
     }
 }
 
-fn handle_database(parts: &[&str]) {
+fn handle_database(catalog: &Catalog, parts: &[&str]) {
     if parts.len() < 2 {
         println!("Usage: database <name>");
         return;
@@ -40,15 +52,72 @@ fn handle_database(parts: &[&str]) {
     let database_name = parts[1];
 
     // Use match to handle both success and error cases
-    match Database::load(database_name) {
+    match catalog.load(database_name) {
         Ok(database) => {
-            println!("Database loaded successfully from JSON file!");
+            println!("Database loaded successfully from snapshot!");
             run_database_command_loop(database).unwrap();
         }
-        Err(_) => {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
             println!("Database file not found! Creating a new one.");
-            let new_database = Database::new(database_name).unwrap();
+            let new_database = catalog.create(database_name).unwrap();
             run_database_command_loop(new_database).unwrap();
         }
+        Err(e) => println!("Failed to open database '{}': {}", database_name, e),
+    }
+}
+
+fn handle_convert(catalog: &Catalog, parts: &[&str]) {
+    if parts.len() < 3 {
+        println!("Usage: convert <name> <json|binary>");
+        return;
+    }
+
+    let Some(format) = parse_snapshot_format(parts[2]) else {
+        println!("Invalid format: '{}'. Use 'json' or 'binary'.", parts[2]);
+        return;
+    };
+
+    let converted = catalog.load(parts[1])
+        .and_then(|mut database| database.store_as(format).map(|_| database.snapshot_path(format)));
+    match converted {
+        Ok(path) => println!("Database '{}' converted to '{}'.", parts[1], path.display()),
+        Err(e) => println!("Conversion failed: {}", e),
+    }
+}
+
+fn handle_list_databases(catalog: &Catalog) {
+    match catalog.list_databases() {
+        Ok(names) if names.is_empty() => println!("No databases found."),
+        Ok(names) => {
+            println!("Databases:");
+            for name in names {
+                println!("- {}", name);
+            }
+        }
+        Err(e) => println!("Failed to list databases: {}", e),
     }
-}
\ No newline at end of file
+}
+
+fn handle_drop_database(catalog: &Catalog, parts: &[&str]) {
+    if parts.len() < 2 {
+        println!("Usage: drop_database <name>");
+        return;
+    }
+
+    match catalog.drop_database(parts[1]) {
+        Ok(()) => println!("Database '{}' dropped.", parts[1]),
+        Err(e) => println!("Failed to drop database: {}", e),
+    }
+}
+
+fn handle_rename_database(catalog: &Catalog, parts: &[&str]) {
+    if parts.len() < 3 {
+        println!("Usage: rename_database <name> <new_name>");
+        return;
+    }
+
+    match catalog.rename_database(parts[1], parts[2]) {
+        Ok(()) => println!("Database '{}' renamed to '{}'.", parts[1], parts[2]),
+        Err(e) => println!("Failed to rename database: {}", e),
+    }
+}
@@ -0,0 +1,210 @@
+use std::io;
+use serde_json::Value;
+use super::structure::Store;
+use super::database::Database;
+use super::changes::ChangeKind;
+use super::complex::QueryOperator;
+use super::ids::IdStrategy;
+use std::collections::HashMap;
+
+impl Store {
+    /// Renames an attribute in every record, in the store's attribute set, in the foreign key declared
+    /// on it and in the locked schema. Every record holding it gets a new version, as with an update.
+    ///
+    /// # Returns
+    /// The number of records holding the attribute.
+    ///
+    /// # Errors
+    /// Returns an error if `from` is not an attribute of the store or `to` already is.
+    pub(super) fn rename_attribute(&mut self, from: &str, to: &str) -> io::Result<usize> {
+        if !self.attributes.contains(from) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Attribute '{}' not found", from)));
+        }
+        if to.is_empty() || self.attributes.contains(to) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Attribute '{}' already exists", to)));
+        }
+
+        let renamed = self.change_records_holding(from, |record| {
+            let value = record.remove(from).unwrap();
+            record.insert(to.to_string(), value);
+        });
+
+        self.attributes.remove(from);
+        self.attributes.insert(to.to_string());
+        for foreign_key in self.foreign_keys.iter_mut().filter(|fk| fk.attribute == from) {
+            foreign_key.attribute = to.to_string();
+        }
+        if let Some(field) = self.schema.as_mut().and_then(|schema| schema.fields.remove(from)) {
+            self.schema.as_mut().unwrap().fields.insert(to.to_string(), field);
+        }
+        match &mut self.id_strategy {
+            IdStrategy::Uuid { attribute } | IdStrategy::Ulid { attribute } | IdStrategy::NaturalKey { attribute } if attribute == from => {
+                *attribute = to.to_string();
+            }
+            _ => {}
+        }
+        Ok(renamed)
+    }
+
+    /// Removes an attribute from every record and from the store's attribute set, along with any foreign key
+    /// and schema field declared on it. Every record that held it gets a new version, as with an update.
+    ///
+    /// # Returns
+    /// The number of records that held the attribute.
+    ///
+    /// # Errors
+    /// Returns an error if `name` is not an attribute of the store.
+    pub(super) fn drop_attribute(&mut self, name: &str) -> io::Result<usize> {
+        if self.id_strategy.key_attribute() == Some(name) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Attribute '{}' holds the record keys", name)));
+        }
+        if !self.attributes.remove(name) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Attribute '{}' not found", name)));
+        }
+
+        let dropped = self.change_records_holding(name, |record| {
+            record.remove(name);
+        });
+        self.foreign_keys.retain(|fk| fk.attribute != name);
+        if let Some(schema) = self.schema.as_mut() {
+            schema.fields.remove(name);
+        }
+        Ok(dropped)
+    }
+
+    /// Applies `change` to every record holding `attribute`, keeping its prior version in the history and
+    /// bumping its metadata as an update would.
+    ///
+    /// # Returns
+    /// The number of records changed.
+    fn change_records_holding(&mut self, attribute: &str, change: impl Fn(&mut HashMap<String, Value>)) -> usize {
+        let ids: Vec<i64> = self.values.iter()
+            .filter(|(_, record)| record.contains_key(attribute))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            self.remember(*id);
+            change(self.values.get_mut(id).unwrap());
+            self.stamp_updated(*id);
+        }
+        ids.len()
+    }
+}
+
+impl Database {
+    /// Renames an attribute of a store, as `Store::rename_attribute` does, publishing an `Update` event
+    /// for every record holding it.
+    ///
+    /// # Returns
+    /// The number of records holding the attribute.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist, and the errors of `Store::rename_attribute`.
+    pub fn rename_attribute(&mut self, store: &str, from: &str, to: &str) -> io::Result<usize> {
+        let old = self.observed_records(store, |record| record.contains_key(from));
+        let renamed = self.store_mut(store)?.rename_attribute(from, to)?;
+        self.records_updated(store, old);
+        Ok(renamed)
+    }
+
+    /// Drops an attribute of a store, as `Store::drop_attribute` does, publishing an `Update` event
+    /// for every record that held it.
+    ///
+    /// # Returns
+    /// The number of records that held the attribute.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist, and the errors of `Store::drop_attribute`.
+    pub fn drop_attribute(&mut self, store: &str, name: &str) -> io::Result<usize> {
+        let old = self.observed_records(store, |record| record.contains_key(name));
+        let dropped = self.store_mut(store)?.drop_attribute(name)?;
+        self.records_updated(store, old);
+        Ok(dropped)
+    }
+
+    /// Renames a store, keeping its `name` field and every foreign key referencing it in sync.
+    ///
+    /// Subscribers see the old store dropped and the new one created.
+    ///
+    /// # Errors
+    /// Returns an error if `from` does not exist or a store called `to` already does.
+    pub fn rename_store(&mut self, from: &str, to: &str) -> io::Result<()> {
+        if !self.stores.contains_key(from) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", from)));
+        }
+        if to.is_empty() || self.stores.contains_key(to) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Store '{}' already exists", to)));
+        }
+
+        let mut store = self.stores.remove(from).unwrap();
+        store.name = to.to_string();
+        self.stores.insert(to.to_string(), store);
+
+        for store in self.stores.values_mut() {
+            for foreign_key in store.foreign_keys.iter_mut().filter(|fk| fk.references == from) {
+                foreign_key.references = to.to_string();
+            }
+        }
+        self.store_changed(from, ChangeKind::StoreDropped);
+        self.store_changed(to, ChangeKind::StoreCreated);
+        Ok(())
+    }
+
+    /// Copies a store, or only the records matching a condition, into a new store.
+    ///
+    /// Records keep their IDs, expirations, system fields and history, and the copy keeps the attributes, foreign keys, schema,
+    /// default TTL and triggers of the source. Expired records are not copied.
+    ///
+    /// # Arguments
+    /// * `source` - The name of the store to copy.
+    /// * `destination` - The name of the new store.
+    /// * `condition` - An optional `(attribute, operator, value)` condition records must satisfy, as in `query`.
+    ///
+    /// # Returns
+    /// The number of records copied.
+    ///
+    /// # Errors
+    /// Returns an error if `source` does not exist, `destination` already does or the condition is invalid.
+    pub fn copy_store(&mut self, source: &str, destination: &str, condition: Option<(&str, QueryOperator, Value)>) -> io::Result<usize> {
+        if destination.is_empty() || self.stores.contains_key(destination) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Store '{}' already exists", destination)));
+        }
+        let store = self.stores.get_mut(source)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", source)))?;
+
+        let values = match condition {
+            Some((attribute, operator, value)) => store.query(attribute, operator, value)?,
+            None => store.live_values().map(|(id, record)| (*id, record.clone())).collect(),
+        };
+        let count = values.len();
+        let mut copy = Store {
+            id: store.id,
+            name: destination.to_string(),
+            attributes: store.attributes.clone(),
+            values,
+            foreign_keys: store.foreign_keys.clone(),
+            schema: store.schema.clone(),
+            default_ttl_ms: store.default_ttl_ms,
+            expirations: store.expirations.clone(),
+            triggers: store.triggers.clone(),
+            track_metadata: store.track_metadata,
+            metadata: store.metadata.clone(),
+            keep_history: store.keep_history,
+            history: store.history.clone(),
+            soft_delete: store.soft_delete,
+            trash_retention_ms: store.trash_retention_ms,
+            trash: Default::default(),
+            id_strategy: store.id_strategy.clone(),
+            key_index: Default::default(),
+            trash_key_index: Default::default(),
+        };
+        copy.expirations.retain(|id, _| copy.values.contains_key(id));
+        copy.metadata.retain(|id, _| copy.values.contains_key(id));
+        copy.history.retain(|id, _| copy.values.contains_key(id));
+        copy.rebuild_key_index();
+
+        self.stores.insert(destination.to_string(), copy);
+        self.store_changed(destination, ChangeKind::StoreCreated);
+        Ok(count)
+    }
+}
@@ -0,0 +1,72 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::complex::QueryOperator;
+    use crate::memory::constraints::ReferentialAction;
+
+    fn create_test_database() -> Database {
+        let mut db = Database::new("alter_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Cascade).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Alice"), "age".to_string() => json!(30) }).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Bob"), "age".to_string() => json!(15) }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(0), "total".to_string() => json!(10) }).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_rename_store_updates_foreign_keys() {
+        let mut db = create_test_database();
+        db.rename_store("users", "customers").unwrap();
+
+        assert!(db.get_store("users").is_none());
+        assert_eq!(db.get_store("customers").unwrap().name, "customers");
+        assert_eq!(db.get_store("orders").unwrap().foreign_keys[0].references, "customers");
+        assert!(db.rename_store("customers", "orders").is_err());
+        assert!(db.rename_store("missing", "other").is_err());
+
+        db.delete_record("customers", 0).unwrap();
+        assert!(db.get_store("orders").unwrap().values.is_empty());
+        assert!(db.convert_to_sql().unwrap().contains("REFERENCES customers(id)"));
+    }
+
+    #[test]
+    fn test_copy_store_with_filter() {
+        let mut db = create_test_database();
+        assert_eq!(db.copy_store("users", "adults", Some(("age", QueryOperator::Ge, json!(18)))).unwrap(), 1);
+        assert_eq!(db.copy_store("users", "everyone", None).unwrap(), 2);
+        assert!(db.copy_store("users", "orders", None).is_err());
+
+        let adults = db.get_store("adults").unwrap();
+        assert_eq!(adults.name, "adults");
+        assert_eq!(adults.values[&0]["name"], json!("Alice"));
+        assert_eq!(db.get_store("everyone").unwrap().values, db.get_store("users").unwrap().values);
+
+        // The copy continues numbering after the source's records.
+        let id = db.add_record("adults", hashmap! { "name".to_string() => json!("Carol") }).unwrap();
+        assert_eq!(id, 2);
+    }
+
+    #[test]
+    fn test_rename_and_drop_attribute() {
+        let mut db = create_test_database();
+        let orders = db.get_store_mut("orders").unwrap();
+        assert_eq!(orders.rename_attribute("user_id", "customer_id").unwrap(), 1);
+        assert!(orders.rename_attribute("missing", "other").is_err());
+        assert!(orders.rename_attribute("total", "customer_id").is_err());
+
+        assert_eq!(orders.values[&0]["customer_id"], json!(0));
+        assert!(orders.attributes.contains("customer_id") && !orders.attributes.contains("user_id"));
+        assert_eq!(orders.foreign_keys[0].attribute, "customer_id");
+
+        assert_eq!(orders.drop_attribute("customer_id").unwrap(), 1);
+        assert!(orders.drop_attribute("customer_id").is_err());
+        assert!(orders.foreign_keys.is_empty());
+        assert_eq!(orders.values[&0], hashmap! { "total".to_string() => json!(10) });
+    }
+}
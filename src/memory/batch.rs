@@ -0,0 +1,175 @@
+use std::io;
+use std::fmt;
+use std::error::Error;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use super::ids::key_of;
+use super::structure::Store;
+use super::database::Database;
+use super::changes::ChangeKind;
+use super::triggers::{AfterTriggerError, TriggerEvent};
+
+/// How many rejected rows the message of a `BatchError` lists before summing up the rest.
+const LISTED_ROWS: usize = 10;
+
+/// A record of a batch that could not be added, with the error it would have raised on its own.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RowError {
+    pub row: usize,             // Position of the record in the batch, from 1.
+    pub kind: io::ErrorKind,    // Kind of the record's error.
+    pub message: String,        // Message of the record's error.
+}
+
+impl RowError {
+    fn new(index: usize, error: &io::Error) -> RowError {
+        RowError { row: index + 1, kind: error.kind(), message: error.to_string() }
+    }
+}
+
+/// The error of a batch insert that added nothing because some of its records were rejected.
+///
+/// It is wrapped in an `io::Error` of kind `InvalidInput`; use `get_ref` and `downcast_ref` to inspect it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BatchError {
+    pub rows: Vec<RowError>,    // The rejected records, in batch order, one error each.
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} record(s) of the batch rejected", self.rows.len())?;
+        for row in self.rows.iter().take(LISTED_ROWS) {
+            write!(f, "; row {}: {}", row.row, row.message)?;
+        }
+        if self.rows.len() > LISTED_ROWS {
+            write!(f, "; and {} more", self.rows.len() - LISTED_ROWS)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for BatchError {}
+
+impl From<BatchError> for io::Error {
+    fn from(error: BatchError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+impl Store {
+    /// Adds several records at once, all of them or none.
+    ///
+    /// Every record is checked as `add_record` would before any is added, then the records are added
+    /// in one pass under consecutive IDs, with the store's attributes extended once for the whole batch.
+    ///
+    /// # Returns
+    /// The IDs of the added records, in batch order.
+    ///
+    /// # Errors
+    /// Returns a `BatchError` listing every rejected record; the store is unchanged then.
+    pub fn add_records(&mut self, mut records: Vec<HashMap<String, Value>>) -> io::Result<Vec<i64>> {
+        let errors = self.check_batch(&mut records);
+        if !errors.is_empty() {
+            return Err(BatchError { rows: errors }.into());
+        }
+        Ok(self.insert_batch(records))
+    }
+
+    /// Fills in the keys of a batch and checks every record against the schema and the keys of the
+    /// store and of the records before it in the batch.
+    pub(super) fn check_batch(&self, records: &mut [HashMap<String, Value>]) -> Vec<RowError> {
+        let key_attribute = self.id_strategy.key_attribute();
+        let mut keys = HashSet::new();
+        let mut errors = Vec::new();
+        for (index, record) in records.iter_mut().enumerate() {
+            let checked = self.assign_key(record)
+                .and_then(|()| self.check_schema(record))
+                .and_then(|()| match key_attribute.and_then(|attribute| record.get(attribute)).and_then(key_of) {
+                    Some(key) if !keys.insert(key.clone()) => Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                        format!("key '{}' appears earlier in the batch", key))),
+                    _ => Ok(()),
+                });
+            if let Err(e) = checked {
+                errors.push(RowError::new(index, &e));
+            }
+        }
+        errors
+    }
+
+    /// Adds a batch that passed `check_batch`.
+    pub(super) fn insert_batch(&mut self, records: Vec<HashMap<String, Value>>) -> Vec<i64> {
+        let first = self.id;
+        let ids: Vec<i64> = (first..first + records.len() as i64).collect();
+
+        let mut attributes = HashSet::new();
+        for attribute in records.iter().flat_map(|record| record.keys()) {
+            if !self.attributes.contains(attribute) && !attributes.contains(attribute) {
+                attributes.insert(attribute.clone());
+            }
+        }
+        self.attributes.extend(attributes);
+
+        let ttl = self.default_ttl();
+        self.values.reserve(records.len());
+        for (id, record) in ids.iter().zip(records) {
+            self.values.insert(*id, record);
+            self.index_key(*id);
+            self.set_expiration(*id, ttl);
+            self.stamp_created(*id);
+        }
+        self.id = first + ids.len() as i64;
+        ids
+    }
+}
+
+impl Database {
+    /// Adds several records to a store at once, all of them or none, as `Store::add_records` does.
+    ///
+    /// The `Before` triggers of every record run and every record is checked against the foreign keys
+    /// first; subscribers then get an `Insert` event per record and the `After` triggers run.
+    ///
+    /// # Returns
+    /// The IDs of the added records, in batch order.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist, and a `BatchError` listing every rejected
+    /// record, in which case nothing is added. If an `After` trigger fails, the batch is added all the
+    /// same and an `AfterTriggerError` holding every ID and the first trigger error is returned once all ran.
+    pub fn add_records(&mut self, store: &str, mut records: Vec<HashMap<String, Value>>) -> io::Result<Vec<i64>> {
+        self.store_mut(store)?;
+        let triggered = self.has_triggers(store, TriggerEvent::Add);
+        let mut errors = Vec::new();
+        for (index, record) in records.iter_mut().enumerate() {
+            let checked = if triggered {
+                self.run_before_triggers(store, TriggerEvent::Add, None, None, Some(record))
+            } else {
+                Ok(())
+            };
+            if let Err(e) = checked.and_then(|()| self.check_references(store, record)) {
+                errors.push(RowError::new(index, &e));
+            }
+        }
+        errors.extend(self.stores[store].check_batch(&mut records));
+        if !errors.is_empty() {
+            errors.sort_by_key(|error| error.row);
+            errors.dedup_by_key(|error| error.row);
+            return Err(BatchError { rows: errors }.into());
+        }
+
+        let new = self.changes.is_observed().then(|| records.clone());
+        let ids = self.store_mut(store)?.insert_batch(records);
+        for (id, record) in ids.iter().zip(new.into_iter().flatten()) {
+            self.record_changed(store, ChangeKind::Insert, *id, None, Some(record));
+        }
+
+        let mut result = Ok(());
+        if triggered {
+            for id in &ids {
+                result = result.and(self.run_after_triggers(store, TriggerEvent::Add, *id, None));
+            }
+        }
+        match result {
+            Ok(()) => Ok(ids),
+            Err(e) => Err(AfterTriggerError::new(ids, &e).into()),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use maplit::hashmap;
+    use serde_json::{json, Value};
+    use std::collections::{HashMap, HashSet};
+    use crate::memory::structure::Store;
+    use crate::memory::database::Database;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::batch::BatchError;
+    use crate::memory::triggers::{AfterTriggerError, Trigger, TriggerAction, TriggerEvent, TriggerTiming};
+    use crate::memory::changes::ChangeKind;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::test_dir::TempDir;
+
+    fn named(name: &str) -> HashMap<String, Value> {
+        hashmap! { "name".to_string() => json!(name) }
+    }
+
+    fn batch_error(error: &std::io::Error) -> &BatchError {
+        error.get_ref().and_then(|inner| inner.downcast_ref::<BatchError>()).unwrap()
+    }
+
+    #[test]
+    fn test_add_records_assigns_consecutive_ids() {
+        let mut store = Store::new().unwrap();
+        store.add_record(named("first")).unwrap();
+        let ids = store.add_records(vec![named("a"), hashmap! { "age".to_string() => json!(3) }, named("c")]).unwrap();
+
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(store.add_record(named("next")).unwrap(), 4);
+        assert_eq!(store.get_record(2).unwrap()["age"], json!(3));
+        assert!(store.attributes.contains("age"));
+    }
+
+    #[test]
+    fn test_rejected_rows_are_all_reported_and_nothing_is_added() {
+        let mut store = Store::new().unwrap();
+        store.set_id_strategy(IdStrategy::NaturalKey { attribute: "sku".to_string() }).unwrap();
+        store.add_record(hashmap! { "sku".to_string() => json!("A") }).unwrap();
+
+        let batch = vec![
+            hashmap! { "sku".to_string() => json!("B") },
+            hashmap! { "sku".to_string() => json!("A") },
+            named("no key"),
+            hashmap! { "sku".to_string() => json!("B") },
+        ];
+        let error = store.add_records(batch).unwrap_err();
+        let rows: Vec<usize> = batch_error(&error).rows.iter().map(|row| row.row).collect();
+
+        assert_eq!(rows, vec![2, 3, 4]);
+        assert!(error.to_string().starts_with("3 record(s) of the batch rejected; row 2:"));
+        assert_eq!(store.get_all_records().unwrap().len(), 1);
+        assert!(store.record_id("B").is_none());
+        assert_eq!(store.add_record(hashmap! { "sku".to_string() => json!("B") }).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_database_batch_checks_foreign_keys_and_notifies() {
+        let mut db = Database::new("batch_fk_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Cascade).unwrap();
+        let user = db.add_record("users", named("Ann")).unwrap();
+
+        let orphan = vec![hashmap! { "user_id".to_string() => json!(user) }, hashmap! { "user_id".to_string() => json!(42) }];
+        let error = db.add_records("orders", orphan).unwrap_err();
+        assert_eq!(batch_error(&error).rows[0].row, 2);
+        assert!(db.get_store("orders").unwrap().get_all_records().unwrap().is_empty());
+
+        let events = db.subscribe(Some("orders"));
+        let ids = db.add_records("orders", vec![hashmap! { "user_id".to_string() => json!(user) }; 3]).unwrap();
+        assert_eq!(events.try_iter().filter(|event| event.kind == ChangeKind::Insert).count(), ids.len());
+        assert!(db.add_records("missing", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_after_trigger_errors_keep_the_batch() {
+        let mut db = Database::new("batch_triggers_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.register_hook("fail", |_| Err(std::io::Error::other("notification failed")));
+        db.get_store_mut("users").unwrap().add_trigger(Trigger {
+            name: "notify".to_string(),
+            timing: TriggerTiming::After,
+            events: vec![TriggerEvent::Add],
+            action: TriggerAction::Call { hook: "fail".to_string() },
+        }).unwrap();
+
+        let error = db.add_records("users", vec![named("Ann"), named("Bob")]).unwrap_err();
+        assert_eq!(AfterTriggerError::of(&error).unwrap().ids, vec![0, 1]);
+        assert_eq!(db.get_store("users").unwrap().record_count(), 2);
+    }
+
+    #[test]
+    fn test_csv_import_is_all_or_nothing() {
+        let mut db = Database::new("batch_csv_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.get_store_mut("users").unwrap().set_id_strategy(IdStrategy::NaturalKey { attribute: "email".to_string() }).unwrap();
+        let dir = TempDir::new("batch_csv");
+        fs::write(dir.file("users.csv"), "email,name\na@x.io,Ann\nb@x.io,Bob\na@x.io,Again\n").unwrap();
+        let result = db.import_csv("users", &dir.file("users.csv"), ',');
+
+        assert!(result.unwrap_err().to_string().contains("row 3:"));
+        assert!(db.get_store("users").unwrap().get_all_records().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+use std::io;
+use std::cell::Cell;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Number, Value};
+use super::structure::Store;
+use super::trash::TrashedStore;
+use super::database::Database;
+use super::migration::{migrate_store, SNAPSHOT_VERSION};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Magic bytes opening every binary snapshot.
+pub const MAGIC: &[u8; 4] = b"RBDB";
+
+/// Maximum nesting of arrays and objects accepted when decoding a value.
+const MAX_DEPTH: usize = 128;
+
+const TAG_NULL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_I64: u8 = 3;
+const TAG_U64: u8 = 4;
+const TAG_F64: u8 = 5;
+const TAG_STRING: u8 = 6;
+const TAG_ARRAY: u8 = 7;
+const TAG_OBJECT: u8 = 8;
+
+thread_local! {
+    /// Whether stores serialized on this thread leave their records out, while a `Definition` is serialized.
+    static WITHOUT_RECORDS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Serializes the records of a store, or an empty map while a `Definition` is serialized.
+pub(super) fn serialize_values<S: Serializer>(values: &HashMap<i64, HashMap<String, Value>>, serializer: S) -> Result<S::Ok, S::Error> {
+    if WITHOUT_RECORDS.get() {
+        serializer.collect_map(std::iter::empty::<(i64, ())>())
+    } else {
+        values.serialize(serializer)
+    }
+}
+
+/// A borrowed view of a store's definition: the store serialized as a `Store`, every field included,
+/// but with no records, which a binary snapshot writes separately.
+struct Definition<'a>(&'a Store);
+
+impl Serialize for Definition<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        /// Restores the previous flag, also if serializing panics.
+        struct Restore(bool);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                WITHOUT_RECORDS.set(self.0);
+            }
+        }
+
+        let _restore = Restore(WITHOUT_RECORDS.replace(true));
+        self.0.serialize(serializer)
+    }
+}
+
+/// Incremental CRC-32 (IEEE 802.3) checksum.
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    fn new() -> Crc32 {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut crc = i as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            }
+            *entry = crc;
+        }
+        Crc32 { table, value: 0xFFFF_FFFF }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.value = self.table[((self.value ^ *byte as u32) & 0xFF) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.value ^ 0xFFFF_FFFF
+    }
+}
+
+/// A writer that checksums everything written through it.
+struct ChecksumWriter<W: Write> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader that checksums everything read through it.
+struct ChecksumReader<R: Read> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn corrupted(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("corrupted binary snapshot: {}", message))
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    let value = u32::try_from(value).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "length exceeds u32"))?;
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u32(writer, bytes.len())?;
+    writer.write_all(bytes)
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+    match value {
+        Value::Null => writer.write_all(&[TAG_NULL]),
+        Value::Bool(false) => writer.write_all(&[TAG_FALSE]),
+        Value::Bool(true) => writer.write_all(&[TAG_TRUE]),
+        Value::Number(number) => {
+            if let Some(int) = number.as_i64() {
+                writer.write_all(&[TAG_I64])?;
+                writer.write_all(&int.to_le_bytes())
+            } else if let Some(uint) = number.as_u64() {
+                writer.write_all(&[TAG_U64])?;
+                writer.write_all(&uint.to_le_bytes())
+            } else {
+                writer.write_all(&[TAG_F64])?;
+                writer.write_all(&number.as_f64().unwrap_or(0.0).to_le_bytes())
+            }
+        }
+        Value::String(text) => {
+            writer.write_all(&[TAG_STRING])?;
+            write_bytes(writer, text.as_bytes())
+        }
+        Value::Array(items) => {
+            writer.write_all(&[TAG_ARRAY])?;
+            write_u32(writer, items.len())?;
+            items.iter().try_for_each(|item| write_value(writer, item))
+        }
+        Value::Object(map) => {
+            writer.write_all(&[TAG_OBJECT])?;
+            write_u32(writer, map.len())?;
+            for (key, item) in map {
+                write_bytes(writer, key.as_bytes())?;
+                write_value(writer, item)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn read_array<const N: usize, R: Read>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes).map_err(|_| corrupted("unexpected end of file"))?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<usize> {
+    Ok(u32::from_le_bytes(read_array(reader)?) as usize)
+}
+
+/// Reads a length-prefixed byte string without trusting the length for the allocation.
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let length = read_u32(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(corrupted("unexpected end of file"));
+    }
+    Ok(bytes)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| corrupted("invalid UTF-8 string"))
+}
+
+fn read_value<R: Read>(reader: &mut R, depth: usize) -> io::Result<Value> {
+    if depth > MAX_DEPTH {
+        return Err(corrupted("values nested too deeply"));
+    }
+
+    let [tag] = read_array(reader)?;
+    Ok(match tag {
+        TAG_NULL => Value::Null,
+        TAG_FALSE => Value::Bool(false),
+        TAG_TRUE => Value::Bool(true),
+        TAG_I64 => Value::from(i64::from_le_bytes(read_array(reader)?)),
+        TAG_U64 => Value::from(u64::from_le_bytes(read_array(reader)?)),
+        TAG_F64 => Number::from_f64(f64::from_le_bytes(read_array(reader)?))
+            .map(Value::Number)
+            .ok_or_else(|| corrupted("non-finite number"))?,
+        TAG_STRING => Value::String(read_string(reader)?),
+        TAG_ARRAY => {
+            let length = read_u32(reader)?;
+            let mut items = Vec::new();
+            for _ in 0..length {
+                items.push(read_value(reader, depth + 1)?);
+            }
+            Value::Array(items)
+        }
+        TAG_OBJECT => {
+            let length = read_u32(reader)?;
+            let mut map = Map::new();
+            for _ in 0..length {
+                let key = read_string(reader)?;
+                map.insert(key, read_value(reader, depth + 1)?);
+            }
+            Value::Object(map)
+        }
+        _ => return Err(corrupted("unknown value tag")),
+    })
+}
+
+/// Writes one store: its name, its definition as JSON (everything but the records) and its records.
+fn write_store<W: Write>(writer: &mut W, name: &str, store: &Store) -> io::Result<()> {
+    write_bytes(writer, name.as_bytes())?;
+
+    // Serialize the definition without the records, which are written in binary below.
+    write_bytes(writer, &serde_json::to_vec(&Definition(store))?)?;
+
+    writer.write_all(&(store.values.len() as u64).to_le_bytes())?;
+    let mut ids: Vec<&i64> = store.values.keys().collect();
+    ids.sort();
+    for id in ids {
+        let record = &store.values[id];
+        writer.write_all(&id.to_le_bytes())?;
+        write_u32(writer, record.len())?;
+        for (key, value) in record {
+            write_bytes(writer, key.as_bytes())?;
+            write_value(writer, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads one store, upgrading its definition from the snapshot `version` it was written at.
+fn read_store<R: Read>(reader: &mut R, version: u32) -> io::Result<(String, Store)> {
+    let name = read_string(reader)?;
+    let mut store = serde_json::from_slice(&read_bytes(reader)?)
+        .map_err(io::Error::from)
+        .and_then(|definition| migrate_store(definition, version))
+        .map_err(|e| corrupted(&format!("invalid definition of store '{}': {}", name, e)))?;
+
+    let count = u64::from_le_bytes(read_array(reader)?);
+    for _ in 0..count {
+        let id = i64::from_le_bytes(read_array(reader)?);
+        let fields = read_u32(reader)?;
+        let mut record = HashMap::new();
+        for _ in 0..fields {
+            let key = read_string(reader)?;
+            record.insert(key, read_value(reader, 0)?);
+        }
+        store.values.insert(id, record);
+    }
+    Ok((name, store))
+}
+
+/// Writes a binary snapshot of the given stores and trashed stores, in name order; see `Database::write_binary`.
+pub(super) fn write_snapshot<W: Write>(writer: W, mut stores: Vec<(&String, &Store)>, mut trash: Vec<(&String, &TrashedStore)>) -> io::Result<()> {
+    let mut writer = ChecksumWriter { inner: writer, crc: Crc32::new() };
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(SNAPSHOT_VERSION as u16).to_le_bytes())?;
+
+    write_u32(&mut writer, stores.len())?;
+    stores.sort_by_key(|(name, _)| *name);
+    for (name, store) in stores {
+        write_store(&mut writer, name, store)?;
+    }
+
+    write_u32(&mut writer, trash.len())?;
+    trash.sort_by_key(|(name, _)| *name);
+    for (name, trashed) in trash {
+        writer.write_all(&trashed.deleted_at.to_le_bytes())?;
+        write_store(&mut writer, name, &trashed.store)?;
+    }
+
+    let checksum = writer.crc.finish();
+    writer.inner.write_all(&checksum.to_le_bytes())?;
+    writer.inner.flush()
+}
+
+impl Database {
+    /// Writes the database as a binary snapshot.
+    ///
+    /// The layout is the `RBDB` magic, a little-endian `u16` snapshot version, the stores, the
+    /// trashed stores (since version 8) and a trailing CRC-32 of everything before it. Each store
+    /// holds its name, its definition as JSON and its records as tagged binary values; a trashed
+    /// store is preceded by the `u64` time it was dropped.
+    pub fn write_binary<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_snapshot(writer, self.stores.iter().collect(), self.trash.iter().collect())
+    }
+
+    /// Reads a binary snapshot written by `write_binary`, streaming it from `reader`.
+    ///
+    /// Store definitions written by older versions are upgraded through the migration chain.
+    ///
+    /// # Errors
+    /// Returns an `InvalidData` error if the magic or checksum do not match, the version is
+    /// unsupported or the data is truncated.
+    pub fn read_binary<R: Read>(name: &str, reader: R) -> io::Result<Database> {
+        let mut reader = ChecksumReader { inner: reader, crc: Crc32::new() };
+        if &read_array::<4, _>(&mut reader)? != MAGIC {
+            return Err(corrupted("missing RBDB header"));
+        }
+        let version = u16::from_le_bytes(read_array(&mut reader)?) as u32;
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported binary snapshot version {}", version)));
+        }
+
+        let count = read_u32(&mut reader)?;
+        let mut stores = HashMap::new();
+        for _ in 0..count {
+            let (store_name, store) = read_store(&mut reader, version)?;
+            stores.insert(store_name, store);
+        }
+        let mut trash = HashMap::new();
+        if version >= 8 {
+            for _ in 0..read_u32(&mut reader)? {
+                let deleted_at = u64::from_le_bytes(read_array(&mut reader)?);
+                let (store_name, store) = read_store(&mut reader, version)?;
+                trash.insert(store_name, TrashedStore { deleted_at, store });
+            }
+        }
+
+        let expected = reader.crc.finish();
+        let checksum = u32::from_le_bytes(read_array(&mut reader.inner)?);
+        if checksum != expected {
+            return Err(corrupted("checksum mismatch"));
+        }
+
+        let mut database = Database { name: name.to_string(), stores, trash, ..Default::default() };
+        database.rebuild_key_indexes();
+        Ok(database)
+    }
+}
@@ -0,0 +1,93 @@
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Cascade).unwrap();
+        db.add_record("users", hashmap! {
+            "name".to_string() => json!("Alice"),
+            "score".to_string() => json!(-1.5),
+            "big".to_string() => json!(u64::MAX),
+            "meta".to_string() => json!({ "tags": ["a", null, true], "nested": { "n": 1 } }),
+        }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(0) }).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let db = create_test_database("binary_db");
+        let mut bytes = Vec::new();
+        db.write_binary(&mut bytes).unwrap();
+        assert_eq!(&bytes[..4], b"RBDB");
+
+        let loaded = Database::read_binary("binary_db", bytes.as_slice()).unwrap();
+        assert_eq!(loaded.stores, db.stores);
+    }
+
+    #[test]
+    fn test_binary_round_trip_keeps_every_store_setting() {
+        let mut db = create_test_database("binary_settings_db");
+        let users = db.get_store_mut("users").unwrap();
+        users.set_history(true);
+        users.set_metadata_tracking(true);
+        users.set_soft_delete(true);
+        users.set_trash_retention(Some(Duration::from_secs(60)));
+        users.set_default_ttl(Some(Duration::from_secs(3600)));
+        db.set_id_strategy("orders", IdStrategy::Uuid { attribute: "uuid".to_string() }).unwrap();
+        db.update_record("users", 0, hashmap! { "name".to_string() => json!("Alicia") }).unwrap();
+        let id = db.add_record("users", hashmap! { "name".to_string() => json!("Bob") }).unwrap();
+        db.delete_record("users", id).unwrap();
+
+        let mut bytes = Vec::new();
+        db.write_binary(&mut bytes).unwrap();
+        let loaded = Database::read_binary("binary_settings_db", bytes.as_slice()).unwrap();
+        assert_eq!(loaded.stores, db.stores);
+    }
+
+    #[test]
+    fn test_binary_detects_corruption() {
+        let db = create_test_database("binary_db");
+        let mut bytes = Vec::new();
+        db.write_binary(&mut bytes).unwrap();
+
+        let mut flipped = bytes.clone();
+        let middle = flipped.len() / 2;
+        flipped[middle] ^= 0x01;
+        assert!(Database::read_binary("binary_db", flipped.as_slice()).is_err());
+
+        assert!(Database::read_binary("binary_db", &bytes[..bytes.len() - 3]).is_err());
+        assert!(Database::read_binary("binary_db", &b"{\"users\": {}}"[..]).is_err());
+    }
+
+    #[test]
+    fn test_saving_replaces_the_other_format() {
+        let dir = TempDir::new("binary_load");
+        let mut db = create_test_database("binary_load_db");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Json).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Bob") }).unwrap();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        assert!(!dir.path().join("binary_load_db.json").exists());
+
+        let loaded = Database::load_in(dir.path(), "binary_load_db").unwrap();
+        assert_eq!(loaded.stores, db.stores);
+
+        // A leftover snapshot in the other format is not silently picked over the current one.
+        std::fs::copy(dir.file("binary_load_db.rbdb"), dir.file("binary_load_db.json")).unwrap();
+        let error = Database::load_in(dir.path(), "binary_load_db").err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
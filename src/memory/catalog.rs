@@ -0,0 +1,248 @@
+use std::io;
+use std::env;
+use std::fs::{self, File};
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+use super::binary::MAGIC;
+use std::io::{BufReader, Read};
+use super::database::Database;
+use std::path::{Path, PathBuf};
+use super::storage::SnapshotFormat;
+use std::collections::{BTreeSet, HashMap};
+
+/// Environment variable naming the data directory.
+pub const DATA_DIR_ENV: &str = "RUSTBASE_DATA_DIR";
+
+/// Command-line flag naming the data directory.
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+
+/// Name of the configuration file, looked up in the working directory and then in the home directory.
+pub const CONFIG_FILE: &str = "rustbase.conf";
+
+/// Maximum length of a database name.
+const MAX_NAME_LENGTH: usize = 64;
+
+/// Checks that a database name is safe to use as a file name in the data directory.
+///
+/// Names may only contain ASCII letters, digits, `_` and `-`, so they can never hold a path
+/// separator or `..` and escape the directory.
+///
+/// # Errors
+/// Returns an `InvalidInput` error describing why the name is rejected.
+pub fn validate_name(name: &str) -> io::Result<()> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid database name '{}': {}", name, reason));
+
+    if name.is_empty() {
+        return Err(invalid("the name is empty"));
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(invalid(&format!("the name is longer than {} characters", MAX_NAME_LENGTH)));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(invalid("only letters, digits, '_' and '-' are allowed"));
+    }
+    Ok(())
+}
+
+/// Reads the `data_dir` setting of a configuration file made of `key = value` lines.
+///
+/// Blank lines and lines starting with `#` are ignored. A relative directory is resolved
+/// against the directory holding the configuration file.
+///
+/// # Returns
+/// `None` if the file does not exist or has no `data_dir` setting.
+pub fn read_config(path: &Path) -> io::Result<Option<PathBuf>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "{}:{}: expected 'key = value'", path.display(), index + 1
+            )));
+        };
+        if key.trim() == "data_dir" {
+            let dir = PathBuf::from(value.trim().trim_matches('"'));
+            let base = path.parent().unwrap_or(Path::new(""));
+            return Ok(Some(base.join(dir)));
+        }
+    }
+    Ok(None)
+}
+
+/// Resolves the data directory from, in order of precedence, the `--data-dir` flag, the
+/// environment variable value and the first configuration file setting it.
+///
+/// # Returns
+/// The current working directory (an empty path) if none of them sets a directory.
+pub fn resolve_data_dir(args: &[String], env_value: Option<String>, config_files: &[PathBuf]) -> io::Result<PathBuf> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_FLAG {
+            return args.next().map(PathBuf::from).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidInput, format!("{} requires a directory", DATA_DIR_FLAG)
+            ));
+        }
+        if let Some(dir) = arg.strip_prefix(DATA_DIR_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+            return Ok(PathBuf::from(dir));
+        }
+    }
+
+    if let Some(dir) = env_value.filter(|dir| !dir.trim().is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+
+    for config in config_files {
+        if let Some(dir) = read_config(config)? {
+            return Ok(dir);
+        }
+    }
+    Ok(PathBuf::new())
+}
+
+/// The outline of a JSON snapshot: the versioned envelope, or the stores map of a version 1 file.
+/// Its fields are only parsed, never read.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+#[serde(untagged)]
+enum SnapshotOutline {
+    Versioned {
+        version: u32,
+        stores: HashMap<String, IgnoredAny>,
+    },
+    Legacy(HashMap<String, StoreOutline>),
+}
+
+/// The outline of a store in a version 1 JSON snapshot.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct StoreOutline {
+    values: IgnoredAny,
+}
+
+/// Checks whether a file holds a database snapshot in the format its extension names.
+fn is_snapshot(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else { return false };
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => serde_json::from_reader::<_, SnapshotOutline>(BufReader::new(file)).is_ok(),
+        Some("rbdb") => {
+            let mut header = [0u8; 6];
+            (&file).read_exact(&mut header).is_ok() && &header[..4] == MAGIC && header[4..] != [0, 0]
+        }
+        _ => false,
+    }
+}
+
+/// A `Catalog` is the set of databases saved in one data directory.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    pub dir: PathBuf,   // Directory holding the database snapshots.
+}
+
+impl Catalog {
+    /// Opens the catalog of a data directory, creating the directory if needed.
+    pub fn new(dir: PathBuf) -> io::Result<Catalog> {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(&dir)?;
+        }
+        Ok(Catalog { dir })
+    }
+
+    /// Opens the catalog configured by the process arguments, the `RUSTBASE_DATA_DIR`
+    /// environment variable or a `rustbase.conf` file, see [`resolve_data_dir`].
+    pub fn from_environment() -> io::Result<Catalog> {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let mut config_files = vec![PathBuf::from(CONFIG_FILE)];
+        if let Some(home) = env::var_os("HOME") {
+            config_files.push(Path::new(&home).join(format!(".{}", CONFIG_FILE)));
+        }
+        Catalog::new(resolve_data_dir(&args, env::var(DATA_DIR_ENV).ok(), &config_files)?)
+    }
+
+    /// Returns the paths of the snapshots of database `name` that exist on disk.
+    fn existing_snapshots(&self, name: &str) -> Vec<PathBuf> {
+        [SnapshotFormat::Json, SnapshotFormat::Binary].into_iter()
+            .map(|format| self.dir.join(format.path(name)))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Lists the names of the databases saved in the data directory, sorted.
+    ///
+    /// Only `.json` files holding a snapshot envelope and `.rbdb` files starting with a binary snapshot
+    /// header are listed, so other files with those extensions are not mistaken for databases.
+    pub fn list_databases(&self) -> io::Result<Vec<String>> {
+        let dir = if self.dir.as_os_str().is_empty() { Path::new(".") } else { self.dir.as_path() };
+        let mut names = BTreeSet::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_stem().and_then(|stem| stem.to_str());
+            if let Some(name) = name.filter(|name| validate_name(name).is_ok()) {
+                if path.is_file() && is_snapshot(&path) {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    /// Loads database `name` from its snapshot in the data directory.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the database has no snapshot.
+    pub fn load(&self, name: &str) -> io::Result<Database> {
+        Database::load_in(&self.dir, name)
+    }
+
+    /// Creates a new, unsaved database that will be saved in the data directory.
+    pub fn create(&self, name: &str) -> io::Result<Database> {
+        let mut database = Database::new(name)?;
+        database.dir = self.dir.clone();
+        Ok(database)
+    }
+
+    /// Deletes every snapshot of database `name`.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the database has no snapshot.
+    pub fn drop_database(&self, name: &str) -> io::Result<()> {
+        validate_name(name)?;
+        let snapshots = self.existing_snapshots(name);
+        if snapshots.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("database '{}' not found", name)));
+        }
+        snapshots.iter().try_for_each(fs::remove_file)
+    }
+
+    /// Renames every snapshot of database `name` to `new_name`.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the database has no snapshot and an `AlreadyExists`
+    /// error if a database called `new_name` already exists.
+    pub fn rename_database(&self, name: &str, new_name: &str) -> io::Result<()> {
+        validate_name(name)?;
+        validate_name(new_name)?;
+        let snapshots = self.existing_snapshots(name);
+        if snapshots.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("database '{}' not found", name)));
+        }
+        if !self.existing_snapshots(new_name).is_empty() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("database '{}' already exists", new_name)));
+        }
+
+        for format in [SnapshotFormat::Json, SnapshotFormat::Binary] {
+            let from = self.dir.join(format.path(name));
+            if from.is_file() {
+                fs::rename(from, self.dir.join(format.path(new_name)))?;
+            }
+        }
+        Ok(())
+    }
+}
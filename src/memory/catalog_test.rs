@@ -0,0 +1,73 @@
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::test_dir::TempDir;
+    use crate::memory::catalog::{resolve_data_dir, validate_name, Catalog};
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("my_db-2").is_ok());
+        for name in ["", "../etc", "a/b", "a\\b", "..", ".hidden", "name.json", "white space"] {
+            assert!(validate_name(name).is_err(), "{} should be rejected", name);
+        }
+        assert!(Database::new("../escape").is_err());
+        assert!(Database::load("../escape").is_err());
+    }
+
+    #[test]
+    fn test_resolve_data_dir_precedence() {
+        let temp = TempDir::new("config");
+        let dir = temp.path().to_path_buf();
+        let config = dir.join("rustbase.conf");
+        fs::write(&config, "# comment\n\ndata_dir = data\n").unwrap();
+        let configs = vec![dir.join("missing.conf"), config];
+
+        let args = vec!["--data-dir".to_string(), "from_flag".to_string()];
+        assert_eq!(resolve_data_dir(&args, Some("from_env".to_string()), &configs).unwrap(), PathBuf::from("from_flag"));
+        let args = vec!["--data-dir=flag2".to_string()];
+        assert_eq!(resolve_data_dir(&args, None, &configs).unwrap(), PathBuf::from("flag2"));
+        assert_eq!(resolve_data_dir(&[], Some("from_env".to_string()), &configs).unwrap(), PathBuf::from("from_env"));
+        assert_eq!(resolve_data_dir(&[], None, &configs).unwrap(), dir.join("data"));
+        assert_eq!(resolve_data_dir(&[], None, &[]).unwrap(), PathBuf::new());
+        assert!(resolve_data_dir(&["--data-dir".to_string()], None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_catalog_list_drop_and_rename() {
+        let temp = TempDir::new("catalog");
+        let dir = temp.path().to_path_buf();
+        let catalog = Catalog::new(dir.clone()).unwrap();
+
+        let mut db = catalog.create("shop").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.store_as(SnapshotFormat::Json).unwrap();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        catalog.create("blog").unwrap().store().unwrap();
+        fs::write(dir.join("notes.txt"), "not a database").unwrap();
+        fs::write(dir.join("package.json"), "{\"name\": \"web\", \"private\": true}").unwrap();
+        fs::write(dir.join("broken.json"), "{\"version\": 9, \"stores\":").unwrap();
+        fs::write(dir.join("blob.rbdb"), "not binary").unwrap();
+        fs::write(dir.join("legacy.json"), "{\"users\": {\"id\": 0, \"name\": \"users\", \"attributes\": [], \"values\": {}}}").unwrap();
+
+        assert!(dir.join("shop.rbdb").is_file() && !dir.join("shop.json").exists());
+        assert_eq!(catalog.list_databases().unwrap(), vec!["blog", "legacy", "shop"]);
+
+        assert!(catalog.rename_database("shop", "blog").is_err());
+        assert!(catalog.rename_database("shop", "../shop").is_err());
+        catalog.rename_database("shop", "store").unwrap();
+        assert_eq!(catalog.list_databases().unwrap(), vec!["blog", "legacy", "store"]);
+        let loaded = catalog.load("store").unwrap();
+        assert_eq!(loaded.dir, dir);
+        assert!(loaded.get_store("users").is_some());
+
+        catalog.drop_database("blog").unwrap();
+        assert!(catalog.drop_database("blog").is_err());
+        assert!(catalog.load("blog").is_err());
+        assert_eq!(catalog.list_databases().unwrap(), vec!["legacy", "store"]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::mem;
+use serde_json::Value;
+use std::collections::HashMap;
+use super::database::Database;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Enumeration representing the kind of a change event.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ChangeKind {
+    /// A record was added
+    Insert,
+    /// A record was replaced, or had a foreign key set to null
+    Update,
+    /// A record was deleted, expired or cascaded away
+    Delete,
+    /// A store was added
+    StoreCreated,
+    /// A store was dropped
+    StoreDropped,
+}
+
+impl ChangeKind {
+    /// Returns the name of the kind, as printed by `watch`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChangeKind::Insert => "insert",
+            ChangeKind::Update => "update",
+            ChangeKind::Delete => "delete",
+            ChangeKind::StoreCreated => "store_created",
+            ChangeKind::StoreDropped => "store_dropped",
+        }
+    }
+}
+
+/// A change made to a database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    pub sequence: u64,                          // Position of the event in the database's feed, starting at 0.
+    pub store: String,                          // Name of the store that changed.
+    pub kind: ChangeKind,                       // What happened.
+    pub id: Option<i64>,                        // ID of the record, `None` for store events.
+    pub old: Option<HashMap<String, Value>>,    // Record before the change, for updates and deletes.
+    pub new: Option<HashMap<String, Value>>,    // Record after the change, for inserts and updates.
+}
+
+/// Identifies a subscription so it can be cancelled.
+pub type SubscriptionId = u64;
+
+/// Where a subscription's events go.
+enum Sink {
+    Callback(Box<dyn FnMut(&ChangeEvent) + Send>),
+    Channel(Sender<ChangeEvent>),
+}
+
+/// A subscriber to a database's changes.
+struct Subscriber {
+    id: SubscriptionId,     // ID handed out by `subscribe` or `on_change`.
+    store: Option<String>,  // Store whose changes are wanted, `None` for every store.
+    sink: Sink,             // Where the events go.
+}
+
+/// The subscribers of a database and the sequence number of its next event.
+#[derive(Default)]
+pub struct ChangeFeed {
+    next_sequence: u64,                 // Sequence number of the next event.
+    next_subscription: SubscriptionId,  // ID of the next subscription.
+    subscribers: Vec<Subscriber>,       // Current subscribers.
+    captured: Option<Vec<ChangeEvent>>, // Events held back for another feed to publish, when capturing.
+}
+
+impl ChangeFeed {
+    /// Checks whether anyone would receive an event, so unobserved changes cost nothing.
+    pub(super) fn is_observed(&self) -> bool {
+        !self.subscribers.is_empty() || self.captured.is_some()
+    }
+
+    /// Holds back every event from now on instead of publishing it, see `take_captured`.
+    pub(super) fn capture(&mut self) {
+        self.captured = Some(Vec::new());
+    }
+
+    /// Returns the events held back since `capture`, which stops capturing.
+    pub(super) fn take_captured(&mut self) -> Vec<ChangeEvent> {
+        self.captured.take().unwrap_or_default()
+    }
+
+    /// Numbers an event and hands it to the subscribers watching its store, in subscription order.
+    ///
+    /// Subscribers whose channel receiver was dropped are removed.
+    pub(super) fn publish(&mut self, mut event: ChangeEvent) {
+        if let Some(captured) = self.captured.as_mut() {
+            captured.push(event);
+            return;
+        }
+        event.sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        self.subscribers.retain_mut(|subscriber| {
+            if subscriber.store.as_ref().is_some_and(|store| *store != event.store) {
+                return true;
+            }
+            match &mut subscriber.sink {
+                Sink::Callback(callback) => {
+                    callback(&event);
+                    true
+                }
+                Sink::Channel(sender) => sender.send(event.clone()).is_ok(),
+            }
+        });
+    }
+
+    /// Subscribes through a channel; see `Database::subscribe`.
+    pub(super) fn subscribe(&mut self, store: Option<&str>) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = channel();
+        self.add(store, Sink::Channel(sender));
+        receiver
+    }
+
+    /// Subscribes through a callback; see `Database::on_change`.
+    pub(super) fn on_change(&mut self, store: Option<&str>, callback: impl FnMut(&ChangeEvent) + Send + 'static) -> SubscriptionId {
+        self.add(store, Sink::Callback(Box::new(callback)))
+    }
+
+    /// Cancels a subscription; see `Database::unsubscribe`.
+    pub(super) fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|subscriber| subscriber.id != id);
+        self.subscribers.len() != before
+    }
+
+    fn add(&mut self, store: Option<&str>, sink: Sink) -> SubscriptionId {
+        let id = self.next_subscription;
+        self.next_subscription += 1;
+        self.subscribers.push(Subscriber { id, store: store.map(str::to_string), sink });
+        id
+    }
+}
+
+impl Database {
+    /// Subscribes to the changes of one store, or of every store, through a channel.
+    ///
+    /// Events arrive in the order the changes were made. Only changes made through `Database`
+    /// methods are reported, not those made on a `Store` directly. The subscription ends when the
+    /// receiver is dropped.
+    ///
+    /// # Arguments
+    /// * `store` - The store to watch; `None` watches every store.
+    pub fn subscribe(&mut self, store: Option<&str>) -> Receiver<ChangeEvent> {
+        self.changes.subscribe(store)
+    }
+
+    /// Subscribes to the changes of one store, or of every store, through a callback run as each change is made.
+    ///
+    /// # Arguments
+    /// * `store` - The store to watch; `None` watches every store.
+    /// * `callback` - The function receiving the events.
+    ///
+    /// # Returns
+    /// The ID to pass to `unsubscribe`.
+    pub fn on_change(&mut self, store: Option<&str>, callback: impl FnMut(&ChangeEvent) + Send + 'static) -> SubscriptionId {
+        self.changes.on_change(store, callback)
+    }
+
+    /// Cancels a subscription made with `on_change` or `subscribe`.
+    ///
+    /// # Returns
+    /// `true` if the subscription existed.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.changes.unsubscribe(id)
+    }
+
+    /// Returns a copy of a record to put in a change event, or `None` when nobody is watching.
+    pub(super) fn observed_record(&self, store: &str, id: i64) -> Option<HashMap<String, Value>> {
+        if !self.changes.is_observed() {
+            return None;
+        }
+        self.stores.get(store).and_then(|target| target.values.get(&id)).cloned()
+    }
+
+    /// Returns copies of the records of a store that `matches` selects, sorted by ID, to put in the
+    /// change events of a change to all of them; empty when nobody is watching.
+    pub(super) fn observed_records(&self, store: &str, matches: impl Fn(&HashMap<String, Value>) -> bool) -> Vec<(i64, HashMap<String, Value>)> {
+        let Some(target) = self.stores.get(store).filter(|_| self.changes.is_observed()) else { return Vec::new() };
+        let mut records: Vec<(i64, HashMap<String, Value>)> = target.values.iter()
+            .filter(|(_, record)| matches(record))
+            .map(|(id, record)| (*id, record.clone()))
+            .collect();
+        records.sort_by_key(|(id, _)| *id);
+        records
+    }
+
+    /// Reports an `Update` of each record taken by `observed_records`, with its value now.
+    pub(super) fn records_updated(&mut self, store: &str, old: Vec<(i64, HashMap<String, Value>)>) {
+        for (id, old) in old {
+            let new = self.stores.get(store).and_then(|target| target.values.get(&id)).cloned();
+            self.record_changed(store, ChangeKind::Update, id, Some(old), new);
+        }
+    }
+
+    /// Reports a record change to the subscribers, if there are any.
+    pub(super) fn record_changed(&mut self, store: &str, kind: ChangeKind, id: i64, old: Option<HashMap<String, Value>>, new: Option<HashMap<String, Value>>) {
+        if self.changes.is_observed() {
+            self.changes.publish(ChangeEvent { sequence: 0, store: store.to_string(), kind, id: Some(id), old, new });
+        }
+    }
+
+    /// Reports a store being created or dropped to the subscribers, if there are any.
+    pub(super) fn store_changed(&mut self, store: &str, kind: ChangeKind) {
+        if self.changes.is_observed() {
+            self.changes.publish(ChangeEvent { sequence: 0, store: store.to_string(), kind, id: None, old: None, new: None });
+        }
+    }
+
+    /// Moves the subscribers out of the database, leaving it unobserved.
+    pub(super) fn take_changes(&mut self) -> ChangeFeed {
+        mem::take(&mut self.changes)
+    }
+}
@@ -0,0 +1,162 @@
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::time::Duration;
+    use std::sync::{Arc, Mutex};
+    use std::collections::{HashMap, HashSet};
+    use crate::memory::database::Database;
+    use crate::memory::shared::SharedDatabase;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::changes::{ChangeEvent, ChangeKind};
+    use crate::memory::constraints::ReferentialAction;
+
+    fn create_test_database() -> Database {
+        let mut db = Database::new("changes_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Cascade).unwrap();
+        db
+    }
+
+    fn summarize(events: &[ChangeEvent]) -> Vec<(u64, String, ChangeKind, Option<i64>)> {
+        events.iter().map(|event| (event.sequence, event.store.clone(), event.kind, event.id)).collect()
+    }
+
+    #[test]
+    fn test_subscription_receives_ordered_events() {
+        let mut db = create_test_database();
+        let receiver = db.subscribe(None);
+
+        db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        db.update_record("users", 0, hashmap! { "name".to_string() => json!("alicia") }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(0) }).unwrap();
+        db.delete_record("users", 0).unwrap();
+        db.add_store("items".to_string(), HashSet::new()).unwrap();
+        db.delete_store("items").unwrap();
+
+        let events: Vec<ChangeEvent> = receiver.try_iter().collect();
+        assert_eq!(summarize(&events), vec![
+            (0, "users".to_string(), ChangeKind::Insert, Some(0)),
+            (1, "users".to_string(), ChangeKind::Update, Some(0)),
+            (2, "orders".to_string(), ChangeKind::Insert, Some(0)),
+            (3, "orders".to_string(), ChangeKind::Delete, Some(0)),
+            (4, "users".to_string(), ChangeKind::Delete, Some(0)),
+            (5, "items".to_string(), ChangeKind::StoreCreated, None),
+            (6, "items".to_string(), ChangeKind::StoreDropped, None),
+        ]);
+
+        assert_eq!(events[0].old, None);
+        assert_eq!(events[0].new, Some(hashmap! { "name".to_string() => json!("alice") }));
+        assert_eq!(events[1].old, Some(hashmap! { "name".to_string() => json!("alice") }));
+        assert_eq!(events[1].new, Some(hashmap! { "name".to_string() => json!("alicia") }));
+        assert_eq!(events[4].old, Some(hashmap! { "name".to_string() => json!("alicia") }));
+        assert_eq!(events[4].new, None);
+    }
+
+    #[test]
+    fn test_store_filter_and_set_null_updates() {
+        let mut db = Database::new("changes_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::SetNull).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(0) }).unwrap();
+
+        let orders = db.subscribe(Some("orders"));
+        db.add_record("users", hashmap! { "name".to_string() => json!("bob") }).unwrap();
+        db.delete_record("users", 0).unwrap();
+
+        let events: Vec<ChangeEvent> = orders.try_iter().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ChangeKind::Update);
+        assert_eq!(events[0].old, Some(hashmap! { "user_id".to_string() => json!(0) }));
+        assert_eq!(events[0].new, Some(hashmap! { "user_id".to_string() => json!(null) }));
+    }
+
+    #[test]
+    fn test_attribute_changes_and_generated_keys_publish_updates() {
+        let mut db = Database::new("changes_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        db.add_record("users", hashmap! { "age".to_string() => json!(30) }).unwrap();
+
+        let receiver = db.subscribe(None);
+        db.rename_attribute("users", "name", "full_name").unwrap();
+        db.drop_attribute("users", "age").unwrap();
+        db.set_id_strategy("users", IdStrategy::Uuid { attribute: "uuid".to_string() }).unwrap();
+
+        let events: Vec<ChangeEvent> = receiver.try_iter().collect();
+        assert_eq!(summarize(&events), vec![
+            (0, "users".to_string(), ChangeKind::Update, Some(0)),
+            (1, "users".to_string(), ChangeKind::Update, Some(1)),
+            (2, "users".to_string(), ChangeKind::Update, Some(0)),
+            (3, "users".to_string(), ChangeKind::Update, Some(1)),
+        ]);
+        assert_eq!(events[0].old, Some(hashmap! { "name".to_string() => json!("alice") }));
+        assert_eq!(events[0].new, Some(hashmap! { "full_name".to_string() => json!("alice") }));
+        assert_eq!(events[1].old, Some(hashmap! { "age".to_string() => json!(30) }));
+        assert_eq!(events[1].new, Some(HashMap::new()));
+        assert_eq!(events[2].old, Some(hashmap! { "full_name".to_string() => json!("alice") }));
+        assert!(events[2].new.as_ref().unwrap()["uuid"].is_string());
+        assert!(events[3].new.as_ref().unwrap()["uuid"].is_string());
+    }
+
+    #[test]
+    fn test_callbacks_and_unsubscribe() {
+        let mut db = create_test_database();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = db.on_change(Some("users"), move |event| sink.lock().unwrap().push(event.id));
+
+        db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        db.add_record_with_ttl("users", hashmap! { "name".to_string() => json!("bob") }, Some(Duration::ZERO)).unwrap();
+        assert_eq!(db.purge_expired(), 1);
+        assert!(db.unsubscribe(id));
+        assert!(!db.unsubscribe(id));
+        db.add_record("users", hashmap! { "name".to_string() => json!("carol") }).unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![Some(0), Some(1), Some(1)]);
+    }
+
+    #[test]
+    fn test_dropped_receivers_are_removed() {
+        let mut db = create_test_database();
+        drop(db.subscribe(None));
+        let receiver = db.subscribe(Some("users"));
+
+        db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        db.rename_store("users", "people").unwrap();
+
+        let kinds: Vec<ChangeKind> = receiver.try_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, vec![ChangeKind::Insert, ChangeKind::StoreDropped]);
+    }
+
+    #[test]
+    fn test_shared_database_publishes_changes_from_every_thread() {
+        let db = SharedDatabase::new(create_test_database());
+        let receiver = db.subscribe(Some("users")).unwrap();
+
+        let writers: Vec<_> = (0..4).map(|_| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for _ in 0..50 {
+                    db.add_record("users", hashmap! { "name".to_string() => json!("x") }).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        db.with_database(|database| database.delete_record("users", 0)).unwrap().unwrap();
+
+        let events: Vec<ChangeEvent> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 201);
+        assert!(events.windows(2).all(|pair| pair[0].sequence < pair[1].sequence));
+        // Inserts into one store are published in ID order, since the store stays locked until they are.
+        assert_eq!(events[..200].iter().map(|event| event.id.unwrap()).collect::<Vec<_>>(), (0..200).collect::<Vec<_>>());
+        assert_eq!(events[200].kind, ChangeKind::Delete);
+    }
+}
@@ -1,12 +1,14 @@
 use std::io;
+use regex::Regex;
 use std::cmp::Ordering;
 use super::structure::Store;
+use serde::{Deserialize, Serialize};
+use super::path::{resolve, root_attribute};
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 
 /// Enumeration representing different query operators for filtering values.
-#[derive(PartialEq, Debug, Eq)]
-#[allow(dead_code)]
+#[derive(PartialEq, Debug, Eq, Clone, Copy, Deserialize, Serialize)]
 pub enum QueryOperator {
     /// Equal to
     Eq,
@@ -22,25 +24,143 @@ pub enum QueryOperator {
     Le,
     /// Checks if a string contains a substring
     Contains,
+    /// Equal to one of the values of an array
+    In,
+    /// Equal to none of the values of an array
+    NotIn,
+    /// Between the two values of a `[low, high]` array, inclusive
+    Between,
+    /// Checks if a string starts with a prefix
+    StartsWith,
+    /// Checks if a string ends with a suffix
+    EndsWith,
+    /// Checks if a string contains a substring, ignoring case
+    IContains,
+    /// Matches a string against a SQL `LIKE` pattern (`%` any sequence, `_` any character)
+    Like,
+    /// Matches a string against a regular expression
+    Regex,
+    /// The attribute is present in the record
+    Exists,
+    /// The attribute is absent from the record
+    NotExists,
+    /// The attribute is present and null
+    IsNull,
+    /// An array attribute holds at least one of the values of an array
+    AnyOf,
+    /// An array attribute holds the value as one of its elements
+    ContainsElement,
+}
+
+/// Enumeration representing the aggregate functions that can be computed over records.
+#[derive(PartialEq, Debug, Eq, Clone, Copy)]
+pub enum AggregateFunction {
+    /// Number of records (or of records holding a non-null attribute)
+    Count,
+    /// Sum of the numeric values of an attribute
+    Sum,
+    /// Average of the numeric values of an attribute
+    Avg,
+    /// Smallest value of an attribute
+    Min,
+    /// Largest value of an attribute
+    Max,
+    /// Number of distinct values of an attribute
+    CountDistinct,
+}
+
+/// Compares two JSON values of the same kind (numbers or strings).
+///
+/// # Returns
+/// `None` when the values cannot be ordered against each other.
+pub(super) fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(num_a), Value::Number(num_b)) => {
+            num_a.as_f64().unwrap_or(0.0).partial_cmp(&num_b.as_f64().unwrap_or(0.0))
+        },
+        (Value::String(str_a), Value::String(str_b)) => Some(str_a.cmp(str_b)),
+        _ => None,
+    }
+}
+
+/// Checks that a value suits a query operator and compiles the pattern of a `Regex` condition.
+///
+/// # Errors
+/// Returns an `InvalidInput` error if the operator expects an array, a `[low, high]` pair or a valid pattern and `value` is not one.
+pub(super) fn prepare_condition(operator: &QueryOperator, value: &Value) -> io::Result<Option<Regex>> {
+    match (operator, value) {
+        (QueryOperator::In | QueryOperator::NotIn | QueryOperator::AnyOf, Value::Array(_)) => {},
+        (QueryOperator::In | QueryOperator::NotIn | QueryOperator::AnyOf, _) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "operator expects an array of values"));
+        },
+        (QueryOperator::Between, Value::Array(bounds)) if bounds.len() == 2 => {},
+        (QueryOperator::Between, _) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "between expects a [low, high] array"));
+        },
+        _ => {},
+    }
+
+    // Compile the regular expression once rather than per record.
+    Ok(match (operator, value) {
+        (QueryOperator::Regex, Value::String(expression)) => Some(Regex::new(expression)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?),
+        (QueryOperator::Regex, _) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "regex expects a string pattern"));
+        },
+        _ => None,
+    })
+}
+
+/// Checks whether a record satisfies an `attribute operator value` condition, as `query` does.
+///
+/// `pattern` is the compiled pattern returned by `prepare_condition`.
+pub(super) fn record_matches(record: &HashMap<String, Value>, attribute: &str, operator: &QueryOperator, value: &Value, pattern: Option<&Regex>) -> bool {
+    match resolve(record, attribute) {
+        None => *operator == QueryOperator::NotExists,
+        Some(attr_value) => match (operator, attr_value, value) {
+            (QueryOperator::Eq, a, b) => a == b,
+            (QueryOperator::Neq, a, b) => a != b,
+            (QueryOperator::Gt, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) > b.as_f64().unwrap_or(0.0),
+            (QueryOperator::Lt, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) < b.as_f64().unwrap_or(0.0),
+            (QueryOperator::Ge, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) >= b.as_f64().unwrap_or(0.0),
+            (QueryOperator::Le, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) <= b.as_f64().unwrap_or(0.0),
+            (QueryOperator::Contains, Value::String(a), Value::String(b)) => a.contains(b),
+            (QueryOperator::In, a, Value::Array(items)) => items.contains(a),
+            (QueryOperator::NotIn, a, Value::Array(items)) => !items.contains(a),
+            (QueryOperator::Between, a, Value::Array(bounds)) => {
+                compare_values(a, &bounds[0]).is_some_and(|ord| ord != Ordering::Less)
+                    && compare_values(a, &bounds[1]).is_some_and(|ord| ord != Ordering::Greater)
+            },
+            (QueryOperator::StartsWith, Value::String(a), Value::String(b)) => a.starts_with(b.as_str()),
+            (QueryOperator::EndsWith, Value::String(a), Value::String(b)) => a.ends_with(b.as_str()),
+            (QueryOperator::IContains, Value::String(a), Value::String(b)) => a.to_lowercase().contains(&b.to_lowercase()),
+            (QueryOperator::Like, Value::String(a), Value::String(b)) => like_matches(a, b),
+            (QueryOperator::Regex, Value::String(a), _) => pattern.is_some_and(|p| p.is_match(a)),
+            (QueryOperator::Exists, _, _) => true,
+            (QueryOperator::IsNull, a, _) => a.is_null(),
+            (QueryOperator::AnyOf, Value::Array(elements), Value::Array(items)) => elements.iter().any(|e| items.contains(e)),
+            (QueryOperator::ContainsElement, Value::Array(elements), b) => elements.contains(b),
+            _ => false,
+        },
+    }
 }
 
 impl Store {
     /// Filters values based on a single attribute and search value.
     ///
     /// # Arguments
-    /// * `attribute` - The attribute to filter by.
+    /// * `attribute` - The attribute or nested path to filter by.
     /// * `search_value` - The value to match.
     ///
     /// # Returns
     /// A `HashMap` containing the filtered results.
-    pub fn filter(&mut self, attribute: &str, search_value: &str) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
+    pub fn filter(&self, attribute: &str, search_value: &str) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
         let mut result = HashMap::new();
-        if self.attributes.contains(attribute) {
-            let all_values = self.values.clone();
-            for (id, data) in all_values {
-                if let Some(attr_value) = data.get(attribute) {
+        if root_attribute(&self.attributes, attribute).is_some_and(|root| self.attributes.contains(&root)) {
+            for (id, data) in self.live_values() {
+                if let Some(attr_value) = resolve(data, attribute) {
                     if attr_value == &json!(search_value) { // Fixed comparison
-                        result.insert(id, data);
+                        result.insert(*id, data.clone());
                     }
                 }
             }
@@ -52,37 +172,32 @@ impl Store {
 
     /// Filters values based on multiple attributes and their corresponding values.
     ///
+    /// Sets have no order, so a record matches when the values it holds at
+    /// `attributes` are exactly `search_values`, each used once.
+    ///
     /// # Arguments
-    /// * `attributes` - A set of attribute names.
+    /// * `attributes` - A set of attribute names or nested paths.
     /// * `search_values` - A set of values corresponding to the attributes.
     ///
     /// # Returns
     /// A `HashMap` containing the filtered results.
-    pub fn filter_attributes(&mut self, attributes: &HashSet<&str>, search_values: &HashSet<&str>) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
+    pub fn filter_attributes(&self, attributes: &HashSet<&str>, search_values: &HashSet<&str>) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
         let mut result = HashMap::new();
 
         if attributes.len() != search_values.len() {
             return Err(io::Error::new(io::ErrorKind::InvalidInput, "attributes and values must have the same length"));
         }
 
-        let all_values = self.values.clone();
-        for (id, data) in all_values {
-            let mut match_found = true;
-
-            for (attribute, search_value) in attributes.iter().zip(search_values.iter()) {
-                if let Some(attr_value) = data.get(*attribute) {
-                    if attr_value != search_value {
-                        match_found = false;
-                        break;
-                    }
-                } else {
-                    match_found = false;
-                    break;
-                }
-            }
+        for (id, data) in self.live_values() {
+            let mut unmatched = search_values.clone();
+            let match_found = attributes.iter().all(|attribute| {
+                let found = resolve(data, attribute)
+                    .and_then(|attr_value| unmatched.iter().copied().find(|search_value| attr_value == search_value));
+                found.is_some_and(|search_value| unmatched.remove(search_value))
+            });
 
             if match_found {
-                result.insert(id, data);
+                result.insert(*id, data.clone());
             }
         }
 
@@ -92,28 +207,29 @@ impl Store {
     /// Sorts records based on a given attribute in ascending or descending order.
     ///
     /// # Arguments
-    /// * `attribute` - The attribute to sort by.
+    /// * `attribute` - The attribute or nested path to sort by.
     /// * `ascending` - If `true`, sorts in ascending order; otherwise, sorts in descending order.
     ///
     /// # Returns
     /// A sorted vector of records.
-    pub fn sort_by(&mut self, attribute: &str, ascending: bool) -> io::Result<Vec<(i64, HashMap<String, Value>)>> {
-        let mut records: Vec<(i64, HashMap<String, Value>)> = self.values.clone().into_iter().collect();
+    pub fn sort_by(&self, attribute: &str, ascending: bool) -> io::Result<Vec<(i64, HashMap<String, Value>)>> {
+        let mut records: Vec<(i64, HashMap<String, Value>)> = self.live_values()
+            .map(|(id, record)| (*id, record.clone()))
+            .collect();
 
         records.sort_by(|a, b| {
-            let val_a = a.1.get(attribute);
-            let val_b = b.1.get(attribute);
+            let val_a = resolve(&a.1, attribute);
+            let val_b = resolve(&b.1, attribute);
 
+            // Records missing the attribute sort after the others, whatever the direction.
             match (val_a, val_b) {
-                (Some(Value::Number(num_a)), Some(Value::Number(num_b))) => {
-                    let ord = num_a.as_f64().unwrap_or(0.0).partial_cmp(&num_b.as_f64().unwrap_or(0.0)).unwrap_or(Ordering::Equal);
+                (Some(a), Some(b)) => {
+                    let ord = compare_values(a, b).unwrap_or(Ordering::Equal);
                     if ascending { ord } else { ord.reverse() }
                 },
-                (Some(Value::String(str_a)), Some(Value::String(str_b))) => {
-                    let ord = str_a.cmp(str_b);
-                    if ascending { ord } else { ord.reverse() }
-                },
-                _ => Ordering::Equal,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
             }
         });
 
@@ -123,34 +239,163 @@ impl Store {
     /// Queries the dataset using various operators (e.g., equality, greater than, etc.).
     ///
     /// # Arguments
-    /// * `attribute` - The attribute to query.
+    /// * `attribute` - The attribute to query, or a path into nested values (`address.city`, `tags[0]`, `/meta/score`).
     /// * `operator` - The comparison operator.
-    /// * `value` - The value to compare against.
+    /// * `value` - The value to compare against. `In`, `NotIn`, `AnyOf` and `Between` expect an array, and
+    ///   `Exists`, `NotExists` and `IsNull` ignore it.
     ///
     /// # Returns
     /// A `HashMap` containing the matching records.
-    pub fn query(&mut self, attribute: &str, operator: QueryOperator, value: Value) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
+    pub fn query(&self, attribute: &str, operator: QueryOperator, value: Value) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
+        let pattern = prepare_condition(&operator, &value)?;
         let mut result = HashMap::new();
+        for (id, data) in self.live_values() {
+            if record_matches(data, attribute, &operator, &value, pattern.as_ref()) {
+                result.insert(*id, data.clone());
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Computes an aggregate over the store's records, optionally grouped and filtered.
+    ///
+    /// # Arguments
+    /// * `function` - The aggregate function to compute.
+    /// * `attribute` - The attribute to aggregate. Only `Count` may omit it, in which case records are counted.
+    /// * `group_by` - The attributes to group records by. An empty slice yields a single group.
+    /// * `condition` - An optional `(attribute, operator, value)` condition records must satisfy, as in `query`.
+    ///
+    /// # Returns
+    /// A vector of `(group key, aggregate)` pairs sorted by group key, where the group key holds one value
+    /// per `group_by` attribute (`Value::Null` when a record lacks it).
+    pub fn aggregate(&self, function: AggregateFunction, attribute: Option<&str>, group_by: &[&str], condition: Option<(&str, QueryOperator, Value)>) -> io::Result<Vec<(Vec<Value>, Value)>> {
+        if attribute.is_none() && function != AggregateFunction::Count {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "attribute required for this aggregate function"));
+        }
+
+        let records = match condition {
+            Some((cond_attribute, operator, value)) => self.query(cond_attribute, operator, value)?,
+            None => self.get_all_records()?,
+        };
+
+        // Group records by the serialized group key so that `Value`s can be used as map keys.
+        type Group = (Vec<Value>, Vec<HashMap<String, Value>>);
+        let mut groups: HashMap<String, Group> = HashMap::new();
+        for (_, record) in records {
+            let key: Vec<Value> = group_by.iter()
+                .map(|attr| resolve(&record, attr).cloned().unwrap_or(Value::Null))
+                .collect();
+            groups.entry(serde_json::to_string(&key)?)
+                .or_insert_with(|| (key, Vec::new()))
+                .1
+                .push(record);
+        }
 
-        for (id, data) in self.values.clone() {
-            if let Some(attr_value) = data.get(attribute) {
-                let condition_met = match (&operator, attr_value, &value) {
-                    (QueryOperator::Eq, a, b) => a == b,
-                    (QueryOperator::Neq, a, b) => a != b,
-                    (QueryOperator::Gt, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) > b.as_f64().unwrap_or(0.0),
-                    (QueryOperator::Lt, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) < b.as_f64().unwrap_or(0.0),
-                    (QueryOperator::Ge, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) >= b.as_f64().unwrap_or(0.0),
-                    (QueryOperator::Le, Value::Number(a), Value::Number(b)) => a.as_f64().unwrap_or(0.0) <= b.as_f64().unwrap_or(0.0),
-                    (QueryOperator::Contains, Value::String(a), Value::String(b)) => a.contains(b),
-                    _ => false,
+        // Without grouping, an empty store still produces a single (empty) group.
+        if group_by.is_empty() && groups.is_empty() {
+            groups.insert(String::from("[]"), (Vec::new(), Vec::new()));
+        }
+
+        let mut result: Vec<(String, Vec<Value>, Value)> = groups.into_iter()
+            .map(|(serialized, (key, records))| {
+                let values: Vec<&Value> = match attribute {
+                    Some(attr) => records.iter()
+                        .filter_map(|record| resolve(record, attr))
+                        .filter(|value| !value.is_null())
+                        .collect(),
+                    None => Vec::new(),
+                };
+                let aggregate = match (function, attribute) {
+                    (AggregateFunction::Count, None) => json!(records.len()),
+                    (AggregateFunction::Count, Some(_)) => json!(values.len()),
+                    (AggregateFunction::Sum, _) => sum_values(&values),
+                    (AggregateFunction::Avg, _) => {
+                        let numbers: Vec<f64> = values.iter().filter_map(|value| value.as_f64()).collect();
+                        if numbers.is_empty() {
+                            Value::Null
+                        } else {
+                            json!(numbers.iter().sum::<f64>() / numbers.len() as f64)
+                        }
+                    },
+                    (AggregateFunction::Min, _) => extreme_value(&values, Ordering::Less),
+                    (AggregateFunction::Max, _) => extreme_value(&values, Ordering::Greater),
+                    (AggregateFunction::CountDistinct, _) => {
+                        let distinct: HashSet<String> = values.iter().map(|value| value.to_string()).collect();
+                        json!(distinct.len())
+                    },
                 };
+                (serialized, key, aggregate)
+            })
+            .collect();
 
-                if condition_met {
-                    result.insert(id, data);
-                }
-            }
+        result.sort_by(|a, b| compare_group_keys(&a.1, &b.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(result.into_iter().map(|(_, key, aggregate)| (key, aggregate)).collect())
+    }
+}
+
+/// Matches `text` against a SQL `LIKE` pattern, where `%` matches any sequence and `_` any single character.
+fn like_matches(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    // Position of the last `%` seen in the pattern and of the text when it was reached.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '_' || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '%' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
         }
+    }
 
-        Ok(result)
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+/// Orders group keys value by value: nulls, then booleans, numbers by value, strings, and other values last.
+fn compare_group_keys(a: &[Value], b: &[Value]) -> Ordering {
+    let rank = |value: &Value| match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        _ => 4,
+    };
+    a.iter().zip(b)
+        .map(|(x, y)| rank(x).cmp(&rank(y))
+            .then_with(|| compare_values(x, y).unwrap_or(Ordering::Equal))
+            .then_with(|| x.to_string().cmp(&y.to_string())))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Sums the numeric values, keeping an integer result when every value is an integer and the sum fits in one.
+fn sum_values(values: &[&Value]) -> Value {
+    let integers = values.iter().map(|value| value.as_i64()).try_fold(0i64, |sum, value| sum.checked_add(value?));
+    match integers {
+        Some(sum) => json!(sum),
+        None => json!(values.iter().filter_map(|value| value.as_f64()).sum::<f64>()),
+    }
+}
+
+/// Returns the value that compares as `wanted` against every other comparable value, or `Value::Null`.
+fn extreme_value(values: &[&Value], wanted: Ordering) -> Value {
+    let mut best: Option<&Value> = None;
+    for value in values {
+        best = match best {
+            None => Some(value),
+            Some(current) if compare_values(value, current) == Some(wanted) => Some(value),
+            Some(current) => Some(current),
+        };
     }
+    best.cloned().unwrap_or(Value::Null)
 }
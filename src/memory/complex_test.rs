@@ -1,7 +1,11 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use maplit::hashmap;
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+    use crate::memory::structure::Store;
+    use crate::memory::complex::{AggregateFunction, QueryOperator};
 
     fn create_test_store() -> Store {
         let mut store = Store::make_store(
@@ -36,21 +40,21 @@ mod tests {
 
     #[test]
     fn test_filter() {
-        let mut store = create_test_store();
+        let store = create_test_store();
         let result = store.filter("city", "Paris").unwrap();
         assert_eq!(result.len(), 2);
     }
 
     #[test]
     fn test_filter_non_existent_attribute() {
-        let mut store = create_test_store();
+        let store = create_test_store();
         let result = store.filter("country", "France");
         assert!(result.is_err());
     }
 
     #[test]
     fn test_filter_attributes() {
-        let mut store = create_test_store();
+        let store = create_test_store();
         let attributes: HashSet<&str> = ["name", "city"].iter().cloned().collect();
         let values: HashSet<&str> = ["Alice", "Paris"].iter().cloned().collect();
         let result = store.filter_attributes(&attributes, &values).unwrap();
@@ -59,7 +63,7 @@ mod tests {
 
     #[test]
     fn test_sort_by() {
-        let mut store = create_test_store();
+        let store = create_test_store();
         let result = store.sort_by("age", true).unwrap();
         let ages: Vec<i64> = result.iter().map(|(_, v)| v["age"].as_i64().unwrap()).collect();
         assert_eq!(ages, vec![25, 30, 35]);
@@ -67,15 +71,155 @@ mod tests {
 
     #[test]
     fn test_query_equal() {
-        let mut store = create_test_store();
+        let store = create_test_store();
         let result = store.query("age", QueryOperator::Eq, json!(30)).unwrap();
         assert_eq!(result.len(), 1);
     }
 
     #[test]
     fn test_query_greater_than() {
-        let mut store = create_test_store();
+        let store = create_test_store();
         let result = store.query("age", QueryOperator::Gt, json!(30)).unwrap();
         assert_eq!(result.len(), 1);
     }
+
+    #[test]
+    fn test_aggregate_count() {
+        let store = create_test_store();
+        let result = store.aggregate(AggregateFunction::Count, None, &[], None).unwrap();
+        assert_eq!(result, vec![(vec![], json!(3))]);
+    }
+
+    #[test]
+    fn test_aggregate_count_by_with_condition() {
+        let store = create_test_store();
+        let result = store.aggregate(AggregateFunction::Count, None, &["city"], Some(("age", QueryOperator::Gt, json!(25)))).unwrap();
+        assert_eq!(result, vec![(vec![json!("Paris")], json!(2))]);
+    }
+
+    #[test]
+    fn test_aggregate_numeric_functions() {
+        let store = create_test_store();
+        assert_eq!(store.aggregate(AggregateFunction::Sum, Some("age"), &[], None).unwrap()[0].1, json!(90));
+        assert_eq!(store.aggregate(AggregateFunction::Avg, Some("age"), &[], None).unwrap()[0].1, json!(30.0));
+        assert_eq!(store.aggregate(AggregateFunction::Min, Some("name"), &[], None).unwrap()[0].1, json!("Alice"));
+        assert_eq!(store.aggregate(AggregateFunction::Max, Some("age"), &[], None).unwrap()[0].1, json!(35));
+    }
+
+    #[test]
+    fn test_aggregate_sum_falls_back_to_float_on_overflow() {
+        let mut store = create_test_store();
+        store.add_record(hashmap! { "name".to_string() => json!("Dan"), "age".to_string() => json!(i64::MAX) }).unwrap();
+        let sum = store.aggregate(AggregateFunction::Sum, Some("age"), &[], None).unwrap()[0].1.clone();
+        assert_eq!(sum, json!(i64::MAX as f64 + 90.0));
+    }
+
+    #[test]
+    fn test_aggregate_grouped_distinct() {
+        let store = create_test_store();
+        let result = store.aggregate(AggregateFunction::CountDistinct, Some("name"), &["city"], None).unwrap();
+        assert_eq!(result, vec![
+            (vec![json!("London")], json!(1)),
+            (vec![json!("Paris")], json!(2)),
+        ]);
+    }
+
+    #[test]
+    fn test_aggregate_orders_numeric_groups_by_value() {
+        let mut store = create_test_store();
+        store.add_record(hashmap! { "name".to_string() => json!("Dan"), "age".to_string() => json!(9) }).unwrap();
+        store.add_record(hashmap! { "name".to_string() => json!("Eve"), "age".to_string() => json!(100) }).unwrap();
+        let result = store.aggregate(AggregateFunction::Count, None, &["age"], None).unwrap();
+        let ages: Vec<Value> = result.into_iter().map(|(key, _)| key[0].clone()).collect();
+        assert_eq!(ages, vec![json!(9), json!(25), json!(30), json!(35), json!(100)]);
+    }
+
+    #[test]
+    fn test_aggregate_requires_attribute() {
+        let store = create_test_store();
+        assert!(store.aggregate(AggregateFunction::Sum, None, &[], None).is_err());
+    }
+
+    #[test]
+    fn test_query_in_and_not_in() {
+        let store = create_test_store();
+        assert_eq!(store.query("age", QueryOperator::In, json!([25, 35])).unwrap().len(), 2);
+        assert_eq!(store.query("city", QueryOperator::NotIn, json!(["Paris"])).unwrap().len(), 1);
+        assert!(store.query("age", QueryOperator::In, json!(25)).is_err());
+    }
+
+    #[test]
+    fn test_query_between() {
+        let store = create_test_store();
+        assert_eq!(store.query("age", QueryOperator::Between, json!([25, 30])).unwrap().len(), 2);
+        assert_eq!(store.query("name", QueryOperator::Between, json!(["B", "Z"])).unwrap().len(), 2);
+        assert!(store.query("age", QueryOperator::Between, json!([25])).is_err());
+    }
+
+    #[test]
+    fn test_query_string_operators() {
+        let store = create_test_store();
+        assert_eq!(store.query("name", QueryOperator::StartsWith, json!("Ch")).unwrap().len(), 1);
+        assert_eq!(store.query("name", QueryOperator::EndsWith, json!("e")).unwrap().len(), 2);
+        assert_eq!(store.query("name", QueryOperator::IContains, json!("LI")).unwrap().len(), 2);
+        assert_eq!(store.query("city", QueryOperator::Like, json!("P_r%")).unwrap().len(), 2);
+        assert_eq!(store.query("city", QueryOperator::Like, json!("%on%n")).unwrap().len(), 1);
+        assert_eq!(store.query("city", QueryOperator::Like, json!("Par")).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_query_regex() {
+        let store = create_test_store();
+        assert_eq!(store.query("name", QueryOperator::Regex, json!("^(Alice|Bob)$")).unwrap().len(), 2);
+        assert!(store.query("name", QueryOperator::Regex, json!("(")).is_err());
+    }
+
+    #[test]
+    fn test_query_presence_operators() {
+        let mut store = create_test_store();
+        store.add_record(hashmap! {
+            "name".to_string() => json!("Dana"),
+            "age".to_string() => Value::Null,
+        }).unwrap();
+
+        assert_eq!(store.query("city", QueryOperator::Exists, Value::Null).unwrap().len(), 3);
+        assert_eq!(store.query("city", QueryOperator::NotExists, Value::Null).unwrap().len(), 1);
+        assert_eq!(store.query("age", QueryOperator::IsNull, Value::Null).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        let mut store = create_test_store();
+        store.add_record(hashmap! {
+            "name".to_string() => json!("Eve"),
+            "address".to_string() => json!({ "city": "Rome", "geo": { "lat": 41.9 } }),
+            "tags".to_string() => json!(["admin", "beta"]),
+        }).unwrap();
+        store.add_record(hashmap! {
+            "name".to_string() => json!("Finn"),
+            "address".to_string() => json!({ "city": "Oslo", "geo": { "lat": 59.9 } }),
+            "tags".to_string() => json!(["beta"]),
+        }).unwrap();
+
+        assert_eq!(store.filter("address.city", "Rome").unwrap().len(), 1);
+        assert_eq!(store.query("/address/geo/lat", QueryOperator::Gt, json!(50)).unwrap().len(), 1);
+        assert_eq!(store.query("tags[0]", QueryOperator::Eq, json!("beta")).unwrap().len(), 1);
+
+        let sorted = store.sort_by("address.geo.lat", false).unwrap();
+        assert_eq!(sorted[0].1["name"], json!("Finn"));
+
+        let grouped = store.aggregate(AggregateFunction::Count, None, &["address.city"], None).unwrap();
+        assert_eq!(grouped.len(), 3);
+    }
+
+    #[test]
+    fn test_query_array_operators() {
+        let mut store = create_test_store();
+        store.add_record(hashmap! { "tags".to_string() => json!(["admin", "beta"]) }).unwrap();
+        store.add_record(hashmap! { "tags".to_string() => json!(["beta"]) }).unwrap();
+
+        assert_eq!(store.query("tags", QueryOperator::ContainsElement, json!("admin")).unwrap().len(), 1);
+        assert_eq!(store.query("tags", QueryOperator::AnyOf, json!(["admin", "beta"])).unwrap().len(), 2);
+        assert_eq!(store.query("tags", QueryOperator::AnyOf, json!(["gamma"])).unwrap().len(), 0);
+    }
 }
@@ -0,0 +1,349 @@
+use std::io;
+use serde_json::Value;
+use super::structure::Store;
+use super::database::Database;
+use super::changes::ChangeKind;
+use super::triggers::{AfterTriggerError, TriggerEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Enumeration representing what happens to referencing records when a referenced record is deleted.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+pub enum ReferentialAction {
+    /// Refuse the deletion while referencing records exist
+    Restrict,
+    /// Delete the referencing records as well
+    Cascade,
+    /// Set the referencing attribute to null
+    SetNull,
+}
+
+impl ReferentialAction {
+    /// Returns the SQL keyword for the action, as used in `ON DELETE` clauses.
+    pub fn to_sql(self) -> &'static str {
+        match self {
+            ReferentialAction::Restrict => "RESTRICT",
+            ReferentialAction::Cascade => "CASCADE",
+            ReferentialAction::SetNull => "SET NULL",
+        }
+    }
+}
+
+/// A `ForeignKey` declares that an attribute of a store holds record IDs of another store.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct ForeignKey {
+    pub attribute: String,                  // Attribute of the referencing store.
+    pub references: String,                 // Name of the referenced store.
+    pub on_delete: ReferentialAction,       // Action applied when a referenced record is deleted.
+}
+
+/// Changes to apply once a deletion has been checked against every foreign key.
+struct DeletePlan {
+    deletions: HashSet<(String, i64)>,
+    nullifications: Vec<(String, i64, String)>,
+}
+
+impl Database {
+    /// Declares a foreign key from `store.attribute` to the record IDs of `references`.
+    ///
+    /// # Arguments
+    /// * `store` - The name of the referencing store.
+    /// * `attribute` - The attribute holding the referenced record IDs.
+    /// * `references` - The name of the referenced store.
+    /// * `on_delete` - The action applied when a referenced record or store is deleted.
+    ///
+    /// # Errors
+    /// Returns an error if either store does not exist or an existing record already violates the constraint.
+    pub fn add_foreign_key(&mut self, store: &str, attribute: &str, references: &str, on_delete: ReferentialAction) -> io::Result<()> {
+        if !self.stores.contains_key(references) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", references)));
+        }
+        let foreign_key = ForeignKey { attribute: attribute.to_string(), references: references.to_string(), on_delete };
+
+        let referencing = self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?;
+        for record in referencing.values.values() {
+            self.check_reference(&foreign_key, record)?;
+        }
+
+        let referencing = self.stores.get_mut(store).unwrap();
+        referencing.foreign_keys.retain(|fk| fk.attribute != attribute);
+        referencing.attributes.insert(attribute.to_string());
+        referencing.foreign_keys.push(foreign_key);
+        Ok(())
+    }
+
+    /// Removes the foreign key declared on `store.attribute`, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the store does not exist.
+    pub fn drop_foreign_key(&mut self, store: &str, attribute: &str) -> io::Result<()> {
+        let store = self.stores.get_mut(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?;
+        store.foreign_keys.retain(|fk| fk.attribute != attribute);
+        Ok(())
+    }
+
+    /// Adds a record to a store after running its `Before` triggers and checking it against its foreign keys,
+    /// then runs its `After` triggers.
+    ///
+    /// # Returns
+    /// Returns the ID of the newly added record.
+    ///
+    /// # Errors
+    /// Returns an error if the store does not exist or the record references a missing record, and an
+    /// `AfterTriggerError` if an `After` trigger fails, in which case the record is added all the same.
+    pub fn add_record(&mut self, store: &str, record: HashMap<String, Value>) -> io::Result<i64> {
+        self.add_record_with_ttl(store, record, None)
+    }
+
+    /// Replaces a record of a store after running its `Before` triggers and checking it against its foreign
+    /// keys, then runs its `After` triggers.
+    ///
+    /// # Errors
+    /// Returns an error if the store or record does not exist or the record references a missing record, and
+    /// an `AfterTriggerError` if an `After` trigger fails, in which case the record is replaced all the same.
+    pub fn update_record(&mut self, store: &str, id: i64, mut record: HashMap<String, Value>) -> io::Result<()> {
+        let triggered = self.has_triggers(store, TriggerEvent::Update);
+        let old = if triggered {
+            Some(self.store_mut(store)?.get_record(id)?.clone())
+        } else {
+            self.observed_record(store, id)
+        };
+        if triggered {
+            self.run_before_triggers(store, TriggerEvent::Update, Some(id), old.as_ref(), Some(&mut record))?;
+        }
+
+        self.check_references(store, &record)?;
+        let new = self.changes.is_observed().then(|| record.clone());
+        self.store_mut(store)?.update_record(id, record)?;
+        self.record_changed(store, ChangeKind::Update, id, old.clone().filter(|_| self.changes.is_observed()), new);
+        self.run_after_triggers(store, TriggerEvent::Update, id, old.as_ref())
+    }
+
+    /// Deletes a record from a store, applying the referential action of every foreign key pointing at it.
+    ///
+    /// Unlike `Store::delete_record`, which only sees its own store, this keeps other stores free of orphans.
+    ///
+    /// # Errors
+    /// Returns an error if the record does not exist or a `Restrict` foreign key still references it,
+    /// in which case nothing is deleted.
+    pub fn delete_record(&mut self, store: &str, id: i64) -> io::Result<()> {
+        self.delete_records(store, &[id])
+    }
+
+    /// Deletes several records from a store at once, applying the referential actions as `delete_record` does.
+    ///
+    /// The `Delete` triggers run on the records and on the records their deletion cascades to.
+    ///
+    /// # Errors
+    /// Returns an error if a record does not exist, a `Restrict` foreign key still references one or a
+    /// `Before` trigger rejects a deletion, in which case nothing is deleted. Returns an `AfterTriggerError`
+    /// with the first error of an `After` trigger once all ran; the records are deleted then.
+    pub fn delete_records(&mut self, store: &str, ids: &[i64]) -> io::Result<()> {
+        self.remove_records(store, ids, true)?.1
+    }
+
+    /// Deletes several records from a store as `delete_records` does, running the `Before` triggers
+    /// only if `before_triggers` is set.
+    ///
+    /// # Returns
+    /// The number of records deleted, including cascaded deletions, with the outcome of the `After` triggers.
+    pub(super) fn remove_records(&mut self, store: &str, ids: &[i64], before_triggers: bool) -> io::Result<(usize, io::Result<()>)> {
+        let target = self.store_mut(store)?;
+        if let Some(missing) = ids.iter().find(|id| !target.values.contains_key(id)) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Record {} not found", missing)));
+        }
+
+        let plan = self.plan_delete(ids.iter().map(|id| (store.to_string(), *id)).collect())?;
+        // The requested records come first, in the order given, then the cascaded ones.
+        let mut cascaded: Vec<&(String, i64)> = plan.deletions.iter().filter(|(name, id)| name != store || !ids.contains(id)).collect();
+        cascaded.sort();
+        let mut deleted = Vec::new();
+        for (name, id) in ids.iter().map(|id| (store, *id)).chain(cascaded.into_iter().map(|(name, id)| (name.as_str(), *id))) {
+            if self.has_triggers(name, TriggerEvent::Delete) {
+                let old = self.stores[name].values[&id].clone();
+                if before_triggers {
+                    self.run_before_triggers(name, TriggerEvent::Delete, Some(id), Some(&old), None)?;
+                }
+                deleted.push((name.to_string(), id, old));
+            }
+        }
+        let removed = plan.deletions.len();
+        self.apply_delete_plan(plan);
+
+        let mut result = Ok(());
+        for (name, id, old) in deleted {
+            result = result.and(self.run_after_triggers(&name, TriggerEvent::Delete, id, Some(&old)));
+        }
+        Ok((removed, result.map_err(|e| AfterTriggerError::new(ids.to_vec(), &e).into())))
+    }
+
+    /// Checks the foreign keys of a store before a store drop and applies their referential actions.
+    ///
+    /// Foreign keys of other stores that referenced the dropped store are removed with it.
+    ///
+    /// # Arguments
+    /// * `keep_records` - Leave the store's own records untouched, for a store moving to the trash.
+    pub(super) fn release_store_references(&mut self, name: &str, keep_records: bool) -> io::Result<()> {
+        let seeds = match self.stores.get(name) {
+            Some(store) => store.values.keys().map(|id| (name.to_string(), *id)).collect(),
+            None => return Ok(()),
+        };
+        let mut plan = self.plan_delete(seeds)?;
+        if keep_records {
+            plan.deletions.retain(|(store, _)| store != name);
+            plan.nullifications.retain(|(store, _, _)| store != name);
+        }
+        self.apply_delete_plan(plan);
+
+        for store in self.stores.values_mut() {
+            store.foreign_keys.retain(|fk| fk.references != name);
+        }
+        Ok(())
+    }
+
+    /// Returns the store names ordered so that referenced stores come before the stores referencing them.
+    pub fn stores_in_dependency_order(&self) -> Vec<&String> {
+        fn visit<'a>(database: &'a Database, name: &'a String, visited: &mut HashSet<&'a String>, ordered: &mut Vec<&'a String>) {
+            if !visited.insert(name) {
+                return;
+            }
+            let mut references: Vec<&String> = database.stores[name].foreign_keys.iter()
+                .filter_map(|fk| database.stores.get_key_value(&fk.references).map(|(key, _)| key))
+                .collect();
+            references.sort();
+            for reference in references {
+                visit(database, reference, visited, ordered);
+            }
+            ordered.push(name);
+        }
+
+        let mut names: Vec<&String> = self.stores.keys().collect();
+        names.sort();
+
+        let mut visited = HashSet::new();
+        let mut ordered = Vec::new();
+        for name in names {
+            visit(self, name, &mut visited, &mut ordered);
+        }
+        ordered
+    }
+
+    pub(super) fn store_mut(&mut self, name: &str) -> io::Result<&mut Store> {
+        self.stores.get_mut(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", name)))
+    }
+
+    /// Checks every foreign key of `store` against a record about to be written.
+    pub(super) fn check_references(&self, store: &str, record: &HashMap<String, Value>) -> io::Result<()> {
+        let store = self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?;
+        for foreign_key in &store.foreign_keys {
+            self.check_reference(foreign_key, record)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a record's foreign key attribute is absent, null, or the ID of an existing record.
+    fn check_reference(&self, foreign_key: &ForeignKey, record: &HashMap<String, Value>) -> io::Result<()> {
+        let value = match record.get(&foreign_key.attribute) {
+            None | Some(Value::Null) => return Ok(()),
+            Some(value) => value,
+        };
+        let exists = value.as_i64()
+            .zip(self.stores.get(&foreign_key.references))
+            .is_some_and(|(id, referenced)| referenced.values.contains_key(&id));
+
+        if exists {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "{} = {} does not reference an existing record of '{}'",
+                foreign_key.attribute, value, foreign_key.references
+            )))
+        }
+    }
+
+    /// Collects every deletion and nullification caused by deleting the `seeds`, following cascades.
+    fn plan_delete(&self, seeds: Vec<(String, i64)>) -> io::Result<DeletePlan> {
+        let mut deletions: HashSet<(String, i64)> = seeds.iter().cloned().collect();
+        let mut nullifications = Vec::new();
+        let mut pending = seeds;
+        // The referencing records of each foreign key by the ID they hold, built the first time the key is followed.
+        let mut indexes: HashMap<(&str, usize), HashMap<i64, Vec<i64>>> = HashMap::new();
+
+        while let Some((deleted_store, deleted_id)) = pending.pop() {
+            for (name, store) in &self.stores {
+                for (position, foreign_key) in store.foreign_keys.iter().enumerate().filter(|(_, fk)| fk.references == deleted_store) {
+                    let index = indexes.entry((name.as_str(), position)).or_insert_with(|| referencing_ids(store, foreign_key));
+                    for id in index.get(&deleted_id).into_iter().flatten() {
+                        let key = (name.clone(), *id);
+                        if deletions.contains(&key) {
+                            continue;
+                        }
+                        match foreign_key.on_delete {
+                            ReferentialAction::Restrict => {
+                                return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!(
+                                    "record {} of '{}' is still referenced by record {} of '{}'",
+                                    deleted_id, deleted_store, id, name
+                                )));
+                            }
+                            ReferentialAction::Cascade => {
+                                deletions.insert(key.clone());
+                                pending.push(key);
+                            }
+                            ReferentialAction::SetNull => {
+                                nullifications.push((name.clone(), *id, foreign_key.attribute.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(DeletePlan { deletions, nullifications })
+    }
+
+    fn apply_delete_plan(&mut self, plan: DeletePlan) {
+        for (store, id, attribute) in plan.nullifications {
+            if plan.deletions.contains(&(store.clone(), id)) {
+                continue;
+            }
+            let old = self.observed_record(&store, id);
+            if let Some(target) = self.stores.get_mut(&store) {
+                target.remember(id);
+            }
+            if let Some(record) = self.stores.get_mut(&store).and_then(|s| s.values.get_mut(&id)) {
+                record.insert(attribute, Value::Null);
+                let new = old.is_some().then(|| record.clone());
+                self.stores.get_mut(&store).unwrap().stamp_updated(id);
+                self.record_changed(&store, ChangeKind::Update, id, old, new);
+            }
+        }
+        let mut deletions: Vec<(String, i64)> = plan.deletions.into_iter().collect();
+        deletions.sort();
+        for (name, id) in deletions {
+            if let Some(store) = self.stores.get_mut(&name) {
+                let old = store.discard(id);
+                if old.is_some() {
+                    self.record_changed(&name, ChangeKind::Delete, id, old, None);
+                }
+            }
+        }
+    }
+}
+
+/// Maps each ID the foreign key attribute of `store` holds to the IDs of the records holding it, sorted.
+fn referencing_ids(store: &Store, foreign_key: &ForeignKey) -> HashMap<i64, Vec<i64>> {
+    let mut index: HashMap<i64, Vec<i64>> = HashMap::new();
+    for (id, record) in &store.values {
+        if let Some(referenced) = record.get(&foreign_key.attribute).and_then(Value::as_i64) {
+            index.entry(referenced).or_default().push(*id);
+        }
+    }
+    for ids in index.values_mut() {
+        ids.sort();
+    }
+    index
+}
@@ -0,0 +1,97 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(on_delete: ReferentialAction) -> Database {
+        let mut db = Database::new("constraints_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", on_delete).unwrap();
+
+        db.add_record("users", hashmap! { "name".to_string() => json!("Alice") }).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Bob") }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(0), "total".to_string() => json!(10) }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(1), "total".to_string() => json!(20) }).unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_insert_rejects_missing_reference() {
+        let mut db = create_test_database(ReferentialAction::Restrict);
+        assert!(db.add_record("orders", hashmap! { "user_id".to_string() => json!(42) }).is_err());
+        assert!(db.add_record("orders", hashmap! { "user_id".to_string() => Value::Null }).is_ok());
+    }
+
+    #[test]
+    fn test_update_rejects_missing_reference() {
+        let mut db = create_test_database(ReferentialAction::Restrict);
+        assert!(db.update_record("orders", 0, hashmap! { "user_id".to_string() => json!(9) }).is_err());
+        assert!(db.update_record("orders", 0, hashmap! { "user_id".to_string() => json!(1) }).is_ok());
+    }
+
+    #[test]
+    fn test_add_foreign_key_rejects_existing_orphans() {
+        let mut db = create_test_database(ReferentialAction::Restrict);
+        db.add_store("payments".to_string(), HashSet::new()).unwrap();
+        db.add_record("payments", hashmap! { "order_id".to_string() => json!(7) }).unwrap();
+        assert!(db.add_foreign_key("payments", "order_id", "orders", ReferentialAction::Restrict).is_err());
+    }
+
+    #[test]
+    fn test_delete_restrict() {
+        let mut db = create_test_database(ReferentialAction::Restrict);
+        assert!(db.delete_record("users", 0).is_err());
+        assert!(db.get_store("users").unwrap().values.contains_key(&0));
+        assert!(db.delete_store("users").is_err());
+    }
+
+    #[test]
+    fn test_delete_cascade() {
+        let mut db = create_test_database(ReferentialAction::Cascade);
+        db.delete_record("users", 0).unwrap();
+        let orders = &db.get_store("orders").unwrap().values;
+        assert_eq!(orders.len(), 1);
+        assert!(orders.contains_key(&1));
+    }
+
+    #[test]
+    fn test_delete_set_null() {
+        let mut db = create_test_database(ReferentialAction::SetNull);
+        db.delete_record("users", 1).unwrap();
+        assert_eq!(db.get_store("orders").unwrap().values[&1]["user_id"], Value::Null);
+    }
+
+    #[test]
+    fn test_delete_store_cascade_drops_constraint() {
+        let mut db = create_test_database(ReferentialAction::Cascade);
+        db.delete_store("users").unwrap();
+        let orders = db.get_store("orders").unwrap();
+        assert!(orders.values.is_empty());
+        assert!(orders.foreign_keys.is_empty());
+    }
+
+    #[test]
+    fn test_sql_foreign_key_clause() {
+        let db = create_test_database(ReferentialAction::Cascade);
+        let sql = db.convert_to_sql().unwrap();
+        assert!(sql.contains("CREATE TABLE orders (id INTEGER PRIMARY KEY, total TEXT, user_id TEXT, FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE);"));
+        assert!(sql.find("CREATE TABLE users").unwrap() < sql.find("CREATE TABLE orders").unwrap());
+    }
+
+    #[test]
+    fn test_foreign_keys_persisted() {
+        let dir = TempDir::new("constraints");
+        let mut db = create_test_database(ReferentialAction::SetNull);
+        db.dir = dir.path().to_path_buf();
+        db.store().unwrap();
+        let loaded = Database::load_in(dir.path(), "constraints_db").unwrap();
+        assert_eq!(loaded.get_store("orders").unwrap().foreign_keys, db.get_store("orders").unwrap().foreign_keys);
+    }
+}
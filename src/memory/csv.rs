@@ -0,0 +1,235 @@
+use std::io;
+use std::fs::File;
+use serde_json::{json, Value};
+use super::structure::Store;
+use super::database::Database;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use super::record_parser::infer_value;
+
+/// Enumeration representing when exported CSV fields are wrapped in quotes.
+#[derive(PartialEq, Debug, Eq, Clone, Copy)]
+pub enum QuoteStyle {
+    /// Only fields containing the delimiter, a quote or a line break
+    Necessary,
+    /// Every field
+    Always,
+}
+
+/// Options controlling how a store is written as CSV.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,                    // Field separator.
+    pub quote_style: QuoteStyle,            // When fields are quoted.
+    pub columns: Option<Vec<String>>,       // Columns to write, in order; all attributes sorted by name if `None`.
+    pub include_id: bool,                   // Whether to write the record ID as a leading `id` column.
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: ',', quote_style: QuoteStyle::Necessary, columns: None, include_id: false }
+    }
+}
+
+/// Reads one CSV record, following quoted fields across line breaks.
+///
+/// # Returns
+/// `None` at the end of the input, otherwise the fields of the record.
+fn read_csv_record<R: BufRead>(reader: &mut R, delimiter: char, line_number: &mut usize) -> io::Result<Option<Vec<String>>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut read_any = false;
+    let start_line = *line_number + 1;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            if in_quotes {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: unterminated quoted field", start_line)));
+            }
+            if !read_any {
+                return Ok(None);
+            }
+            break;
+        }
+        read_any = true;
+        *line_number += 1;
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (in_quotes, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => in_quotes = false,
+                (true, c) => field.push(c),
+                (false, '"') if field.is_empty() => in_quotes = true,
+                (false, c) if c == delimiter => fields.push(std::mem::take(&mut field)),
+                (false, '\n') => {}
+                (false, '\r') if chars.peek() == Some(&'\n') => {}
+                (false, c) => field.push(c),
+            }
+        }
+
+        if !in_quotes {
+            break;
+        }
+    }
+
+    fields.push(field);
+    Ok(Some(fields))
+}
+
+/// Infers a type for a whole CSV column so that every cell of the column gets the same JSON type.
+///
+/// The column is numeric or boolean only if every non-empty cell is; integers are widened to floats
+/// when mixed with them, and any other mix keeps the cells as strings.
+fn infer_column(cells: &[Option<&String>]) -> Vec<Option<Value>> {
+    let inferred: Vec<Option<Value>> = cells.iter().map(|cell| cell.map(|text| infer_value(text))).collect();
+    let values = || inferred.iter().flatten();
+
+    if values().all(|value| value.is_i64()) || values().all(|value| value.is_boolean()) {
+        inferred
+    } else if values().all(|value| value.is_number()) {
+        inferred.into_iter().map(|value| value.map(|v| json!(v.as_f64().unwrap_or(0.0)))).collect()
+    } else {
+        cells.iter().map(|cell| cell.map(|text| json!(text))).collect()
+    }
+}
+
+/// Parses CSV data with a header row into records, inferring a type per column.
+///
+/// Empty cells leave the attribute out of the record.
+///
+/// # Errors
+/// Returns an error, with the line number, for a missing or duplicated header, a row with
+/// more fields than the header, or an unterminated quoted field.
+pub fn parse_csv<R: BufRead>(mut reader: R, delimiter: char) -> io::Result<Vec<HashMap<String, Value>>> {
+    let mut line_number = 0;
+    let header = read_csv_record(&mut reader, delimiter, &mut line_number)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing CSV header"))?;
+
+    let mut seen = HashSet::new();
+    for name in &header {
+        if name.trim().is_empty() || !seen.insert(name.trim()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line 1: empty or duplicated column '{}'", name)));
+        }
+    }
+
+    let mut rows = Vec::new();
+    loop {
+        let row_line = line_number + 1;
+        let Some(row) = read_csv_record(&mut reader, delimiter, &mut line_number)? else { break };
+        if row.len() == 1 && row[0].trim().is_empty() {
+            continue; // Blank line
+        }
+        if row.len() > header.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "line {}: {} fields but the header has {}", row_line, row.len(), header.len()
+            )));
+        }
+        rows.push(row);
+    }
+
+    let mut records: Vec<HashMap<String, Value>> = vec![HashMap::new(); rows.len()];
+    for (column, name) in header.iter().enumerate() {
+        let cells: Vec<Option<&String>> = rows.iter()
+            .map(|row| row.get(column).filter(|cell| !cell.is_empty()))
+            .collect();
+        for (record, value) in records.iter_mut().zip(infer_column(&cells)) {
+            if let Some(value) = value {
+                record.insert(name.trim().to_string(), value);
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Formats a value as a CSV cell, quoting it when required.
+fn format_cell(value: Option<&Value>, options: &CsvOptions) -> String {
+    let text = match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    };
+
+    let needs_quotes = options.quote_style == QuoteStyle::Always
+        || text.contains(options.delimiter)
+        || text.contains(['"', '\n', '\r']);
+    if needs_quotes {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+impl Store {
+    /// Writes the store's records as CSV, ordered by record ID.
+    ///
+    /// Nested arrays and objects are written as JSON text and nulls as empty cells.
+    ///
+    /// # Returns
+    /// The number of records written.
+    pub fn write_csv<W: Write>(&self, mut writer: W, options: &CsvOptions) -> io::Result<usize> {
+        let columns: Vec<String> = match &options.columns {
+            Some(columns) => columns.clone(),
+            None => {
+                let mut columns: Vec<String> = self.attributes.iter().cloned().collect();
+                columns.sort();
+                columns
+            }
+        };
+
+        let mut header: Vec<String> = Vec::new();
+        if options.include_id {
+            header.push(format_cell(Some(&json!("id")), options));
+        }
+        header.extend(columns.iter().map(|column| format_cell(Some(&json!(column)), options)));
+        writeln!(writer, "{}", header.join(&options.delimiter.to_string()))?;
+
+        let mut ids: Vec<&i64> = self.live_values().map(|(id, _)| id).collect();
+        ids.sort();
+        for id in &ids {
+            let record = &self.values[*id];
+            let mut row: Vec<String> = Vec::new();
+            if options.include_id {
+                row.push(format_cell(Some(&json!(id)), options));
+            }
+            row.extend(columns.iter().map(|column| format_cell(record.get(column), options)));
+            writeln!(writer, "{}", row.join(&options.delimiter.to_string()))?;
+        }
+
+        writer.flush()?;
+        Ok(ids.len())
+    }
+
+    /// Exports the store to a CSV file.
+    ///
+    /// # Returns
+    /// The number of records written.
+    pub fn export_csv(&self, path: &str, options: &CsvOptions) -> io::Result<usize> {
+        self.write_csv(BufWriter::new(File::create(path)?), options)
+    }
+}
+
+impl Database {
+    /// Imports a CSV file with a header row into a store, creating the store if it does not exist.
+    ///
+    /// The whole file is parsed, then added as one batch: if a row is rejected, none is added, see `add_records`.
+    /// A file that cannot be parsed leaves the database untouched; a store the import creates has no schema,
+    /// triggers or foreign keys, so it accepts every row.
+    ///
+    /// # Returns
+    /// The number of records imported.
+    pub fn import_csv(&mut self, store: &str, path: &str, delimiter: char) -> io::Result<usize> {
+        let records = parse_csv(BufReader::new(File::open(path)?), delimiter)?;
+
+        if !self.stores.contains_key(store) {
+            self.add_store(store.to_string(), HashSet::new())?;
+        }
+        self.add_records(store, records).map(|ids| ids.len())
+    }
+}
@@ -0,0 +1,87 @@
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::structure::Store;
+    use crate::memory::database::Database;
+    use crate::memory::csv::{parse_csv, CsvOptions, QuoteStyle};
+    use crate::memory::test_dir::TempDir;
+
+    #[test]
+    fn test_parse_csv_infers_column_types() {
+        let data = "name,age,score,zip,active\nAlice,30,1.5,01234,true\nBob,25,2,75001,false\n";
+        let records = parse_csv(Cursor::new(data), ',').unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["age"], json!(30));
+        assert_eq!(records[1]["score"], json!(2.0));
+        assert_eq!(records[1]["zip"], json!("75001"));
+        assert_eq!(records[0]["active"], json!(true));
+    }
+
+    #[test]
+    fn test_parse_csv_quoted_fields() {
+        let data = "name;note\r\n\"Doe; John\";\"line one\nline \"\"two\"\"\"\r\nAnn;\r\n";
+        let records = parse_csv(Cursor::new(data), ';').unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["name"], json!("Doe; John"));
+        assert_eq!(records[0]["note"], json!("line one\nline \"two\""));
+        assert!(!records[1].contains_key("note"));
+    }
+
+    #[test]
+    fn test_parse_csv_errors() {
+        assert!(parse_csv(Cursor::new(""), ',').is_err());
+        assert!(parse_csv(Cursor::new("a,a\n1,2\n"), ',').is_err());
+
+        let error = parse_csv(Cursor::new("a,b\n1,2\n1,2,3\n"), ',').unwrap_err();
+        assert!(error.to_string().contains("line 3"));
+        assert!(parse_csv(Cursor::new("a\n\"open\n"), ',').is_err());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut store = Store::make_store("people".to_string(), HashSet::new()).unwrap();
+        store.add_record(hashmap! {
+            "name".to_string() => json!("Doe, John"),
+            "tags".to_string() => json!(["a", "b"]),
+        }).unwrap();
+        store.add_record(hashmap! { "name".to_string() => json!("Ann") }).unwrap();
+
+        let mut output = Vec::new();
+        store.write_csv(&mut output, &CsvOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "name,tags\n\"Doe, John\",\"[\"\"a\"\",\"\"b\"\"]\"\nAnn,\n");
+
+        let options = CsvOptions {
+            delimiter: '\t',
+            quote_style: QuoteStyle::Always,
+            columns: Some(vec!["name".to_string()]),
+            include_id: true,
+        };
+        let mut output = Vec::new();
+        store.write_csv(&mut output, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "\"id\"\t\"name\"\n\"0\"\t\"Doe, John\"\n\"1\"\t\"Ann\"\n");
+    }
+
+    #[test]
+    fn test_csv_round_trip_through_files() {
+        let mut db = Database::new("csv_db").unwrap();
+        db.add_store("people".to_string(), HashSet::new()).unwrap();
+        db.add_record("people", hashmap! {
+            "name".to_string() => json!("Doe, John"),
+            "bio".to_string() => json!("multi\nline"),
+            "age".to_string() => json!(41),
+        }).unwrap();
+
+        let dir = TempDir::new("csv_round_trip");
+        let path = dir.file("people.csv");
+        db.get_store("people").unwrap().export_csv(&path, &CsvOptions::default()).unwrap();
+        let imported = db.import_csv("copy", &path, ',');
+
+        assert_eq!(imported.unwrap(), 1);
+        let copy = db.get_store("copy").unwrap();
+        assert_eq!(copy.values[&0], db.get_store("people").unwrap().values[&0]);
+    }
+}
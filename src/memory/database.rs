@@ -1,11 +1,21 @@
 use std::io;
+use std::path::PathBuf;
 use super::structure::Store;
+use super::changes::{ChangeFeed, ChangeKind};
+use super::catalog::validate_name;
+use super::triggers::Hook;
+use super::trash::TrashedStore;
 use std::collections::{HashMap, HashSet};
 
 /// A `Database` is a collection of stores, each identified by a unique name.
+#[derive(Default)]
 pub struct Database {
     pub name: String,
     pub stores: HashMap<String, Store>,
+    pub dir: PathBuf,   // Data directory the database is saved in; empty for the working directory.
+    pub(super) changes: ChangeFeed,     // Subscribers to the database's changes.
+    pub(super) hooks: HashMap<String, Hook>,    // Functions `Call` triggers run, by name.
+    pub(super) trash: HashMap<String, TrashedStore>,    // Dropped stores that had soft deletes, by name.
 }
 
 impl Database {
@@ -14,10 +24,18 @@ impl Database {
     /// # Returns
     ///
     /// Returns a `Database` instance initialized with an empty store collection.
+    ///
+    /// # Errors
+    /// Returns an error if `name` is not a valid database name, see `validate_name`.
     pub fn new(name: &str) -> io::Result<Database> {
+        validate_name(name)?;
         Ok(Database {
             name: name.to_string(),
             stores: HashMap::new(),
+            dir: PathBuf::new(),
+            changes: ChangeFeed::default(),
+            hooks: HashMap::new(),
+            trash: HashMap::new(),
         })
     }
 
@@ -32,7 +50,8 @@ impl Database {
     pub fn add_store(&mut self, name: String, attributes: HashSet<String>) -> io::Result<()> {
         match Store::make_store(name.clone(), attributes) {
             Ok(store) => {
-                self.stores.insert(name, store);
+                self.stores.insert(name.clone(), store);
+                self.store_changed(&name, ChangeKind::StoreCreated);
                 Ok(())
             }
             Err(e) => Err(e),
@@ -68,9 +87,23 @@ impl Database {
     /// # Parameters
     /// - `name`: The name of the store to delete.
     ///
-    /// # Returns
-    /// This function does not return a value. The store is removed from the database.
-    pub fn delete_store(&mut self, name: &str) {
-        self.stores.remove(name);
+    /// The referential action of every foreign key pointing at the store is applied to the
+    /// referencing records, and those foreign keys are dropped along with the store. Subscribers get a
+    /// `Delete` event for every record of the store before the `StoreDropped` one.
+    ///
+    /// A store with soft deletes goes to the trash instead, records and all, see `restore_store`;
+    /// its subscribers only get the `StoreDropped` event.
+    ///
+    /// # Errors
+    /// Returns an error, leaving the database unchanged, if a `Restrict` foreign key still references a record of the store.
+    pub fn delete_store(&mut self, name: &str) -> io::Result<()> {
+        if self.stores.get(name).is_some_and(Store::soft_deletes) {
+            return self.trash_store(name);
+        }
+        self.release_store_references(name, false)?;
+        if self.stores.remove(name).is_some() {
+            self.store_changed(name, ChangeKind::StoreDropped);
+        }
+        Ok(())
     }
 }
@@ -1,7 +1,8 @@
 
 #[cfg(test)]
 mod database_tests {
-    use super::*;
+    use crate::memory::database::Database;
+    use crate::memory::test_dir::TempDir;
     use std::collections::{HashSet};
 
     fn setup_database() -> Database {
@@ -50,7 +51,7 @@ mod database_tests {
         db.add_store("places".to_string(), attributes).unwrap();
         assert!(db.get_store("places").is_some());
 
-        db.delete_store("places");
+        db.delete_store("places").unwrap();
         assert!(db.get_store("places").is_none());
     }
 
@@ -60,10 +61,12 @@ mod database_tests {
         let mut attributes = HashSet::new();
         attributes.insert("username".to_string());
 
+        let dir = TempDir::new("store_and_load");
+        db.dir = dir.path().to_path_buf();
         db.add_store("accounts".to_string(), attributes).unwrap();
         db.store().unwrap();
 
-        let loaded_db = Database::load("test_db").unwrap();
+        let loaded_db = Database::load_in(dir.path(), "test_db").unwrap();
         assert!(loaded_db.get_store("accounts").is_some());
     }
 }
@@ -0,0 +1,107 @@
+use std::io;
+use serde_json::Value;
+use std::collections::HashMap;
+use super::structure::Store;
+use super::database::Database;
+use super::ttl::now_millis;
+use serde::{Deserialize, Serialize};
+
+/// One version of a record, as kept by a store with history.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct HistoryEntry {
+    pub version: u64,                       // Version of the record, see `RecordMetadata`.
+    pub valid_from: u64,                    // Time this version was written, in Unix milliseconds.
+    pub valid_to: Option<u64>,              // Time it was replaced or deleted, `None` for the current version.
+    pub record: HashMap<String, Value>,     // Content of the record at this version.
+}
+
+impl Store {
+    /// Checks whether the store keeps the prior versions of its records.
+    pub fn keeps_history(&self) -> bool {
+        self.keep_history
+    }
+
+    /// Starts or stops keeping the prior versions of the store's records.
+    ///
+    /// History needs record versions, so starting it also starts `set_metadata_tracking`.
+    /// Stopping it discards the versions kept so far.
+    pub fn set_history(&mut self, enabled: bool) {
+        if enabled {
+            self.set_metadata_tracking(true);
+        } else {
+            self.history.clear();
+        }
+        self.keep_history = enabled;
+    }
+
+    /// Keeps the current version of a record before it is replaced or deleted, if the store has history.
+    pub(super) fn remember(&mut self, id: i64) {
+        if !self.keep_history {
+            return;
+        }
+        let (Some(record), Some(metadata)) = (self.values.get(&id), self.metadata.get(&id)) else { return };
+        let entry = HistoryEntry {
+            version: metadata.version,
+            valid_from: metadata.updated_at,
+            valid_to: Some(now_millis().max(metadata.updated_at)),
+            record: record.clone(),
+        };
+        self.history.entry(id).or_default().push(entry);
+    }
+
+    /// Returns every known version of a record, oldest first, ending with the current one unless it was deleted.
+    ///
+    /// The versions of deleted records stay available, so they can still be read as of an earlier time.
+    pub fn record_history(&self, id: i64) -> Vec<HistoryEntry> {
+        let mut entries = self.history.get(&id).cloned().unwrap_or_default();
+        if let (Some(record), Some(metadata)) = (self.values.get(&id), self.metadata.get(&id)) {
+            entries.push(HistoryEntry { version: metadata.version, valid_from: metadata.updated_at, valid_to: None, record: record.clone() });
+        }
+        entries
+    }
+
+    /// Returns a record as it was at a point in time.
+    ///
+    /// # Arguments
+    /// * `at` - The time, in Unix milliseconds.
+    ///
+    /// # Returns
+    /// The record, or `None` if it did not exist then or its history is not kept.
+    pub fn record_as_of(&self, id: i64, at: u64) -> Option<HashMap<String, Value>> {
+        let mut entries = self.record_history(id);
+        let index = entries.iter().rposition(|entry| entry.valid_from <= at)?;
+        // A later version means this one was still current then; otherwise it may have been deleted since.
+        let current = index + 1 < entries.len() || entries[index].valid_to.is_none_or(|valid_to| at < valid_to);
+        current.then(|| entries.swap_remove(index).record)
+    }
+
+    /// Returns a record as it was at one of its versions, or `None` if that version is not known.
+    pub fn record_at_version(&self, id: i64, version: u64) -> Option<HashMap<String, Value>> {
+        self.record_history(id).into_iter().find(|entry| entry.version == version).map(|entry| entry.record)
+    }
+}
+
+impl Database {
+    /// Brings a record back to the content of one of its prior versions, as a new version.
+    ///
+    /// The restore is an ordinary `update_record`: foreign keys, schema and triggers apply, and
+    /// the version being replaced is kept in the history too.
+    ///
+    /// # Returns
+    /// The record's new version.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store, the record or the version does not exist (deleted
+    /// records cannot be restored this way), and the errors of `update_record`.
+    pub fn restore_version(&mut self, store: &str, id: i64, version: u64) -> io::Result<u64> {
+        let target = self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?;
+        if target.get_record(id).is_err() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "Record not found"));
+        }
+        let record = target.record_at_version(id, version)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("record {} has no version {}", id, version)))?;
+        self.update_record(store, id, record)?;
+        Ok(self.stores[store].metadata(id).map_or(0, |metadata| metadata.version))
+    }
+}
@@ -0,0 +1,122 @@
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::time::Duration;
+    use std::collections::HashSet;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::database::Database;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("customers".to_string(), HashSet::new()).unwrap();
+        db.get_store_mut("customers").unwrap().set_history(true);
+        db
+    }
+
+    fn names(db: &Database, id: i64) -> Vec<(u64, serde_json::Value)> {
+        db.get_store("customers").unwrap().record_history(id).into_iter()
+            .map(|entry| (entry.version, entry.record["name"].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_updates_keep_prior_versions() {
+        let mut db = create_test_database("history_updates_db");
+        let id = db.add_record("customers", hashmap! { "name".to_string() => json!("acme") }).unwrap();
+        db.update_record("customers", id, hashmap! { "name".to_string() => json!("acme inc") }).unwrap();
+        db.update_record("customers", id, hashmap! { "name".to_string() => json!("acme corp") }).unwrap();
+
+        assert_eq!(names(&db, id), vec![(1, json!("acme")), (2, json!("acme inc")), (3, json!("acme corp"))]);
+        let customers = db.get_store("customers").unwrap();
+        assert_eq!(customers.record_at_version(id, 2).unwrap()["name"], json!("acme inc"));
+        assert!(customers.record_at_version(id, 4).is_none());
+        let history = customers.record_history(id);
+        assert!(history[..2].iter().all(|entry| entry.valid_to.is_some()));
+        assert!(history[2].valid_to.is_none());
+    }
+
+    #[test]
+    fn test_record_as_of_a_time() {
+        let mut db = create_test_database("history_as_of_db");
+        let id = db.add_record("customers", hashmap! { "name".to_string() => json!("first") }).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        db.update_record("customers", id, hashmap! { "name".to_string() => json!("second") }).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        db.delete_record("customers", id).unwrap();
+
+        let customers = db.get_store("customers").unwrap();
+        let history = customers.record_history(id);
+        assert_eq!(history.len(), 2);
+        assert!(customers.record_as_of(id, history[0].valid_from - 1).is_none());
+        assert_eq!(customers.record_as_of(id, history[0].valid_from).unwrap()["name"], json!("first"));
+        assert_eq!(customers.record_as_of(id, history[1].valid_from).unwrap()["name"], json!("second"));
+        assert!(customers.record_as_of(id, history[1].valid_to.unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_restore_makes_a_new_version() {
+        let mut db = create_test_database("history_restore_db");
+        let id = db.add_record("customers", hashmap! { "name".to_string() => json!("original") }).unwrap();
+        db.update_record("customers", id, hashmap! { "name".to_string() => json!("overwritten") }).unwrap();
+
+        assert_eq!(db.restore_version("customers", id, 1).unwrap(), 3);
+        assert_eq!(db.get_store("customers").unwrap().get_record(id).unwrap()["name"], json!("original"));
+        assert_eq!(names(&db, id).len(), 3);
+        assert!(db.restore_version("customers", id, 9).is_err());
+        db.delete_record("customers", id).unwrap();
+        assert!(db.restore_version("customers", id, 1).is_err());
+    }
+
+    #[test]
+    fn test_attribute_changes_and_generated_keys_make_new_versions() {
+        let mut db = create_test_database("history_alter_db");
+        let id = db.add_record("customers", hashmap! { "name".to_string() => json!("acme"), "city".to_string() => json!("oslo") }).unwrap();
+        let other = db.add_record("customers", hashmap! { "name".to_string() => json!("globex") }).unwrap();
+
+        db.drop_attribute("customers", "city").unwrap();
+        db.rename_attribute("customers", "name", "title").unwrap();
+        db.set_id_strategy("customers", IdStrategy::Ulid { attribute: "ulid".to_string() }).unwrap();
+
+        let customers = db.get_store("customers").unwrap();
+        assert_eq!(customers.record_at_version(id, 1).unwrap()["city"], json!("oslo"));
+        assert_eq!(customers.record_at_version(id, 2).unwrap()["name"], json!("acme"));
+        assert_eq!(customers.record_at_version(id, 3).unwrap()["title"], json!("acme"));
+        assert!(!customers.record_at_version(id, 3).unwrap().contains_key("ulid"));
+        assert!(customers.record_at_version(id, 4).unwrap()["ulid"].is_string());
+        assert_eq!(customers.record_history(other).len(), 3);
+    }
+
+    #[test]
+    fn test_cascades_are_kept_and_history_is_saved() {
+        let mut db = create_test_database("history_saved_db");
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.get_store_mut("orders").unwrap().set_history(true);
+        db.add_foreign_key("orders", "customer_id", "customers", ReferentialAction::SetNull).unwrap();
+        let customer = db.add_record("customers", hashmap! { "name".to_string() => json!("gone") }).unwrap();
+        let order = db.add_record("orders", hashmap! { "customer_id".to_string() => json!(customer) }).unwrap();
+        db.delete_record("customers", customer).unwrap();
+
+        let orders = db.get_store("orders").unwrap();
+        assert_eq!(orders.record_at_version(order, 1).unwrap()["customer_id"], json!(customer));
+        assert_eq!(orders.record_at_version(order, 2).unwrap()["customer_id"], json!(null));
+
+        let dir = TempDir::new("history_saved");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        let reloaded = Database::load_in(dir.path(), "history_saved_db").unwrap();
+        assert!(reloaded.get_store("customers").unwrap().keeps_history());
+        assert_eq!(names(&reloaded, customer), vec![(1, json!("gone"))]);
+        assert_eq!(reloaded.get_store("orders").unwrap().record_history(order), db.get_store("orders").unwrap().record_history(order));
+
+        let mut db = reloaded;
+        db.get_store_mut("orders").unwrap().set_metadata_tracking(false);
+        assert!(!db.get_store("orders").unwrap().keeps_history());
+        assert!(db.get_store("orders").unwrap().record_history(order).is_empty());
+    }
+}
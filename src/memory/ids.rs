@@ -0,0 +1,308 @@
+use std::io;
+use ulid::Ulid;
+use uuid::Uuid;
+use serde_json::Value;
+use std::collections::HashMap;
+use super::structure::Store;
+use super::database::Database;
+use serde::{Deserialize, Serialize};
+
+/// Enumeration representing how a store identifies its records.
+///
+/// Every record keeps an internal `i64` ID, which foreign keys hold; the other strategies add a key
+/// kept in an attribute of the record, so it travels with the record when it is exported or merged
+/// into another database.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Default)]
+pub enum IdStrategy {
+    /// The store's counter, as assigned by `add_record`
+    #[default]
+    Counter,
+    /// A random UUID (version 4), generated into the attribute unless the record brings one
+    Uuid { attribute: String },
+    /// A ULID, which sorts by creation time, generated into the attribute unless the record brings one
+    Ulid { attribute: String },
+    /// The value of the attribute, which every record must carry
+    NaturalKey { attribute: String },
+}
+
+impl IdStrategy {
+    /// Returns the attribute holding the key, or `None` for the counter.
+    pub fn key_attribute(&self) -> Option<&str> {
+        match self {
+            IdStrategy::Counter => None,
+            IdStrategy::Uuid { attribute } | IdStrategy::Ulid { attribute } | IdStrategy::NaturalKey { attribute } => Some(attribute),
+        }
+    }
+
+    /// Returns the name of the strategy, as used by the `id_strategy` command.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IdStrategy::Counter => "counter",
+            IdStrategy::Uuid { .. } => "uuid",
+            IdStrategy::Ulid { .. } => "ulid",
+            IdStrategy::NaturalKey { .. } => "key",
+        }
+    }
+
+    /// Returns the SQL type of the key column.
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            IdStrategy::Counter => "INTEGER",
+            IdStrategy::Uuid { .. } => "CHAR(36)",
+            IdStrategy::Ulid { .. } => "CHAR(26)",
+            IdStrategy::NaturalKey { .. } => "TEXT",
+        }
+    }
+
+    /// Generates a key for a record that does not bring one, for the strategies that can.
+    fn generate(&self) -> Option<String> {
+        match self {
+            IdStrategy::Uuid { .. } => Some(new_uuid()),
+            IdStrategy::Ulid { .. } => Some(new_ulid()),
+            _ => None,
+        }
+    }
+
+    /// Checks that a key has the shape the strategy produces.
+    fn accepts(&self, key: &str) -> bool {
+        match self {
+            IdStrategy::Counter => key.parse::<i64>().is_ok(),
+            IdStrategy::Uuid { .. } => key.len() == 36 && Uuid::try_parse(key).is_ok(),
+            IdStrategy::Ulid { .. } => Ulid::from_string(key).is_ok(),
+            IdStrategy::NaturalKey { .. } => !key.is_empty(),
+        }
+    }
+}
+
+/// Generates a random version 4 UUID, in its lowercase hyphenated form.
+pub fn new_uuid() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Generates a ULID: the current time in milliseconds followed by 80 random bits, in Crockford base 32.
+pub fn new_ulid() -> String {
+    Ulid::new().to_string()
+}
+
+/// Returns the key a value stands for: strings as they are, numbers in their JSON form, with whole
+/// floats written as integers so that `1.0` and `1` are the same key.
+pub fn key_of(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => match number.as_f64() {
+            Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() < i64::MAX as f64 => Some((float as i64).to_string()),
+            _ => Some(number.to_string()),
+        },
+        _ => None,
+    }
+}
+
+impl Store {
+    /// Returns how the store identifies its records.
+    pub fn id_strategy(&self) -> &IdStrategy {
+        &self.id_strategy
+    }
+
+    /// Changes how the store identifies its records.
+    ///
+    /// Records without a key get a generated one under `Uuid` and `Ulid`, and a new version, as with an update.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error, leaving the store unchanged, if a record lacks a natural key,
+    /// holds a key of the wrong shape, or shares its key with another record.
+    pub(super) fn set_id_strategy(&mut self, strategy: IdStrategy) -> io::Result<()> {
+        let mut index = HashMap::new();
+        let mut generated = Vec::new();
+        if let Some(attribute) = strategy.key_attribute() {
+            let mut ids: Vec<&i64> = self.values.keys().collect();
+            ids.sort();
+            for id in ids {
+                let key = match self.values[id].get(attribute) {
+                    Some(value) => key_of(value).filter(|key| strategy.accepts(key)),
+                    None => strategy.generate().inspect(|key| generated.push((*id, key.clone()))),
+                };
+                let key = key.ok_or_else(|| invalid(format!("record {} has no valid {} key in '{}'", id, strategy.name(), attribute)))?;
+                if index.insert(key.clone(), *id).is_some() {
+                    return Err(invalid(format!("key '{}' is shared by several records", key)));
+                }
+            }
+        }
+
+        if let Some(attribute) = strategy.key_attribute() {
+            self.attributes.insert(attribute.to_string());
+            for (id, key) in generated {
+                self.remember(id);
+                self.values.get_mut(&id).unwrap().insert(attribute.to_string(), Value::String(key));
+                self.stamp_updated(id);
+            }
+        }
+        self.id_strategy = strategy;
+        self.key_index = index;
+        self.rebuild_trash_key_index();
+        Ok(())
+    }
+
+    /// Finds the internal ID of a record from its key: the ID itself under `Counter`, the key attribute's value otherwise.
+    ///
+    /// Records in the trash are found too, so they can be restored by key.
+    pub fn record_id(&self, key: &str) -> Option<i64> {
+        if self.id_strategy.key_attribute().is_none() {
+            return key.parse().ok();
+        }
+        self.key_index.get(key).or_else(|| self.trash_key_index.get(key)).copied()
+    }
+
+    /// Returns the key of a record under the store's strategy.
+    pub fn record_key(&self, id: i64) -> Option<String> {
+        match self.id_strategy.key_attribute() {
+            None => self.values.contains_key(&id).then(|| id.to_string()),
+            Some(attribute) => self.values.get(&id).and_then(|record| record.get(attribute)).and_then(key_of),
+        }
+    }
+
+    /// Retrieves a record from the store by its key, see `record_id`.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if no record has this key.
+    pub fn get_record_by_key(&self, key: &str) -> io::Result<&HashMap<String, Value>> {
+        self.record_id(key)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Record not found"))
+            .and_then(|id| self.get_record(id))
+    }
+
+    /// Fills in and checks the key of a record about to be added.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error if the record has no valid key and none can be generated, and an
+    /// `AlreadyExists` error if another record, even one in the trash, has the same key.
+    pub(super) fn assign_key(&self, record: &mut HashMap<String, Value>) -> io::Result<()> {
+        let Some(attribute) = self.id_strategy.key_attribute() else { return Ok(()) };
+        if !record.contains_key(attribute) {
+            if let Some(key) = self.id_strategy.generate() {
+                record.insert(attribute.to_string(), Value::String(key));
+            }
+        }
+        self.check_key_free(record, None)
+    }
+
+    /// Keeps the key of a record being replaced: a record without the key attribute keeps the old one.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error if the new record changes the key.
+    pub(super) fn keep_key(&self, id: i64, record: &mut HashMap<String, Value>) -> io::Result<()> {
+        let Some(attribute) = self.id_strategy.key_attribute() else { return Ok(()) };
+        let Some(old) = self.values.get(&id).and_then(|old| old.get(attribute)) else { return Ok(()) };
+        match record.get(attribute) {
+            None => {
+                record.insert(attribute.to_string(), old.clone());
+                Ok(())
+            }
+            Some(new) if key_of(new) == key_of(old) => Ok(()),
+            Some(_) => Err(invalid(format!("the key '{}' of record {} cannot be changed", attribute, id))),
+        }
+    }
+
+    /// Checks that a record carries a valid key no other record has, other than the record `id` itself.
+    pub(super) fn check_key_free(&self, record: &HashMap<String, Value>, id: Option<i64>) -> io::Result<()> {
+        let Some(attribute) = self.id_strategy.key_attribute() else { return Ok(()) };
+        let key = record.get(attribute).and_then(key_of).filter(|key| self.id_strategy.accepts(key))
+            .ok_or_else(|| invalid(format!("the record has no valid {} key in '{}'", self.id_strategy.name(), attribute)))?;
+        match self.record_id(&key) {
+            Some(existing) if Some(existing) != id => {
+                Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("a record with {} '{}' already exists", attribute, key)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds a record that was just inserted to the key index.
+    pub(super) fn index_key(&mut self, id: i64) {
+        if let Some(key) = self.id_strategy.key_attribute().and_then(|_| self.record_key(id)) {
+            self.key_index.insert(key, id);
+        }
+    }
+
+    /// Removes a record about to be removed from the key index.
+    pub(super) fn unindex_key(&mut self, id: i64) {
+        if let Some(key) = self.id_strategy.key_attribute().and_then(|_| self.record_key(id)) {
+            self.key_index.remove(&key);
+        }
+    }
+
+    /// Adds a record that was just moved to the trash to the index of trashed keys.
+    pub(super) fn index_trashed_key(&mut self, id: i64) {
+        if let Some(key) = self.trashed_key(id) {
+            self.trash_key_index.insert(key, id);
+        }
+    }
+
+    /// Removes a record about to leave the trash from the index of trashed keys.
+    pub(super) fn unindex_trashed_key(&mut self, id: i64) {
+        if let Some(key) = self.trashed_key(id) {
+            self.trash_key_index.remove(&key);
+        }
+    }
+
+    /// Returns the key of a trashed record under the store's strategy, for key strategies.
+    fn trashed_key(&self, id: i64) -> Option<String> {
+        let attribute = self.id_strategy.key_attribute()?;
+        self.trash.get(&id).and_then(|trashed| trashed.record.get(attribute)).and_then(key_of)
+    }
+
+    /// Rebuilds the key index from the records, which is not saved with the store.
+    pub(super) fn rebuild_key_index(&mut self) {
+        self.key_index.clear();
+        let ids: Vec<i64> = self.values.keys().copied().collect();
+        for id in ids {
+            self.index_key(id);
+        }
+        self.rebuild_trash_key_index();
+    }
+
+    /// Rebuilds the index of trashed keys from the trash.
+    fn rebuild_trash_key_index(&mut self) {
+        self.trash_key_index.clear();
+        let ids: Vec<i64> = self.trash.keys().copied().collect();
+        for id in ids {
+            self.index_trashed_key(id);
+        }
+    }
+}
+
+impl Database {
+    /// Changes how a store identifies its records, as `Store::set_id_strategy` does, publishing an
+    /// `Update` event for every record given a generated key.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist, and the errors of `Store::set_id_strategy`.
+    pub fn set_id_strategy(&mut self, store: &str, strategy: IdStrategy) -> io::Result<()> {
+        let attribute = strategy.key_attribute().map(str::to_string);
+        let old = self.observed_records(store, |record| attribute.as_ref().is_some_and(|attribute| !record.contains_key(attribute)));
+        self.store_mut(store)?.set_id_strategy(strategy)?;
+        self.records_updated(store, old);
+        Ok(())
+    }
+
+    /// Deletes a record by its key, as `delete_record` does; see `Store::record_id`.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist or no record has this key, and the errors of `delete_record`.
+    pub fn delete_record_by_key(&mut self, store: &str, key: &str) -> io::Result<()> {
+        let id = self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?
+            .record_id(key)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Record '{}' not found", key)))?;
+        self.delete_record(store, id)
+    }
+
+    /// Rebuilds the key index of every store, after loading.
+    pub(super) fn rebuild_key_indexes(&mut self) {
+        for store in self.stores.values_mut() {
+            store.rebuild_key_index();
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
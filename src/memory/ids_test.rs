@@ -0,0 +1,116 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::structure::Store;
+    use crate::memory::database::Database;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::ids::{key_of, new_ulid, new_uuid, IdStrategy};
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str, strategy: IdStrategy) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("products".to_string(), HashSet::new()).unwrap();
+        db.get_store_mut("products").unwrap().set_id_strategy(strategy).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_generated_keys_are_well_formed_and_unique() {
+        let uuids: HashSet<String> = (0..1000).map(|_| new_uuid()).collect();
+        assert_eq!(uuids.len(), 1000);
+        for uuid in &uuids {
+            assert_eq!(uuid.len(), 36);
+            assert_eq!(&uuid[14..15], "4");
+            assert!("89ab".contains(&uuid[19..20]));
+        }
+
+        let first = new_ulid();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = new_ulid();
+        assert_eq!(first.len(), 26);
+        assert!(first < second);
+    }
+
+    #[test]
+    fn test_uuid_keys_are_generated_and_resolve() {
+        let mut db = create_test_database("ids_uuid_db", IdStrategy::Uuid { attribute: "uuid".to_string() });
+        let id = db.add_record("products", hashmap! { "name".to_string() => json!("lamp") }).unwrap();
+
+        let products = db.get_store("products").unwrap();
+        let key = products.record_key(id).unwrap();
+        assert_eq!(products.record_id(&key), Some(id));
+        assert_eq!(products.get_record_by_key(&key).unwrap()["name"], json!("lamp"));
+        assert!(products.record_id(&id.to_string()).is_none());
+
+        let clash = hashmap! { "name".to_string() => json!("copy"), "uuid".to_string() => json!(key) };
+        assert!(db.add_record("products", clash).is_err());
+        assert!(db.add_record("products", hashmap! { "uuid".to_string() => json!("not-a-uuid") }).is_err());
+    }
+
+    #[test]
+    fn test_natural_keys_are_required_and_kept() {
+        let mut db = create_test_database("ids_natural_db", IdStrategy::NaturalKey { attribute: "sku".to_string() });
+        assert!(db.add_record("products", hashmap! { "name".to_string() => json!("lamp") }).is_err());
+        let id = db.add_record("products", hashmap! { "sku".to_string() => json!("LMP-1"), "name".to_string() => json!("lamp") }).unwrap();
+
+        db.update_record("products", id, hashmap! { "name".to_string() => json!("desk lamp") }).unwrap();
+        assert_eq!(db.get_store("products").unwrap().get_record_by_key("LMP-1").unwrap()["name"], json!("desk lamp"));
+        assert!(db.update_record("products", id, hashmap! { "sku".to_string() => json!("LMP-2") }).is_err());
+
+        db.delete_record_by_key("products", "LMP-1").unwrap();
+        assert!(db.get_store("products").unwrap().record_id("LMP-1").is_none());
+        db.add_record("products", hashmap! { "sku".to_string() => json!("LMP-1") }).unwrap();
+    }
+
+    #[test]
+    fn test_numeric_keys_compare_by_value() {
+        assert_eq!(key_of(&json!(1.0)), Some("1".to_string()));
+        assert_eq!(key_of(&json!(1.5)), Some("1.5".to_string()));
+        assert_eq!(key_of(&json!("1.0")), Some("1.0".to_string()));
+
+        let mut db = create_test_database("ids_numeric_db", IdStrategy::NaturalKey { attribute: "sku".to_string() });
+        let id = db.add_record("products", hashmap! { "sku".to_string() => json!(1) }).unwrap();
+        assert!(db.add_record("products", hashmap! { "sku".to_string() => json!(1.0) }).is_err());
+        db.update_record("products", id, hashmap! { "sku".to_string() => json!(1.0), "name".to_string() => json!("lamp") }).unwrap();
+        assert_eq!(db.get_store("products").unwrap().record_id("1"), Some(id));
+    }
+
+    #[test]
+    fn test_changing_strategy_checks_existing_records() {
+        let mut db = Database::new("ids_change_db").unwrap();
+        db.add_store("products".to_string(), HashSet::new()).unwrap();
+        let first = db.add_record("products", hashmap! { "sku".to_string() => json!("A") }).unwrap();
+        let second = db.add_record("products", hashmap! { "sku".to_string() => json!("A") }).unwrap();
+
+        let products = db.get_store_mut("products").unwrap();
+        assert!(products.set_id_strategy(IdStrategy::NaturalKey { attribute: "sku".to_string() }).is_err());
+        assert_eq!(products.id_strategy(), &IdStrategy::Counter);
+
+        products.set_id_strategy(IdStrategy::Ulid { attribute: "ulid".to_string() }).unwrap();
+        let keys: HashSet<String> = [first, second].iter().map(|id| products.record_key(*id).unwrap()).collect();
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn test_key_index_is_rebuilt_on_load() {
+        let mut db = create_test_database("ids_saved_db", IdStrategy::NaturalKey { attribute: "sku".to_string() });
+        let id = db.add_record("products", hashmap! { "sku".to_string() => json!(42) }).unwrap();
+        let dir = TempDir::new("ids_saved");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        let reloaded = Database::load_in(dir.path(), "ids_saved_db").unwrap();
+
+        assert_eq!(reloaded.get_store("products").unwrap().record_id("42"), Some(id));
+    }
+
+    #[test]
+    fn test_key_is_the_sql_primary_key() {
+        let mut store = Store::make_store("test".to_string(), vec!["name"].into_iter().map(String::from).collect()).unwrap();
+        store.set_id_strategy(IdStrategy::Uuid { attribute: "uuid".to_string() }).unwrap();
+        let sql = store.to_sql_create_table("products").unwrap();
+        assert_eq!(sql, "CREATE TABLE products (id INTEGER NOT NULL UNIQUE, uuid CHAR(36) PRIMARY KEY, name TEXT);");
+    }
+}
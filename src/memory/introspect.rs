@@ -0,0 +1,170 @@
+use std::io;
+use std::fs;
+use std::mem::size_of;
+use serde_json::Value;
+use std::cmp::Ordering;
+use super::structure::Store;
+use super::database::Database;
+use super::complex::compare_values;
+use super::storage::SnapshotFormat;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// What a store's records actually hold under one attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSummary {
+    pub name: String,                           // Attribute name.
+    pub types: BTreeMap<&'static str, usize>,   // Number of values of each observed JSON type.
+    pub present: usize,                         // Number of records holding the attribute.
+    pub distinct: usize,                        // Number of distinct values.
+    pub min: Option<Value>,                     // Smallest value, numbers first, then strings.
+    pub max: Option<Value>,                     // Largest value, numbers first, then strings.
+}
+
+/// Size figures of one store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreStats {
+    pub name: String,                   // Store name.
+    pub records: usize,                 // Number of records.
+    pub attributes: usize,              // Number of attributes.
+    pub memory_bytes: usize,            // Approximate in-memory footprint.
+    pub snapshot_bytes: usize,          // Size of the store in a JSON snapshot.
+}
+
+/// Size figures of a database and its stores.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseStats {
+    pub stores: Vec<StoreStats>,                        // Per-store figures, sorted by store name.
+    pub snapshot_files: Vec<(String, u64)>,             // Saved snapshot files and their sizes on disk.
+}
+
+/// Returns the name of the JSON type of a value, distinguishing integers from floats.
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(number) if number.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Estimates the heap and inline memory used by a JSON value.
+fn approximate_value_size(value: &Value) -> usize {
+    size_of::<Value>() + match value {
+        Value::String(text) => text.capacity(),
+        Value::Array(items) => items.iter().map(approximate_value_size).sum(),
+        Value::Object(map) => map.iter().map(|(key, item)| size_of::<String>() + key.capacity() + approximate_value_size(item)).sum(),
+        _ => 0,
+    }
+}
+
+/// Keeps the smaller (or larger) of two values when they can be ordered against each other.
+fn keep_extreme(current: &mut Option<Value>, candidate: &Value, wanted: Ordering) {
+    match current {
+        Some(value) if compare_values(candidate, value) != Some(wanted) => {}
+        _ => *current = Some(candidate.clone()),
+    }
+}
+
+impl Store {
+    /// Returns the number of records in the store.
+    pub fn record_count(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the number of attributes of the store.
+    pub fn attribute_count(&self) -> usize {
+        self.attributes.len()
+    }
+
+    /// Summarizes every attribute of the store from the values its records hold, sorted by attribute name.
+    ///
+    /// Attributes that appear in records but not in the store's attribute set are included too.
+    pub fn describe(&self) -> Vec<AttributeSummary> {
+        let mut names: BTreeSet<&String> = self.attributes.iter().collect();
+        names.extend(self.values.values().flat_map(|record| record.keys()));
+
+        names.into_iter().map(|name| {
+            let mut types = BTreeMap::new();
+            let mut distinct = HashSet::new();
+            let (mut min_number, mut max_number, mut min_string, mut max_string) = (None, None, None, None);
+            let mut present = 0;
+
+            for value in self.values.values().filter_map(|record| record.get(name)) {
+                present += 1;
+                *types.entry(type_name(value)).or_insert(0) += 1;
+                distinct.insert(value.to_string());
+                match value {
+                    Value::Number(_) => {
+                        keep_extreme(&mut min_number, value, Ordering::Less);
+                        keep_extreme(&mut max_number, value, Ordering::Greater);
+                    }
+                    Value::String(_) => {
+                        keep_extreme(&mut min_string, value, Ordering::Less);
+                        keep_extreme(&mut max_string, value, Ordering::Greater);
+                    }
+                    _ => {}
+                }
+            }
+
+            AttributeSummary {
+                name: name.clone(),
+                types,
+                present,
+                distinct: distinct.len(),
+                min: min_number.or(min_string),
+                max: max_number.or(max_string),
+            }
+        }).collect()
+    }
+
+    /// Estimates the memory used by the store's records and attribute set.
+    ///
+    /// The figure counts keys, values and strings but not hash table overhead, so it is a lower bound.
+    pub fn approximate_memory(&self) -> usize {
+        let attributes: usize = self.attributes.iter().map(|name| size_of::<String>() + name.capacity()).sum();
+        let records: usize = self.values.values()
+            .map(|record| size_of::<i64>() + record.iter()
+                .map(|(key, value)| size_of::<String>() + key.capacity() + approximate_value_size(value))
+                .sum::<usize>())
+            .sum();
+        size_of::<Store>() + attributes + records
+    }
+}
+
+impl Database {
+    /// Collects record counts, approximate memory and snapshot sizes for every store.
+    ///
+    /// # Errors
+    /// Returns an error if a store cannot be serialized or a snapshot file cannot be inspected.
+    pub fn stats(&self) -> io::Result<DatabaseStats> {
+        let mut names: Vec<&String> = self.stores.keys().collect();
+        names.sort();
+
+        let mut stores = Vec::new();
+        for name in names {
+            let store = &self.stores[name];
+            stores.push(StoreStats {
+                name: name.clone(),
+                records: store.record_count(),
+                attributes: store.attribute_count(),
+                memory_bytes: store.approximate_memory(),
+                snapshot_bytes: serde_json::to_vec(store)?.len(),
+            });
+        }
+
+        let mut snapshot_files = Vec::new();
+        for format in [SnapshotFormat::Json, SnapshotFormat::Binary] {
+            let path = self.snapshot_path(format);
+            match fs::metadata(&path) {
+                Ok(metadata) => snapshot_files.push((path.display().to_string(), metadata.len())),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(DatabaseStats { stores, snapshot_files })
+    }
+}
@@ -0,0 +1,72 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::introspect::type_name;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("users".to_string(), vec!["email".to_string()].into_iter().collect()).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Bob"), "age".to_string() => json!(30) }).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Alice"), "age".to_string() => json!(2.5) }).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("Bob"), "age".to_string() => json!("unknown") }).unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!(null) }).unwrap();
+        db.add_store("empty".to_string(), HashSet::new()).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_describe_store() {
+        let db = create_test_database("describe_db");
+        let summaries = db.get_store("users").unwrap().describe();
+        let names: Vec<&str> = summaries.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["age", "email", "name"]);
+
+        let age = &summaries[0];
+        assert_eq!(age.present, 3);
+        assert_eq!(age.distinct, 3);
+        assert_eq!(age.types.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), vec![("float", 1), ("int", 1), ("string", 1)]);
+        assert_eq!(age.min, Some(json!(2.5)));
+        assert_eq!(age.max, Some(json!(30)));
+
+        let email = &summaries[1];
+        assert_eq!((email.present, email.distinct, email.min.clone()), (0, 0, None));
+
+        let name = &summaries[2];
+        assert_eq!((name.present, name.distinct), (4, 3));
+        assert_eq!(name.types["null"], 1);
+        assert_eq!((name.min.clone(), name.max.clone()), (Some(json!("Alice")), Some(json!("Bob"))));
+    }
+
+    #[test]
+    fn test_type_names() {
+        assert_eq!(type_name(&json!(1)), "int");
+        assert_eq!(type_name(&json!(u64::MAX)), "int");
+        assert_eq!(type_name(&json!(1.5)), "float");
+        assert_eq!(type_name(&json!([1])), "array");
+        assert_eq!(type_name(&json!({})), "object");
+    }
+
+    #[test]
+    fn test_database_stats() {
+        let mut db = create_test_database("stats_db");
+        let stats = db.stats().unwrap();
+        assert!(stats.snapshot_files.is_empty());
+        assert_eq!(stats.stores.iter().map(|s| (s.name.as_str(), s.records)).collect::<Vec<_>>(), vec![("empty", 0), ("users", 4)]);
+        assert!(stats.stores[1].memory_bytes > stats.stores[0].memory_bytes);
+        assert!(stats.stores[1].snapshot_bytes > stats.stores[0].snapshot_bytes);
+
+        let dir = TempDir::new("stats");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        let stats = db.stats().unwrap();
+        assert_eq!(stats.snapshot_files.len(), 1);
+        assert!(stats.snapshot_files[0].0.ends_with("stats_db.rbdb"));
+        assert!(stats.snapshot_files[0].1 > 0);
+    }
+}
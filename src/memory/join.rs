@@ -0,0 +1,114 @@
+use std::io;
+use serde_json::{json, Value};
+use super::structure::Store;
+use super::path::resolve;
+use super::database::Database;
+use std::collections::{HashMap, HashSet};
+
+/// Enumeration representing the kinds of join supported between two stores.
+#[derive(PartialEq, Debug, Eq, Clone, Copy)]
+pub enum JoinKind {
+    /// Only pairs of records whose join attributes are equal
+    Inner,
+    /// Every record of the left store, with null right attributes when nothing matches
+    Left,
+}
+
+/// Returns the join key of a record, treating `id` as the record ID unless the store defines its own `id` attribute.
+fn join_key(store: &Store, id: i64, record: &HashMap<String, Value>, attribute: &str) -> Option<Value> {
+    if attribute == "id" && !store.attributes.contains("id") {
+        return Some(json!(id));
+    }
+    resolve(record, attribute).filter(|value| !value.is_null()).cloned()
+}
+
+/// Returns the text a join key is indexed under, writing numbers by value so that `1` matches `1.0`.
+fn index_text(key: &Value) -> String {
+    match key.as_f64() {
+        Some(number) if key.is_f64() && number.fract() == 0.0 && number.abs() < 9.0e15 => (number as i64).to_string(),
+        _ => key.to_string(),
+    }
+}
+
+/// Copies a record into `merged`, prefixing each attribute with the store name.
+fn qualify_into(merged: &mut HashMap<String, Value>, store_name: &str, id: i64, record: &HashMap<String, Value>) {
+    merged.insert(format!("{}.id", store_name), json!(id));
+    for (key, value) in record {
+        merged.insert(format!("{}.{}", store_name, key), value.clone());
+    }
+}
+
+impl Database {
+    /// Joins two stores of the database on equality of an attribute from each.
+    ///
+    /// # Arguments
+    /// * `left` - The name of the left store.
+    /// * `right` - The name of the right store.
+    /// * `left_attribute` - The attribute of the left store to join on (`id` refers to the record ID).
+    /// * `right_attribute` - The attribute of the right store to join on (`id` refers to the record ID).
+    /// * `kind` - Whether to perform an inner or a left join.
+    ///
+    /// # Returns
+    /// A new `Store` named `<left>_<right>` whose records hold the merged attributes qualified by
+    /// their store name (e.g. `users.name`, `orders.id`), so it can be filtered, queried and sorted
+    /// like any other store. A store joined with itself is qualified by `left.` and `right.` instead.
+    /// Numeric keys match by value, so an integer `1` joins a float `1.0`.
+    ///
+    /// # Errors
+    /// Returns an error if either store does not exist.
+    pub fn join(&self, left: &str, right: &str, left_attribute: &str, right_attribute: &str, kind: JoinKind) -> io::Result<Store> {
+        let left_store = self.stores.get(left)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", left)))?;
+        let right_store = self.stores.get(right)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", right)))?;
+
+        let (left_prefix, right_prefix) = if left == right { ("left", "right") } else { (left, right) };
+        let mut attributes: HashSet<String> = HashSet::new();
+        for (name, store) in [(left_prefix, left_store), (right_prefix, right_store)] {
+            attributes.insert(format!("{}.id", name));
+            attributes.extend(store.attributes.iter().map(|attr| format!("{}.{}", name, attr)));
+        }
+
+        // Index the right store by the text of its join key.
+        let mut index: HashMap<String, Vec<i64>> = HashMap::new();
+        for (id, record) in right_store.live_values() {
+            if let Some(key) = join_key(right_store, *id, record, right_attribute) {
+                index.entry(index_text(&key)).or_default().push(*id);
+            }
+        }
+        index.values_mut().for_each(|ids| ids.sort());
+
+        let mut left_ids: Vec<&i64> = left_store.live_values().map(|(id, _)| id).collect();
+        left_ids.sort();
+
+        let mut joined = Store::make_store(format!("{}_{}", left, right), attributes)?;
+        for left_id in left_ids {
+            let left_record = &left_store.values[left_id];
+            let matches = join_key(left_store, *left_id, left_record, left_attribute)
+                .and_then(|key| index.get(&index_text(&key)));
+
+            match matches {
+                Some(right_ids) => {
+                    for right_id in right_ids {
+                        let mut merged = HashMap::new();
+                        qualify_into(&mut merged, left_prefix, *left_id, left_record);
+                        qualify_into(&mut merged, right_prefix, *right_id, &right_store.values[right_id]);
+                        joined.add_record(merged)?;
+                    }
+                }
+                None if kind == JoinKind::Left => {
+                    let mut merged = HashMap::new();
+                    qualify_into(&mut merged, left_prefix, *left_id, left_record);
+                    merged.insert(format!("{}.id", right_prefix), Value::Null);
+                    for attr in &right_store.attributes {
+                        merged.insert(format!("{}.{}", right_prefix, attr), Value::Null);
+                    }
+                    joined.add_record(merged)?;
+                }
+                None => {}
+            }
+        }
+
+        Ok(joined)
+    }
+}
@@ -0,0 +1,90 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::join::JoinKind;
+    use crate::memory::complex::QueryOperator;
+
+    fn create_test_database() -> Database {
+        let mut db = Database::new("join_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+
+        let users = db.get_store_mut("users").unwrap();
+        users.add_record(hashmap! { "name".to_string() => json!("Alice") }).unwrap();
+        users.add_record(hashmap! { "name".to_string() => json!("Bob") }).unwrap();
+
+        let orders = db.get_store_mut("orders").unwrap();
+        orders.add_record(hashmap! { "user_id".to_string() => json!(0), "total".to_string() => json!(10) }).unwrap();
+        orders.add_record(hashmap! { "user_id".to_string() => json!(0), "total".to_string() => json!(25) }).unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_inner_join() {
+        let db = create_test_database();
+        let joined = db.join("users", "orders", "id", "user_id", JoinKind::Inner).unwrap();
+        let records = joined.get_all_records().unwrap();
+        assert_eq!(records.len(), 2);
+        for record in records.values() {
+            assert_eq!(record["users.name"], json!("Alice"));
+            assert_eq!(record["orders.user_id"], json!(0));
+        }
+    }
+
+    #[test]
+    fn test_left_join_keeps_unmatched_records() {
+        let db = create_test_database();
+        let joined = db.join("users", "orders", "id", "user_id", JoinKind::Left).unwrap();
+        assert_eq!(joined.get_all_records().unwrap().len(), 3);
+
+        let unmatched = joined.query("users.name", QueryOperator::Eq, json!("Bob")).unwrap();
+        assert_eq!(unmatched.len(), 1);
+        let record = unmatched.values().next().unwrap();
+        assert_eq!(record["orders.id"], Value::Null);
+        assert_eq!(record["orders.total"], Value::Null);
+    }
+
+    #[test]
+    fn test_joined_store_is_queryable() {
+        let db = create_test_database();
+        let joined = db.join("users", "orders", "id", "user_id", JoinKind::Inner).unwrap();
+        let result = joined.query("orders.total", QueryOperator::Gt, json!(20)).unwrap();
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn test_self_join_keeps_both_sides() {
+        let mut db = create_test_database();
+        let users = db.get_store_mut("users").unwrap();
+        users.add_record(hashmap! { "name".to_string() => json!("Carol"), "manager".to_string() => json!(0) }).unwrap();
+
+        let joined = db.join("users", "users", "manager", "id", JoinKind::Inner).unwrap();
+        let records: Vec<_> = joined.get_all_records().unwrap().into_values().collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["left.name"], json!("Carol"));
+        assert_eq!(records[0]["right.name"], json!("Alice"));
+    }
+
+    #[test]
+    fn test_numeric_keys_match_by_value() {
+        let mut db = create_test_database();
+        db.get_store_mut("orders").unwrap().add_record(hashmap! { "user_id".to_string() => json!(1.0), "total".to_string() => json!(5) }).unwrap();
+        let joined = db.join("users", "orders", "id", "user_id", JoinKind::Inner).unwrap();
+        let bob: Vec<_> = joined.get_all_records().unwrap().into_values()
+            .filter(|record| record["users.name"] == json!("Bob"))
+            .collect();
+        assert_eq!(bob.len(), 1);
+        assert_eq!(bob[0]["orders.total"], json!(5));
+    }
+
+    #[test]
+    fn test_join_missing_store() {
+        let db = create_test_database();
+        assert!(db.join("users", "payments", "id", "user_id", JoinKind::Inner).is_err());
+    }
+}
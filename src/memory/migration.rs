@@ -0,0 +1,152 @@
+use std::io;
+use serde_json::{json, Map, Value};
+use super::structure::Store;
+use super::trash::TrashedStore;
+use std::collections::HashMap;
+
+/// Version of the snapshot format written by this build.
+///
+/// * 1 - the unversioned format: a bare JSON map of stores, or a binary snapshot whose header says 1.
+///   Foreign keys and the binary format came before versioning, so stores may or may not carry foreign
+///   keys: JSON files written before foreign keys lack them, binary files always have them.
+/// * 2 - a `{"version", "stores"}` envelope; stores always carry their foreign keys.
+/// * 3 - stores carry an optional locked schema.
+/// * 4 - stores carry a default TTL and the expiration times of their records.
+/// * 5 - stores carry their triggers.
+/// * 6 - stores may track the creation time, modification time and version of their records.
+/// * 7 - stores may keep the prior versions of their records.
+/// * 8 - stores may have soft deletes and a trash; dropped stores are kept in the snapshot's trash.
+/// * 9 - stores carry their record ID strategy.
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+/// A migration upgrading one store definition to the next snapshot version.
+type StoreMigration = fn(&mut Map<String, Value>) -> io::Result<()>;
+
+/// Store migrations in order: the entry at index `i` upgrades a store from version `i + 1` to `i + 2`.
+const STORE_MIGRATIONS: [StoreMigration; SNAPSHOT_VERSION as usize - 1] = [
+    migrate_store_v1_to_v2,
+    migrate_store_v2_to_v3,
+    migrate_store_v3_to_v4,
+    migrate_store_v4_to_v5,
+    migrate_store_v5_to_v6,
+    migrate_store_v6_to_v7,
+    migrate_store_v7_to_v8,
+    migrate_store_v8_to_v9,
+];
+
+/// Version 2 made foreign keys required; version 1 stores written before they existed have none.
+fn migrate_store_v1_to_v2(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("foreign_keys").or_insert_with(|| json!([]));
+    Ok(())
+}
+
+/// Version 3 introduced locked schemas; older stores have none.
+fn migrate_store_v2_to_v3(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("schema").or_insert(Value::Null);
+    Ok(())
+}
+
+/// Version 4 introduced record expiration; older records never expire.
+fn migrate_store_v3_to_v4(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("default_ttl_ms").or_insert(Value::Null);
+    store.entry("expirations").or_insert_with(|| json!({}));
+    Ok(())
+}
+
+/// Version 5 introduced triggers.
+fn migrate_store_v4_to_v5(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("triggers").or_insert_with(|| json!([]));
+    Ok(())
+}
+
+/// Version 6 introduced record system fields; older stores do not track them.
+fn migrate_store_v5_to_v6(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("track_metadata").or_insert(Value::Bool(false));
+    store.entry("metadata").or_insert_with(|| json!({}));
+    Ok(())
+}
+
+/// Version 7 introduced record history; older stores do not keep it.
+fn migrate_store_v6_to_v7(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("keep_history").or_insert(Value::Bool(false));
+    store.entry("history").or_insert_with(|| json!({}));
+    Ok(())
+}
+
+/// Version 8 introduced soft deletes; older stores delete records for good.
+fn migrate_store_v7_to_v8(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("soft_delete").or_insert(Value::Bool(false));
+    store.entry("trash_retention_ms").or_insert(Value::Null);
+    store.entry("trash").or_insert_with(|| json!({}));
+    Ok(())
+}
+
+/// Version 9 introduced ID strategies; older stores number their records with the counter.
+fn migrate_store_v8_to_v9(store: &mut Map<String, Value>) -> io::Result<()> {
+    store.entry("id_strategy").or_insert_with(|| json!("Counter"));
+    Ok(())
+}
+
+fn check_version(version: u32) -> io::Result<()> {
+    if version == 0 || version > SNAPSHOT_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "unsupported snapshot version {} (this build reads versions 1 to {})", version, SNAPSHOT_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Upgrades a serialized store definition written at `version` to the current format and deserializes it.
+///
+/// # Errors
+/// Returns an `InvalidData` error if the version is unknown or the definition is malformed.
+pub fn migrate_store(mut store: Value, version: u32) -> io::Result<Store> {
+    check_version(version)?;
+    let Value::Object(fields) = &mut store else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "store definition is not a JSON object"));
+    };
+
+    for migration in &STORE_MIGRATIONS[version as usize - 1..] {
+        migration(fields)?;
+    }
+    Ok(serde_json::from_value(store)?)
+}
+
+/// Upgrades a JSON snapshot of any supported version and deserializes its stores and trashed stores.
+///
+/// Snapshots without a `version` field are version 1 files, which hold the stores map directly.
+///
+/// # Errors
+/// Returns an `InvalidData` error if the snapshot is newer than this build or malformed.
+pub fn migrate_snapshot(snapshot: Value) -> io::Result<(HashMap<String, Store>, HashMap<String, TrashedStore>)> {
+    let (version, stores, trash) = match snapshot {
+        Value::Object(mut envelope) if envelope.get("version").is_some_and(Value::is_u64) => {
+            let version = envelope["version"].as_u64().unwrap();
+            let version = u32::try_from(version).unwrap_or(u32::MAX);
+            let stores = envelope.remove("stores").unwrap_or_else(|| json!({}));
+            (version, stores, envelope.remove("trash").unwrap_or_else(|| json!({})))
+        }
+        legacy => (1, legacy, json!({})),
+    };
+    check_version(version)?;
+
+    let (Value::Object(stores), Value::Object(trash)) = (stores, trash) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "snapshot stores are not a JSON object"));
+    };
+    let invalid = |name: &str, e: io::Error| io::Error::new(io::ErrorKind::InvalidData, format!("store '{}': {}", name, e));
+    let stores = stores.into_iter()
+        .map(|(name, store)| {
+            let store = migrate_store(store, version).map_err(|e| invalid(&name, e))?;
+            Ok((name, store))
+        })
+        .collect::<io::Result<_>>()?;
+    let trash = trash.into_iter()
+        .map(|(name, mut trashed)| {
+            let store = trashed.get_mut("store").map(Value::take).unwrap_or(Value::Null);
+            let store = migrate_store(store, version).map_err(|e| invalid(&name, e))?;
+            let deleted_at = trashed.get("deleted_at").and_then(Value::as_u64).unwrap_or_default();
+            Ok((name, TrashedStore { deleted_at, store }))
+        })
+        .collect::<io::Result<_>>()?;
+    Ok((stores, trash))
+}
@@ -0,0 +1,123 @@
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::migration::{migrate_snapshot, SNAPSHOT_VERSION};
+    use crate::memory::test_dir::TempDir;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/src/memory/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn assert_fixture_contents(db: &Database) {
+        let users = db.get_store("users").unwrap();
+        assert_eq!(users.values.len(), 2);
+        assert_eq!(users.values[&0]["name"], json!("Alice"));
+        assert_eq!(users.id, 2);
+        assert_eq!(db.get_store("orders").unwrap().values[&0]["user_id"], json!(1));
+    }
+
+    #[test]
+    fn test_load_version_1_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v1.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert!(db.get_store("orders").unwrap().foreign_keys.is_empty());
+    }
+
+    #[test]
+    fn test_load_version_1_binary_fixture() {
+        // Binary snapshots came after foreign keys, so version 1 binary files carry them, unlike early JSON ones.
+        let db = Database::load_from("fixture", fixture("snapshot_v1.rbdb")).unwrap();
+        assert_fixture_contents(&db);
+        assert_eq!(db.get_store("orders").unwrap().foreign_keys.len(), 1);
+    }
+
+    #[test]
+    fn test_load_version_2_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v2.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert_eq!(db.get_store("orders").unwrap().foreign_keys.len(), 1);
+        assert!(db.get_store("orders").unwrap().schema().is_none());
+    }
+
+    #[test]
+    fn test_load_version_3_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v3.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert!(db.get_store("users").unwrap().default_ttl().is_none());
+        assert!(db.get_store("users").unwrap().expirations.is_empty());
+    }
+
+    #[test]
+    fn test_load_version_4_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v4.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert!(db.get_store("orders").unwrap().triggers().is_empty());
+    }
+
+    #[test]
+    fn test_load_version_5_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v5.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert!(!db.get_store("users").unwrap().tracks_metadata());
+        assert!(db.get_store("users").unwrap().metadata(0).is_none());
+    }
+
+    #[test]
+    fn test_load_version_6_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v6.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert!(!db.get_store("users").unwrap().keeps_history());
+        assert!(db.get_store("users").unwrap().record_history(0).is_empty());
+    }
+
+    #[test]
+    fn test_load_version_7_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v7.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert!(!db.get_store("orders").unwrap().soft_deletes());
+        assert!(db.get_store("orders").unwrap().trashed_records().is_empty());
+        assert!(db.trashed_stores().is_empty());
+    }
+
+    #[test]
+    fn test_load_version_8_json_fixture() {
+        let db = Database::load_from("fixture", fixture("snapshot_v8.json")).unwrap();
+        assert_fixture_contents(&db);
+        assert_eq!(db.get_store("users").unwrap().id_strategy(), &IdStrategy::Counter);
+        assert_eq!(db.get_store("users").unwrap().record_id("1"), Some(1));
+    }
+
+    #[test]
+    fn test_migrated_snapshot_is_saved_at_current_version() {
+        let dir = TempDir::new("migration");
+        let mut db = Database::load_from("migration_db", fixture("snapshot_v1.json")).unwrap();
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Json).unwrap();
+
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.file("migration_db.json")).unwrap()).unwrap();
+        let reloaded = Database::load_in(dir.path(), "migration_db").unwrap();
+
+        assert_eq!(saved["version"], json!(SNAPSHOT_VERSION));
+        assert_eq!(reloaded.stores, db.stores);
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        assert!(migrate_snapshot(json!({ "version": SNAPSHOT_VERSION + 1, "stores": {} })).is_err());
+        assert!(migrate_snapshot(json!({ "version": 0, "stores": {} })).is_err());
+        assert!(migrate_snapshot(json!({ "version": 1, "stores": { "users": [] } })).is_err());
+
+        let mut db = Database::new("versions").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        let mut bytes = Vec::new();
+        db.write_binary(&mut bytes).unwrap();
+        bytes[4] = 0xFF;
+        assert!(Database::read_binary("versions", bytes.as_slice()).is_err());
+    }
+}
@@ -2,8 +2,53 @@ mod sql_test;
 mod complex_test;
 mod database_test;
 mod structure_test;
-pub(super) mod complex;
-pub(super) mod storage;
-pub(super) mod database;
-pub(super) mod structure;
-pub(super) mod sql_transform;
+mod join_test;
+mod constraints_test;
+mod path_test;
+mod record_parser_test;
+mod csv_test;
+mod ndjson_test;
+mod binary_test;
+mod migration_test;
+mod catalog_test;
+mod alter_test;
+mod introspect_test;
+mod schema_test;
+mod ttl_test;
+mod shared_test;
+mod changes_test;
+mod triggers_test;
+mod versioning_test;
+mod history_test;
+mod trash_test;
+mod ids_test;
+mod upsert_test;
+mod batch_test;
+mod test_dir;
+pub mod complex;
+pub mod storage;
+pub mod database;
+pub mod join;
+pub mod constraints;
+pub mod path;
+pub mod record_parser;
+pub mod csv;
+pub mod ndjson;
+pub mod binary;
+pub mod migration;
+pub mod catalog;
+pub mod alter;
+pub mod introspect;
+pub mod schema;
+pub mod ttl;
+pub mod shared;
+pub mod changes;
+pub mod triggers;
+pub mod versioning;
+pub mod history;
+pub mod trash;
+pub mod ids;
+pub mod upsert;
+pub mod batch;
+pub mod structure;
+pub mod sql_transform;
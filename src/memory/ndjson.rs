@@ -0,0 +1,93 @@
+use std::io;
+use std::fs::File;
+use serde_json::{Map, Value};
+use super::structure::Store;
+use super::database::Database;
+use super::batch::BatchError;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+impl Store {
+    /// Writes the store's records as JSON Lines, one object per line, ordered by record ID.
+    ///
+    /// Records are serialized one at a time straight into `writer`, so no string holding the
+    /// whole store is ever built.
+    ///
+    /// # Returns
+    /// The number of records written.
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> io::Result<usize> {
+        let mut ids: Vec<&i64> = self.live_values().map(|(id, _)| id).collect();
+        ids.sort();
+
+        for id in &ids {
+            serde_json::to_writer(&mut writer, &self.values[*id])?;
+            writer.write_all(b"\n")?;
+        }
+
+        writer.flush()?;
+        Ok(ids.len())
+    }
+
+    /// Exports the store to a JSON Lines file.
+    ///
+    /// # Returns
+    /// The number of records written.
+    pub fn export_ndjson(&self, path: &str) -> io::Result<usize> {
+        self.write_ndjson(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl Database {
+    /// Streams JSON Lines from `reader` into a store, creating the store if it does not exist.
+    ///
+    /// Each non-blank line must hold a JSON object. Lines are parsed one at a time, so no string
+    /// holding the whole input is built, then the records are added as one batch, see `add_records`:
+    /// if a line is rejected, nothing is added and a store that did not exist is not created.
+    ///
+    /// # Returns
+    /// The number of records imported.
+    ///
+    /// # Errors
+    /// Returns an error naming the line number of the first malformed or rejected line.
+    pub fn read_ndjson<R: BufRead>(&mut self, store: &str, mut reader: R) -> io::Result<usize> {
+        let mut records = Vec::new();
+        let mut line_numbers = Vec::new();
+        let mut line = String::new();
+        let mut line_number = 0;
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return Err(io::Error::new(e.kind(), format!("line {}: {}", line_number + 1, e))),
+            }
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let object = serde_json::from_str::<Map<String, Value>>(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_number, e)))?;
+            records.push(object.into_iter().collect::<HashMap<_, _>>());
+            line_numbers.push(line_number);
+        }
+
+        if !self.stores.contains_key(store) {
+            self.add_store(store.to_string(), HashSet::new())?;
+        }
+        self.add_records(store, records).map(|ids| ids.len()).map_err(|e| {
+            match e.get_ref().and_then(|inner| inner.downcast_ref::<BatchError>()).and_then(|batch| batch.rows.first()) {
+                Some(row) => io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_numbers[row.row - 1], row.message)),
+                None => e,
+            }
+        })
+    }
+
+    /// Imports a JSON Lines file into a store, creating the store if it does not exist.
+    ///
+    /// # Returns
+    /// The number of records imported.
+    pub fn import_ndjson(&mut self, store: &str, path: &str) -> io::Result<usize> {
+        self.read_ndjson(store, BufReader::new(File::open(path)?))
+    }
+}
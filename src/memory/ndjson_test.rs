@@ -0,0 +1,93 @@
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::triggers::{Trigger, TriggerAction, TriggerEvent, TriggerTiming};
+    use crate::memory::test_dir::TempDir;
+
+    #[test]
+    fn test_read_ndjson() {
+        let mut db = Database::new("ndjson_db").unwrap();
+        let data = "{\"name\": \"Alice\", \"tags\": [\"a\"]}\n\n{\"name\": \"Bob\", \"age\": 3}\n";
+        assert_eq!(db.read_ndjson("people", Cursor::new(data)).unwrap(), 2);
+
+        let people = db.get_store("people").unwrap();
+        assert_eq!(people.values[&0]["tags"], json!(["a"]));
+        assert_eq!(people.values[&1]["age"], json!(3));
+    }
+
+    #[test]
+    fn test_read_ndjson_reports_line_and_adds_nothing() {
+        let mut db = Database::new("ndjson_db").unwrap();
+        db.add_store("people".to_string(), HashSet::new()).unwrap();
+        db.add_record("people", hashmap! { "name".to_string() => json!("Zed") }).unwrap();
+
+        let data = "{\"name\": \"Alice\"}\n{\"name\": \"Bob\"}\n[1, 2]\n{\"name\": \"Carl\"}\n";
+        let error = db.read_ndjson("people", Cursor::new(data)).unwrap_err();
+        assert!(error.to_string().starts_with("line 3:"));
+
+        let people = db.get_store("people").unwrap();
+        assert_eq!(people.values.len(), 1);
+        assert_eq!(people.id, 1);
+    }
+
+    #[test]
+    fn test_rejected_read_ndjson_leaves_no_store_or_audit_entries() {
+        let mut db = Database::new("ndjson_db").unwrap();
+        assert!(db.read_ndjson("people", Cursor::new("{\"name\": \"Alice\"}\nnot json\n")).is_err());
+        assert!(db.get_store("people").is_none());
+
+        db.add_store("people".to_string(), HashSet::new()).unwrap();
+        db.add_store("audit".to_string(), HashSet::new()).unwrap();
+        db.get_store_mut("people").unwrap().add_trigger(Trigger {
+            name: "audit_adds".to_string(), timing: TriggerTiming::After, events: vec![TriggerEvent::Add],
+            action: TriggerAction::Audit { store: "audit".to_string() },
+        }).unwrap();
+        db.add_record("people", hashmap! { "name".to_string() => json!("Zed") }).unwrap();
+
+        assert!(db.read_ndjson("people", Cursor::new("{\"name\": \"Alice\"}\n{\"name\": \"Bob\"}\n[1]\n")).is_err());
+        let audit = db.get_store("audit").unwrap();
+        assert_eq!(audit.values.len(), 1);
+        assert_eq!(audit.id, 1);
+        assert_eq!(db.get_store("people").unwrap().values.len(), 1);
+    }
+
+    #[test]
+    fn test_read_ndjson_checks_foreign_keys() {
+        let mut db = Database::new("ndjson_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Restrict).unwrap();
+
+        db.add_record("users", hashmap! { "name".to_string() => json!("Ann") }).unwrap();
+        let error = db.read_ndjson("orders", Cursor::new("{\"user_id\": 0}\n\n{\"user_id\": 4}\n")).unwrap_err();
+        assert!(error.to_string().starts_with("line 3:"));
+        assert!(db.get_store("orders").unwrap().values.is_empty());
+    }
+
+    #[test]
+    fn test_ndjson_round_trip_through_files() {
+        let mut db = Database::new("ndjson_db").unwrap();
+        db.add_store("events".to_string(), HashSet::new()).unwrap();
+        db.add_record("events", hashmap! {
+            "kind".to_string() => json!("login"),
+            "meta".to_string() => json!({ "ip": "10.0.0.1", "ok": true }),
+        }).unwrap();
+        db.add_record("events", hashmap! { "kind".to_string() => json!("logout") }).unwrap();
+
+        let dir = TempDir::new("ndjson_round_trip");
+        let path = dir.file("events.ndjson");
+        assert_eq!(db.get_store("events").unwrap().export_ndjson(&path).unwrap(), 2);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        let imported = db.import_ndjson("copy", &path);
+
+        assert_eq!(imported.unwrap(), 2);
+        assert_eq!(db.get_store("copy").unwrap().values, db.get_store("events").unwrap().values);
+    }
+}
@@ -0,0 +1,106 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// A single step of an attribute path: an object key or an array index.
+#[derive(PartialEq, Debug, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits an attribute path into segments.
+///
+/// Two syntaxes are accepted:
+/// * dotted paths with bracketed indexes, e.g. `address.city` or `tags[0]`;
+/// * JSON pointers, e.g. `/meta/score` (with `~1` for `/` and `~0` for `~`).
+///
+/// # Returns
+/// `None` if the path is malformed (empty segment, unclosed bracket or non-numeric index).
+fn parse_path(path: &str) -> Option<Vec<Segment>> {
+    if let Some(pointer) = path.strip_prefix('/') {
+        return Some(pointer.split('/')
+            .map(|segment| Segment::Key(segment.replace("~1", "/").replace("~0", "~")))
+            .collect());
+    }
+
+    let mut segments = Vec::new();
+    let mut key = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if key.is_empty() && !matches!(segments.last(), Some(Segment::Index(_))) {
+                    return None;
+                }
+                if !key.is_empty() {
+                    segments.push(Segment::Key(std::mem::take(&mut key)));
+                }
+            }
+            '[' => {
+                if !key.is_empty() {
+                    segments.push(Segment::Key(std::mem::take(&mut key)));
+                }
+                let mut index = String::new();
+                loop {
+                    match chars.next()? {
+                        ']' => break,
+                        c => index.push(c),
+                    }
+                }
+                segments.push(Segment::Index(index.trim().parse().ok()?));
+            }
+            _ => key.push(c),
+        }
+    }
+    if !key.is_empty() {
+        segments.push(Segment::Key(key));
+    }
+
+    match segments.first() {
+        Some(Segment::Key(_)) => Some(segments),
+        _ => None,
+    }
+}
+
+/// Returns the top-level attribute an attribute path starts from.
+///
+/// An exact attribute name (even one containing dots, such as the `users.name` attributes of a join) is its own root.
+pub fn root_attribute(attributes: &HashSet<String>, path: &str) -> Option<String> {
+    if attributes.contains(path) {
+        return Some(path.to_string());
+    }
+    match parse_path(path)?.into_iter().next() {
+        Some(Segment::Key(key)) => Some(key),
+        _ => None,
+    }
+}
+
+/// Resolves an attribute name or path against a record.
+///
+/// A top-level attribute whose name matches `path` exactly takes precedence over path interpretation.
+///
+/// # Returns
+/// The value found at the path, or `None` if any step is missing.
+pub fn resolve<'a>(record: &'a HashMap<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = record.get(path) {
+        return Some(value);
+    }
+
+    let segments = parse_path(path)?;
+    let mut segments = segments.iter();
+    let mut current = match segments.next()? {
+        Segment::Key(key) => record.get(key)?,
+        Segment::Index(_) => return None,
+    };
+
+    for segment in segments {
+        current = match (segment, current) {
+            (Segment::Key(key), Value::Object(map)) => map.get(key)?,
+            (Segment::Key(key), Value::Array(items)) => items.get(key.parse::<usize>().ok()?)?,
+            (Segment::Index(index), Value::Array(items)) => items.get(*index)?,
+            _ => return None,
+        };
+    }
+
+    Some(current)
+}
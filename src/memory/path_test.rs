@@ -0,0 +1,37 @@
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use crate::memory::path::resolve;
+
+    fn record() -> HashMap<String, Value> {
+        HashMap::from([
+            ("address".to_string(), json!({ "city": "Paris", "zip": "75001" })),
+            ("tags".to_string(), json!(["a", "b"])),
+            ("meta".to_string(), json!({ "score": 7, "a/b": { "n": [1, [2, 3]] } })),
+            ("users.name".to_string(), json!("Alice")),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_paths() {
+        let record = record();
+        assert_eq!(resolve(&record, "address.city"), Some(&json!("Paris")));
+        assert_eq!(resolve(&record, "tags[1]"), Some(&json!("b")));
+        assert_eq!(resolve(&record, "/meta/score"), Some(&json!(7)));
+        assert_eq!(resolve(&record, "/meta/a~1b/n/1/0"), Some(&json!(2)));
+        assert_eq!(resolve(&record, "meta.a/b.n[1][1]"), Some(&json!(3)));
+        assert_eq!(resolve(&record, "users.name"), Some(&json!("Alice")));
+    }
+
+    #[test]
+    fn test_resolve_missing_or_malformed() {
+        let record = record();
+        assert_eq!(resolve(&record, "address.country"), None);
+        assert_eq!(resolve(&record, "tags[5]"), None);
+        assert_eq!(resolve(&record, "tags[x]"), None);
+        assert_eq!(resolve(&record, "address..city"), None);
+        assert_eq!(resolve(&record, "tags[0"), None);
+    }
+}
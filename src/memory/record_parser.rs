@@ -0,0 +1,220 @@
+use std::io;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Infers the JSON type (integer, float, boolean or string) of a raw textual value.
+///
+/// Numbers written with leading zeros (e.g. the zip code `01234`) are kept as strings
+/// so that no digit is lost.
+pub fn infer_value(raw: &str) -> Value {
+    let digits = raw.strip_prefix('-').unwrap_or(raw);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+
+    if leading_zero {
+        json!(raw)
+    } else if let Ok(num) = raw.parse::<i64>() {
+        json!(num)
+    } else if let Ok(float) = raw.parse::<f64>() {
+        if float.is_finite() { json!(float) } else { json!(raw) }
+    } else if raw.eq_ignore_ascii_case("true") || raw.eq_ignore_ascii_case("false") {
+        json!(raw.eq_ignore_ascii_case("true"))
+    } else {
+        json!(raw)
+    }
+}
+
+/// Converts a raw value to the JSON type named by an explicit type hint.
+///
+/// # Arguments
+/// * `hint` - One of `str`, `int`, `float`, `bool`, `json` or `null`.
+/// * `raw` - The unquoted textual value.
+///
+/// # Returns
+/// `Ok(None)` if `hint` is not a known type name, so the caller can treat the text literally.
+///
+/// # Errors
+/// Returns an error if the value cannot be converted to the hinted type.
+pub fn parse_typed_value(hint: &str, raw: &str) -> io::Result<Option<Value>> {
+    let invalid = |kind: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("'{}' is not a valid {}", raw, kind));
+
+    let value = match hint.to_lowercase().as_str() {
+        "str" | "string" => json!(raw),
+        "int" => json!(raw.trim().parse::<i64>().map_err(|_| invalid("int"))?),
+        "float" => {
+            let float = raw.trim().parse::<f64>().map_err(|_| invalid("float"))?;
+            if !float.is_finite() {
+                return Err(invalid("float"));
+            }
+            json!(float)
+        }
+        "bool" => match raw.trim().to_lowercase().as_str() {
+            "true" => json!(true),
+            "false" => json!(false),
+            _ => return Err(invalid("bool")),
+        },
+        "json" => serde_json::from_str(raw).map_err(|_| invalid("JSON value"))?,
+        "null" => Value::Null,
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Returns the byte positions of `separator` wherever it is not escaped or inside quotes, brackets or braces.
+///
+/// A quote only opens a quoted text at the start of a token, so apostrophes inside words are plain characters.
+///
+/// # Errors
+/// Returns an `InvalidInput` error if a quote is left open or the brackets and braces do not balance.
+fn top_level_positions(input: &str, separator: char) -> io::Result<Vec<usize>> {
+    let mut positions = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut token_start = true;
+
+    for (index, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (_, '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if token_start => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') if depth == 0 => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unbalanced '{}' at position {}", c, index)));
+            }
+            (None, ']' | '}') => depth -= 1,
+            (None, c) if c == separator && depth == 0 => positions.push(index),
+            _ => {}
+        }
+        token_start = quote.is_none() && (c.is_whitespace() || matches!(c, ',' | ':' | '=' | '[' | '{'));
+    }
+
+    if quote.is_some() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "unterminated quote"));
+    }
+    if depth != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "unclosed '[' or '{'"));
+    }
+    Ok(positions)
+}
+
+/// Splits `input` on `separator` wherever it is not escaped or inside quotes, brackets or braces.
+pub fn split_top_level(input: &str, separator: char) -> io::Result<Vec<&str>> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for position in top_level_positions(input, separator)? {
+        pieces.push(&input[start..position]);
+        start = position + separator.len_utf8();
+    }
+    pieces.push(&input[start..]);
+    Ok(pieces)
+}
+
+/// Removes the surrounding quotes of a quoted text and resolves its escape sequences.
+///
+/// # Returns
+/// `None` if the text is not quoted.
+fn unquote(text: &str) -> Option<String> {
+    let quote = text.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    if text.len() < 2 || !text.ends_with(quote) {
+        return None;
+    }
+    Some(unescape(&text[1..text.len() - 1]))
+}
+
+/// Resolves the backslash escape sequences (`\n`, `\t`, and `\` before any other character) of a text.
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Parses the value part of an `attr:value` pair.
+///
+/// Quoted values are taken literally, `type=value` applies an explicit type hint,
+/// values starting with `[` or `{` are parsed as JSON and anything else is inferred.
+fn parse_value(text: &str) -> io::Result<Value> {
+    if let Some(literal) = unquote(text) {
+        return Ok(json!(literal));
+    }
+
+    if let Some((hint, raw)) = text.split_once('=') {
+        let raw = raw.trim();
+        let raw = unquote(raw).unwrap_or_else(|| raw.to_string());
+        if let Some(value) = parse_typed_value(hint.trim(), &raw)? {
+            return Ok(value);
+        }
+    }
+
+    if text.starts_with('[') || text.starts_with('{') {
+        return serde_json::from_str(text).map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid JSON value '{}': {} (quote it to store it as text)", text, e),
+        ));
+    }
+
+    if text.contains('\\') {
+        return Ok(json!(unescape(text)));
+    }
+    Ok(infer_value(text))
+}
+
+/// Parses a record from command-line input.
+///
+/// Two forms are accepted:
+/// * a JSON object, e.g. `{"name": "John", "tags": ["a", "b"]}`;
+/// * comma-separated `attr:value` pairs, e.g. `name:"Doe, John", zip:str=01234, tags:[1,2]`.
+///   Values may be quoted (with `\` escapes) to keep commas and colons, carry a
+///   `str`/`int`/`float`/`bool`/`json`/`null` type hint, or be inline JSON arrays and objects.
+///
+/// # Errors
+/// Returns an error describing the first malformed pair or value.
+pub fn parse_record(input: &str) -> io::Result<HashMap<String, Value>> {
+    let input = input.trim();
+
+    if input.starts_with('{') {
+        let object: Map<String, Value> = serde_json::from_str(input)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid JSON object: {}", e)))?;
+        return Ok(object.into_iter().collect());
+    }
+
+    let mut record = HashMap::new();
+    for pair in split_top_level(input, ',')? {
+        let pair = pair.trim();
+        if pair.is_empty() {
+            continue;
+        }
+
+        // Only the first unquoted colon separates the key, so values like URLs keep theirs.
+        let (key, value_text) = match top_level_positions(pair, ':')?.first() {
+            Some(&position) if !pair[..position].trim().is_empty() => (pair[..position].trim(), pair[position + 1..].trim()),
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid record format: '{}'. Use 'attribute:value'", pair)));
+            }
+        };
+
+        let key = unquote(key).unwrap_or_else(|| key.to_string());
+        record.insert(key, parse_value(value_text)?);
+    }
+
+    if record.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty record"));
+    }
+    Ok(record)
+}
@@ -0,0 +1,85 @@
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::memory::record_parser::{infer_value, parse_record, parse_typed_value, split_top_level};
+
+    #[test]
+    fn test_infer_value() {
+        assert_eq!(infer_value("30"), json!(30));
+        assert_eq!(infer_value("-4.5"), json!(-4.5));
+        assert_eq!(infer_value("TRUE"), json!(true));
+        assert_eq!(infer_value("0"), json!(0));
+        assert_eq!(infer_value("0.25"), json!(0.25));
+        assert_eq!(infer_value("01234"), json!("01234"));
+        assert_eq!(infer_value("NaN"), json!("NaN"));
+        assert_eq!(infer_value("John Doe"), json!("John Doe"));
+    }
+
+    #[test]
+    fn test_parse_typed_value() {
+        assert_eq!(parse_typed_value("str", "01234").unwrap(), Some(json!("01234")));
+        assert_eq!(parse_typed_value("int", "42").unwrap(), Some(json!(42)));
+        assert_eq!(parse_typed_value("float", "1").unwrap(), Some(json!(1.0)));
+        assert_eq!(parse_typed_value("json", "{\"a\": [1]}").unwrap(), Some(json!({"a": [1]})));
+        assert_eq!(parse_typed_value("null", "").unwrap(), Some(Value::Null));
+        assert_eq!(parse_typed_value("color", "red").unwrap(), None);
+        assert!(parse_typed_value("int", "4x").is_err());
+        assert!(parse_typed_value("bool", "yes").is_err());
+    }
+
+    #[test]
+    fn test_split_top_level() {
+        assert_eq!(split_top_level("a:1, b:\"x,y\", c:[1,2]", ',').unwrap(), vec!["a:1", " b:\"x,y\"", " c:[1,2]"]);
+        assert_eq!(split_top_level("a:it's, b:2", ',').unwrap(), vec!["a:it's", " b:2"]);
+        assert!(split_top_level("a:\"open", ',').is_err());
+        assert_eq!(split_top_level("a:[1, b:2", ',').unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(split_top_level("a:1], b:[2", ',').unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_parse_pairs() {
+        let record = parse_record("name:John Doe, age:30, active:true").unwrap();
+        assert_eq!(record["name"], json!("John Doe"));
+        assert_eq!(record["age"], json!(30));
+        assert_eq!(record["active"], json!(true));
+    }
+
+    #[test]
+    fn test_parse_quoted_and_escaped_values() {
+        let record = parse_record(r#"name:"Doe, John", url:https://example.com:8080/a, at:'12:30', note:a\, b, quote:"say \"hi\"""#).unwrap();
+        assert_eq!(record["name"], json!("Doe, John"));
+        assert_eq!(record["url"], json!("https://example.com:8080/a"));
+        assert_eq!(record["at"], json!("12:30"));
+        assert_eq!(record["note"], json!("a, b"));
+        assert_eq!(record["quote"], json!("say \"hi\""));
+    }
+
+    #[test]
+    fn test_parse_type_hints_and_json_values() {
+        let record = parse_record(r#"zip:str=01234, count:int=7, tags:["a", "b"], address:{"city": "Paris"}, "first name":Ann, eq:a=b"#).unwrap();
+        assert_eq!(record["zip"], json!("01234"));
+        assert_eq!(record["count"], json!(7));
+        assert_eq!(record["tags"], json!(["a", "b"]));
+        assert_eq!(record["address"], json!({"city": "Paris"}));
+        assert_eq!(record["first name"], json!("Ann"));
+        assert_eq!(record["eq"], json!("a=b"));
+    }
+
+    #[test]
+    fn test_parse_json_object() {
+        let record = parse_record(r#"{"name": "John", "scores": [1, 2], "meta": {"vip": true}}"#).unwrap();
+        assert_eq!(record.len(), 3);
+        assert_eq!(record["scores"], json!([1, 2]));
+        assert!(parse_record("{\"name\": ").is_err());
+        assert!(parse_record("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_records() {
+        assert!(parse_record("name").is_err());
+        assert!(parse_record(":value").is_err());
+        assert!(parse_record("tags:[1, 2").is_err());
+        assert!(parse_record("age:int=old").is_err());
+    }
+}
@@ -0,0 +1,319 @@
+use std::io;
+use serde_json::{json, Value};
+use super::structure::Store;
+use super::database::Database;
+use super::changes::ChangeKind;
+use serde::{Deserialize, Serialize};
+use super::introspect::type_name;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Enumeration representing the value type of a schema field.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+pub enum FieldType {
+    /// `true` or `false`
+    Bool,
+    /// Whole number
+    Int,
+    /// Any number; whole numbers are accepted too
+    Float,
+    /// Text
+    String,
+    /// JSON array
+    Array,
+    /// JSON object
+    Object,
+    /// Any value; the type of attributes that only held nulls when the schema was locked
+    Any,
+}
+
+impl FieldType {
+    /// Returns the type name, as used by `describe` and the inference report.
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldType::Bool => "bool",
+            FieldType::Int => "int",
+            FieldType::Float => "float",
+            FieldType::String => "string",
+            FieldType::Array => "array",
+            FieldType::Object => "object",
+            FieldType::Any => "any",
+        }
+    }
+
+    /// Checks whether a non-null value has this type.
+    pub fn accepts(self, value: &Value) -> bool {
+        match (self, value) {
+            (FieldType::Bool, Value::Bool(_)) => true,
+            (FieldType::Int, Value::Number(number)) => !number.is_f64(),
+            (FieldType::Float, Value::Number(_)) => true,
+            (FieldType::String, Value::String(_)) => true,
+            (FieldType::Array, Value::Array(_)) => true,
+            (FieldType::Object, Value::Object(_)) => true,
+            (FieldType::Any, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Converts a value to this type when it can be done without losing information.
+    ///
+    /// # Returns
+    /// `None` if the value has no faithful representation in this type.
+    pub fn convert(self, value: &Value) -> Option<Value> {
+        if self.accepts(value) {
+            return Some(value.clone());
+        }
+        match (self, value) {
+            (FieldType::Int, Value::String(text)) => text.trim().parse::<i64>().ok().map(|int| json!(int)),
+            (FieldType::Int, Value::Number(number)) => number.as_f64()
+                .filter(|float| float.fract() == 0.0 && float.abs() < i64::MAX as f64)
+                .map(|float| json!(float as i64)),
+            (FieldType::Float, Value::String(text)) => text.trim().parse::<f64>().ok()
+                .filter(|float| float.is_finite())
+                .map(|float| json!(float)),
+            (FieldType::Bool, Value::String(text)) => match text.trim().to_lowercase().as_str() {
+                "true" => Some(json!(true)),
+                "false" => Some(json!(false)),
+                _ => None,
+            },
+            (FieldType::Bool, Value::Number(number)) => match number.as_i64() {
+                Some(0) => Some(json!(false)),
+                Some(1) => Some(json!(true)),
+                _ => None,
+            },
+            (FieldType::String, Value::Bool(_) | Value::Number(_)) => Some(json!(value.to_string())),
+            (FieldType::Array | FieldType::Object, Value::String(text)) => serde_json::from_str::<Value>(text).ok()
+                .filter(|parsed| self.accepts(parsed)),
+            _ => None,
+        }
+    }
+}
+
+/// The type and nullability of one attribute of a locked schema.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+pub struct FieldSchema {
+    pub field_type: FieldType,          // Type of the non-null values.
+    pub nullable: bool,                 // Whether the attribute may be null or missing.
+}
+
+/// A schema locked onto a store: records may only hold these attributes, with these types.
+#[derive(Debug, Default, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Schema {
+    pub fields: BTreeMap<String, FieldSchema>,  // Fields keyed by attribute name.
+}
+
+/// What was inferred about one attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredField {
+    pub name: String,                           // Attribute name.
+    pub schema: Option<FieldSchema>,            // Inferred field, `None` if every value is null.
+    pub type_counts: BTreeMap<&'static str, usize>, // Number of values of each observed JSON type.
+    pub present: usize,                         // Number of records holding a non-null value.
+    pub unique_candidate: bool,                 // Whether every record holds a distinct int or string value.
+    pub outliers: Vec<i64>,                     // IDs of the records whose value does not have the inferred type.
+}
+
+/// The result of inferring a schema from a store's records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaInference {
+    pub records: usize,                 // Number of records scanned.
+    pub fields: Vec<InferredField>,     // Inferred attributes, sorted by name.
+}
+
+impl SchemaInference {
+    /// Returns the schema described by the inference; attributes that only hold nulls are nullable and untyped.
+    pub fn schema(&self) -> Schema {
+        let untyped = FieldSchema { field_type: FieldType::Any, nullable: true };
+        let fields = self.fields.iter()
+            .map(|field| (field.name.clone(), field.schema.unwrap_or(untyped)))
+            .collect();
+        Schema { fields }
+    }
+
+    /// Returns the `(attribute, record ID)` pairs whose value does not have the inferred type.
+    pub fn outliers(&self) -> Vec<(&str, i64)> {
+        self.fields.iter()
+            .flat_map(|field| field.outliers.iter().map(|id| (field.name.as_str(), *id)))
+            .collect()
+    }
+}
+
+/// Enumeration representing what happens to outliers when a schema is locked.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum OutlierPolicy {
+    /// Convert the outlying values to the inferred type, failing if one cannot be converted
+    Convert,
+    /// Delete the records holding outlying values, applying their foreign key actions
+    Reject,
+}
+
+/// Picks the type most values have, counting whole numbers as floats when floats are present.
+fn dominant_type(type_counts: &BTreeMap<&'static str, usize>) -> Option<FieldType> {
+    let count = |name: &str| type_counts.get(name).copied().unwrap_or(0);
+    let numbers = count("int") + count("float");
+    let number_type = if count("float") > 0 { FieldType::Float } else { FieldType::Int };
+
+    [
+        (number_type, numbers),
+        (FieldType::String, count("string")),
+        (FieldType::Bool, count("bool")),
+        (FieldType::Array, count("array")),
+        (FieldType::Object, count("object")),
+    ]
+        .into_iter()
+        .rev() // Ties go to the earlier type, as `max_by_key` keeps the last maximum.
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map(|(field_type, _)| field_type)
+}
+
+impl Store {
+    /// Infers a schema from the store's records.
+    ///
+    /// Each attribute gets the type most of its values have; the other values are reported as
+    /// outliers. An attribute is nullable if some record lacks it or holds null, and a candidate
+    /// unique key if every record holds a distinct int or string value.
+    pub fn infer_schema(&self) -> SchemaInference {
+        let mut names: Vec<&String> = self.attributes.iter().collect();
+        names.sort();
+        let mut ids: Vec<&i64> = self.values.keys().collect();
+        ids.sort();
+
+        let fields = names.into_iter().map(|name| {
+            let values: Vec<(i64, &Value)> = ids.iter()
+                .filter_map(|id| self.values[*id].get(name).filter(|value| !value.is_null()).map(|value| (**id, value)))
+                .collect();
+
+            let mut type_counts = BTreeMap::new();
+            for (_, value) in &values {
+                *type_counts.entry(type_name(value)).or_insert(0) += 1;
+            }
+            let field_type = dominant_type(&type_counts);
+
+            let distinct: HashSet<String> = values.iter().map(|(_, value)| value.to_string()).collect();
+            let unique_candidate = !values.is_empty()
+                && values.len() == ids.len()
+                && distinct.len() == values.len()
+                && matches!(field_type, Some(FieldType::Int | FieldType::String))
+                && type_counts.len() == 1;
+
+            let outliers = match field_type {
+                Some(field_type) => values.iter().filter(|(_, value)| !field_type.accepts(value)).map(|(id, _)| *id).collect(),
+                None => Vec::new(),
+            };
+
+            InferredField {
+                name: name.clone(),
+                schema: field_type.map(|field_type| FieldSchema { field_type, nullable: values.len() < ids.len() }),
+                type_counts,
+                present: values.len(),
+                unique_candidate,
+                outliers,
+            }
+        }).collect();
+
+        SchemaInference { records: self.values.len(), fields }
+    }
+
+    /// Checks a record against the store's locked schema, if any.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error for an attribute outside the schema, a value of the wrong
+    /// type, or a missing or null value of a non-nullable attribute.
+    pub fn check_schema(&self, record: &HashMap<String, Value>) -> io::Result<()> {
+        let Some(schema) = &self.schema else { return Ok(()) };
+        let invalid = |message: String| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+
+        let mut attributes: Vec<&String> = record.keys().collect();
+        attributes.sort();
+        for attribute in attributes {
+            if !schema.fields.contains_key(attribute) {
+                return invalid(format!("attribute '{}' is not part of the schema of '{}'", attribute, self.name));
+            }
+        }
+
+        for (attribute, field) in &schema.fields {
+            match record.get(attribute) {
+                None | Some(Value::Null) if !field.nullable => {
+                    return invalid(format!("attribute '{}' is required", attribute));
+                }
+                Some(value) if !value.is_null() && !field.field_type.accepts(value) => {
+                    return invalid(format!("attribute '{}' must be {}, got {}", attribute, field.field_type.name(), value));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the schema locked onto the store, if any.
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+
+    /// Removes the locked schema, so records may hold any attributes again.
+    pub fn unlock_schema(&mut self) {
+        self.schema = None;
+    }
+}
+
+impl Database {
+    /// Infers a schema from a store's records and locks it onto the store.
+    ///
+    /// Outliers must be dealt with first: without a policy the schema is only locked if there are none.
+    /// Attributes that never hold a non-null value are kept as nullable and untyped (`FieldType::Any`).
+    ///
+    /// # Returns
+    /// The inference the locked schema was built from, taken before outliers were converted or rejected.
+    ///
+    /// # Errors
+    /// Returns an error, leaving the store unchanged, if the store does not exist, there are
+    /// outliers and no policy, an outlier cannot be converted, or a rejected record is still
+    /// referenced through a `Restrict` foreign key.
+    pub fn lock_schema(&mut self, store: &str, policy: Option<OutlierPolicy>) -> io::Result<SchemaInference> {
+        let target = self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?;
+        let inference = target.infer_schema();
+        let schema = inference.schema();
+        let outliers = inference.outliers();
+
+        match (policy, outliers.is_empty()) {
+            (_, true) => {}
+            (None, false) => {
+                let listed: Vec<String> = outliers.iter().map(|(attribute, id)| format!("{}@{}", attribute, id)).collect();
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "{} outlier(s) do not match the inferred types: {}", outliers.len(), listed.join(", ")
+                )));
+            }
+            (Some(OutlierPolicy::Convert), false) => {
+                let mut conversions = Vec::new();
+                for (attribute, id) in &outliers {
+                    let value = &target.values[id][*attribute];
+                    let field_type = schema.fields[*attribute].field_type;
+                    let converted = field_type.convert(value).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!(
+                        "cannot convert {} of record {} to {}", value, id, field_type.name()
+                    )))?;
+                    conversions.push((*id, attribute.to_string(), converted));
+                }
+                for (id, attribute, value) in conversions {
+                    let old = self.observed_record(store, id);
+                    self.stores.get_mut(store).unwrap().remember(id);
+                    let record = self.stores.get_mut(store).unwrap().values.get_mut(&id).unwrap();
+                    record.insert(attribute, value);
+                    let new = old.is_some().then(|| record.clone());
+                    self.stores.get_mut(store).unwrap().stamp_updated(id);
+                    self.record_changed(store, ChangeKind::Update, id, old, new);
+                }
+                self.stores.get_mut(store).unwrap().rebuild_key_index();
+            }
+            (Some(OutlierPolicy::Reject), false) => {
+                let mut ids: Vec<i64> = outliers.iter().map(|(_, id)| *id).collect();
+                ids.sort();
+                ids.dedup();
+                self.delete_records(store, &ids)?;
+            }
+        }
+
+        self.stores.get_mut(store).unwrap().schema = Some(schema);
+        Ok(inference)
+    }
+}
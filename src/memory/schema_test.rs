@@ -0,0 +1,112 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::schema::{FieldSchema, FieldType, OutlierPolicy};
+
+    fn create_test_database() -> Database {
+        let mut db = Database::new("schema_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        for (id, age) in [json!(30), json!(25), json!("41"), json!(19)].into_iter().enumerate() {
+            let mut record = hashmap! { "email".to_string() => json!(format!("user{}@example.com", id)), "age".to_string() => age };
+            if id == 1 {
+                record.insert("nickname".to_string(), json!("bob"));
+            }
+            db.add_record("users", record).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let db = create_test_database();
+        let inference = db.get_store("users").unwrap().infer_schema();
+        assert_eq!(inference.records, 4);
+
+        let names: Vec<&str> = inference.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["age", "email", "nickname"]);
+
+        let age = &inference.fields[0];
+        assert_eq!(age.schema, Some(FieldSchema { field_type: FieldType::Int, nullable: false }));
+        assert_eq!(age.outliers, vec![2]);
+        assert!(!age.unique_candidate);
+
+        let email = &inference.fields[1];
+        assert_eq!(email.schema.unwrap().field_type, FieldType::String);
+        assert!(email.unique_candidate);
+
+        assert!(inference.fields[2].schema.unwrap().nullable);
+        assert_eq!(inference.outliers(), vec![("age", 2)]);
+    }
+
+    #[test]
+    fn test_lock_schema_requires_outliers_to_be_handled() {
+        let mut db = create_test_database();
+        assert!(db.lock_schema("users", None).is_err());
+        assert!(db.get_store("users").unwrap().schema().is_none());
+
+        db.lock_schema("users", Some(OutlierPolicy::Convert)).unwrap();
+        let users = db.get_store("users").unwrap();
+        assert_eq!(users.values[&2]["age"], json!(41));
+        assert!(users.schema().is_some());
+
+        // Records must now follow the schema.
+        assert!(db.add_record("users", hashmap! { "email".to_string() => json!("x"), "age".to_string() => json!("old") }).is_err());
+        assert!(db.add_record("users", hashmap! { "email".to_string() => json!("x") }).is_err());
+        assert!(db.add_record("users", hashmap! { "email".to_string() => json!("x"), "age".to_string() => json!(1), "extra".to_string() => json!(1) }).is_err());
+        assert!(db.add_record("users", hashmap! { "email".to_string() => json!("x"), "age".to_string() => json!(1), "nickname".to_string() => json!(null) }).is_ok());
+
+        db.get_store_mut("users").unwrap().unlock_schema();
+        assert!(db.add_record("users", hashmap! { "extra".to_string() => json!(1) }).is_ok());
+    }
+
+    #[test]
+    fn test_lock_schema_keeps_null_only_attributes() {
+        let mut db = create_test_database();
+        db.update_record("users", 1, hashmap! { "email".to_string() => json!("user1@example.com"), "age".to_string() => json!(25), "nickname".to_string() => json!(null) }).unwrap();
+        db.lock_schema("users", Some(OutlierPolicy::Convert)).unwrap();
+
+        let users = db.get_store("users").unwrap();
+        assert_eq!(users.schema().unwrap().fields["nickname"], FieldSchema { field_type: FieldType::Any, nullable: true });
+        assert_eq!(users.values[&1]["nickname"], json!(null));
+        assert!(db.add_record("users", hashmap! { "email".to_string() => json!("x"), "age".to_string() => json!(1), "nickname".to_string() => json!("x") }).is_ok());
+    }
+
+    #[test]
+    fn test_lock_schema_rejects_outliers() {
+        let mut db = create_test_database();
+        db.add_store("logins".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("logins", "user_id", "users", ReferentialAction::Restrict).unwrap();
+        db.add_record("logins", hashmap! { "user_id".to_string() => json!(2) }).unwrap();
+
+        // A restricted reference keeps the outlier, and nothing is locked.
+        assert!(db.lock_schema("users", Some(OutlierPolicy::Reject)).is_err());
+        assert_eq!(db.get_store("users").unwrap().values.len(), 4);
+
+        db.delete_record("logins", 0).unwrap();
+        db.lock_schema("users", Some(OutlierPolicy::Reject)).unwrap();
+        let users = db.get_store("users").unwrap();
+        assert_eq!(users.values.len(), 3);
+        assert!(!users.values.contains_key(&2));
+    }
+
+    #[test]
+    fn test_convert_failure_and_conversions() {
+        let mut db = create_test_database();
+        db.add_record("users", hashmap! { "email".to_string() => json!("z"), "age".to_string() => json!("unknown") }).unwrap();
+        assert!(db.lock_schema("users", Some(OutlierPolicy::Convert)).is_err());
+        assert_eq!(db.get_store("users").unwrap().values[&2]["age"], json!("41"));
+
+        assert_eq!(FieldType::Int.convert(&json!(3.0)), Some(json!(3)));
+        assert_eq!(FieldType::Int.convert(&json!(3.5)), None);
+        assert_eq!(FieldType::Float.convert(&json!("2.5")), Some(json!(2.5)));
+        assert_eq!(FieldType::String.convert(&json!(12)), Some(json!("12")));
+        assert_eq!(FieldType::Bool.convert(&json!("TRUE")), Some(json!(true)));
+        assert_eq!(FieldType::Array.convert(&json!("[1, 2]")), Some(json!([1, 2])));
+        assert_eq!(FieldType::Object.convert(&json!("[1, 2]")), None);
+    }
+}
@@ -0,0 +1,500 @@
+use std::io;
+use std::mem;
+use std::thread;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+use super::structure::Store;
+use super::database::Database;
+use super::storage::SnapshotFormat;
+use std::thread::JoinHandle;
+use std::io::Write;
+use super::binary::write_snapshot;
+use super::ttl::{now_millis, read_as_of};
+use std::sync::mpsc::Receiver;
+use super::changes::{ChangeEvent, ChangeFeed, SubscriptionId};
+use super::triggers::{Hook, HookContext};
+use super::trash::TrashedStore;
+use super::upsert::{UpsertKey, Upserted};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockWriteGuard};
+
+/// A database that can be shared between threads.
+///
+/// Every store sits behind its own reader/writer lock: any number of threads can read a store at once,
+/// and writers only wait for the stores they touch. Operations spanning several stores (foreign key
+/// checks and referential actions) lock every store involved, always in name order, so they cannot
+/// deadlock. Adding, dropping or renaming stores and changing foreign keys lock the whole database.
+///
+/// Stores are reference-counted and copied on write, so `snapshot` can hand out a point-in-time view
+/// without holding any lock while it is read.
+///
+/// Changes made through the handle are published to its subscribers in the order they were made;
+/// a change spanning several stores is published before their locks are released.
+///
+/// Cloning the handle is cheap; every clone refers to the same database.
+#[derive(Clone)]
+pub struct SharedDatabase {
+    inner: Arc<Shared>,
+}
+
+/// The state behind every clone of a `SharedDatabase`.
+struct Shared {
+    name: String,                                       // Name of the database.
+    dir: PathBuf,                                       // Data directory the database is saved in.
+    stores: RwLock<HashMap<String, RwLock<Arc<Store>>>>, // Stores, each behind its own lock.
+    changes: Mutex<ChangeFeed>,                         // Subscribers to the database's changes.
+    hooks: RwLock<HashMap<String, Hook>>,               // Functions `Call` triggers run, by name.
+    trash: Mutex<HashMap<String, TrashedStore>>,        // Dropped stores in the trash, by name.
+}
+
+/// A read-only view of every store of a `SharedDatabase` as it was when the snapshot was taken.
+///
+/// Reading a snapshot takes no lock, so it never blocks writers. A store is only copied when it is
+/// written to while a snapshot still shares it, once per store however many snapshots are open.
+#[derive(Clone)]
+pub struct ReadSnapshot {
+    stores: HashMap<String, Arc<Store>>,    // Stores as they were when the snapshot was taken.
+    taken_at: u64,                          // Time the snapshot was taken, in Unix milliseconds.
+}
+
+/// A background thread purging expired records; it stops when dropped.
+pub struct Sweeper {
+    stop: Arc<(Mutex<bool>, Condvar)>,  // Set to ask the thread to stop.
+    handle: Option<JoinHandle<()>>,     // The sweeping thread.
+}
+
+fn poisoned() -> io::Error {
+    io::Error::other("a lock was poisoned by a panicking thread")
+}
+
+/// Takes a store out of its `Arc`, copying it if a snapshot still shares it.
+fn unshare(store: Arc<Store>) -> Store {
+    Arc::try_unwrap(store).unwrap_or_else(|shared| (*shared).clone())
+}
+
+fn store_not_found(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", name))
+}
+
+impl SharedDatabase {
+    /// Wraps a database so it can be shared between threads.
+    pub fn new(mut database: Database) -> SharedDatabase {
+        let changes = Mutex::new(database.take_changes());
+        let hooks = RwLock::new(mem::take(&mut database.hooks));
+        let trash = Mutex::new(mem::take(&mut database.trash));
+        let stores = database.stores.into_iter()
+            .map(|(name, store)| (name, RwLock::new(Arc::new(store))))
+            .collect();
+        SharedDatabase {
+            inner: Arc::new(Shared { name: database.name, dir: database.dir, stores: RwLock::new(stores), changes, hooks, trash }),
+        }
+    }
+
+    /// Returns the name of the database.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Returns the names of the stores, sorted.
+    pub fn store_names(&self) -> io::Result<Vec<String>> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let mut names: Vec<String> = stores.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Subscribes to the changes of one store, or of every store, as `Database::subscribe` does.
+    ///
+    /// Changes made through `write` act on a `Store` directly and are not reported.
+    pub fn subscribe(&self, store: Option<&str>) -> io::Result<Receiver<ChangeEvent>> {
+        Ok(self.inner.changes.lock().map_err(|_| poisoned())?.subscribe(store))
+    }
+
+    /// Subscribes to the changes of one store, or of every store, through a callback, as `Database::on_change` does.
+    ///
+    /// The callback runs while the changed stores are locked, so it must not use the database itself.
+    pub fn on_change(&self, store: Option<&str>, callback: impl FnMut(&ChangeEvent) + Send + 'static) -> io::Result<SubscriptionId> {
+        Ok(self.inner.changes.lock().map_err(|_| poisoned())?.on_change(store, callback))
+    }
+
+    /// Cancels a subscription made with `on_change` or `subscribe`.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> io::Result<bool> {
+        Ok(self.inner.changes.lock().map_err(|_| poisoned())?.unsubscribe(id))
+    }
+
+    /// Registers a hook for `Call` triggers to run, as `Database::register_hook` does.
+    pub fn register_hook(&self, name: &str, hook: impl Fn(&mut HookContext) -> io::Result<()> + Send + Sync + 'static) -> io::Result<()> {
+        self.inner.hooks.write().map_err(|_| poisoned())?.insert(name.to_string(), Arc::new(hook));
+        Ok(())
+    }
+
+    /// Runs `f` on a store while holding its read lock; other readers are not blocked.
+    ///
+    /// # Errors
+    /// Returns an error if the store does not exist.
+    pub fn read<R>(&self, store: &str, f: impl FnOnce(&Store) -> R) -> io::Result<R> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let lock = stores.get(store).ok_or_else(|| store_not_found(store))?;
+        let guard = lock.read().map_err(|_| poisoned())?;
+        Ok(f(&guard))
+    }
+
+    /// Runs `f` on a store while holding its write lock, so read-modify-write sequences are atomic.
+    ///
+    /// Only this store is locked: foreign keys are not checked, use `add_record`, `update_record`
+    /// and `delete_record` for that.
+    ///
+    /// # Errors
+    /// Returns an error if the store does not exist.
+    pub fn write<R>(&self, store: &str, f: impl FnOnce(&mut Store) -> R) -> io::Result<R> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let lock = stores.get(store).ok_or_else(|| store_not_found(store))?;
+        let mut guard = lock.write().map_err(|_| poisoned())?;
+        Ok(f(Arc::make_mut(&mut guard)))
+    }
+
+    /// Takes a consistent read snapshot of every store without copying any record.
+    ///
+    /// Multi-store writes hold all their locks until they finish, and the snapshot waits for them,
+    /// so it never sees half of a cascade.
+    pub fn snapshot(&self) -> io::Result<ReadSnapshot> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let mut names: Vec<&String> = stores.keys().collect();
+        names.sort();
+
+        let mut guards = Vec::new();
+        for name in names {
+            guards.push((name, stores[name].read().map_err(|_| poisoned())?));
+        }
+        Ok(ReadSnapshot {
+            stores: guards.iter().map(|(name, guard)| (name.to_string(), Arc::clone(guard))).collect(),
+            taken_at: now_millis(),
+        })
+    }
+
+    /// Adds a record to a store after checking it against the store's foreign keys, as `Database::add_record` does.
+    ///
+    /// # Returns
+    /// The ID of the newly added record.
+    pub fn add_record(&self, store: &str, record: HashMap<String, Value>) -> io::Result<i64> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referenced_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.add_record(store, record))
+    }
+
+    /// Adds several records to a store at once, all of them or none, as `Database::add_records` does.
+    ///
+    /// # Returns
+    /// The IDs of the added records, in batch order.
+    pub fn add_records(&self, store: &str, records: Vec<HashMap<String, Value>>) -> io::Result<Vec<i64>> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referenced_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.add_records(store, records))
+    }
+
+    /// Replaces a record of a store after checking it against the store's foreign keys, as `Database::update_record` does.
+    pub fn update_record(&self, store: &str, id: i64, record: HashMap<String, Value>) -> io::Result<()> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referenced_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.update_record(store, id, record))
+    }
+
+    /// Adds a record under a caller-chosen ID, as `Database::insert_record_at` does.
+    pub fn insert_record_at(&self, store: &str, id: i64, record: HashMap<String, Value>) -> io::Result<()> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referenced_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.insert_record_at(store, id, record))
+    }
+
+    /// Replaces the record a key finds, or adds the record if there is none, as `Database::upsert_record` does.
+    ///
+    /// The lookup and the write happen under the same lock, so two writers upserting the same key cannot both insert.
+    /// The stores referencing `store` are locked too, for purging an expired record that holds the key.
+    pub fn upsert_record(&self, store: &str, key: &UpsertKey, record: HashMap<String, Value>) -> io::Result<Upserted> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let mut names = self.referenced_stores(&stores, store)?;
+        names.extend(self.referencing_stores(&stores, store)?);
+        self.with_stores(&stores, &names, |database| database.upsert_record(store, key, record))
+    }
+
+    /// Replaces a record only if it is still at the version the caller read, as `Database::update_record_if_version` does.
+    ///
+    /// The check and the update happen under the same lock, so of two writers that read the same version only one succeeds.
+    pub fn update_record_if_version(&self, store: &str, id: i64, expected_version: u64, record: HashMap<String, Value>) -> io::Result<u64> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referenced_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.update_record_if_version(store, id, expected_version, record))
+    }
+
+    /// Brings a record back to one of its prior versions, as `Database::restore_version` does.
+    pub fn restore_version(&self, store: &str, id: i64, version: u64) -> io::Result<u64> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referenced_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.restore_version(store, id, version))
+    }
+
+    /// Deletes a record from a store, applying the referential actions as `Database::delete_record` does.
+    pub fn delete_record(&self, store: &str, id: i64) -> io::Result<()> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = self.referencing_stores(&stores, store)?;
+        self.with_stores(&stores, &names, |database| database.delete_record(store, id))
+    }
+
+    /// Physically removes every expired record, as `Database::purge_expired` does.
+    ///
+    /// # Returns
+    /// The number of records removed.
+    pub fn purge_expired(&self) -> io::Result<usize> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        // Only lock, and copy out of open snapshots, the stores that have something to purge.
+        let mut names = BTreeSet::new();
+        for (name, lock) in stores.iter() {
+            let expired = !lock.read().map_err(|_| poisoned())?.expired_ids().is_empty();
+            if expired && !names.contains(name) {
+                names.extend(self.referencing_stores(&stores, name)?);
+            }
+        }
+        if names.is_empty() {
+            return Ok(0);
+        }
+        self.with_stores(&stores, &names, |database| Ok(database.purge_expired()))
+    }
+
+    /// Saves a consistent snapshot of every store in the given format.
+    pub fn save(&self, format: SnapshotFormat) -> io::Result<()> {
+        let stores = self.inner.stores.read().map_err(|_| poisoned())?;
+        let names = stores.keys().cloned().collect();
+        let mut trash = self.inner.trash.lock().map_err(|_| poisoned())?;
+        self.with_stores(&stores, &names, |database| {
+            database.trash = mem::take(&mut *trash);
+            let result = database.store_as(format);
+            *trash = mem::take(&mut database.trash);
+            result
+        })
+    }
+
+    /// Runs `f` with exclusive access to the whole database, for operations that add, drop or rename
+    /// stores or change foreign keys.
+    ///
+    /// The database is put back even if `f` panics.
+    pub fn with_database<R>(&self, f: impl FnOnce(&mut Database) -> R) -> io::Result<R> {
+        let mut stores = self.inner.stores.write().map_err(|_| poisoned())?;
+        let mut changes = self.inner.changes.lock().map_err(|_| poisoned())?;
+        let mut hooks = self.inner.hooks.write().map_err(|_| poisoned())?;
+        let mut trash = self.inner.trash.lock().map_err(|_| poisoned())?;
+        if stores.values().any(RwLock::is_poisoned) {
+            return Err(poisoned());
+        }
+        let mut database = self.empty_database();
+        database.changes = mem::take(&mut *changes);
+        database.hooks = mem::take(&mut *hooks);
+        database.trash = mem::take(&mut *trash);
+        for (name, lock) in stores.drain() {
+            database.stores.insert(name, unshare(lock.into_inner().unwrap_or_else(PoisonError::into_inner)));
+        }
+
+        let mut lent = LentDatabase { shared: &self.inner, guards: Some((stores, changes, hooks, trash)), database };
+        Ok(f(&mut lent.database))
+    }
+
+    /// Starts a background thread purging expired records every `interval`.
+    pub fn spawn_sweeper(&self, interval: Duration) -> Sweeper {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let database = self.clone();
+        let signal = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let (stopped, condvar) = &*signal;
+            let Ok(mut stopped) = stopped.lock() else { return };
+            while !*stopped {
+                let Ok((guard, timeout)) = condvar.wait_timeout(stopped, interval) else { return };
+                stopped = guard;
+                if timeout.timed_out() && !*stopped {
+                    let _ = database.purge_expired();
+                }
+            }
+        });
+        Sweeper { stop, handle: Some(handle) }
+    }
+
+    fn empty_database(&self) -> Database {
+        Database { name: self.inner.name.clone(), dir: self.inner.dir.clone(), ..Default::default() }
+    }
+
+    /// Returns `store`, the stores its foreign keys reference and the stores its triggers audit into.
+    fn referenced_stores(&self, stores: &HashMap<String, RwLock<Arc<Store>>>, store: &str) -> io::Result<BTreeSet<String>> {
+        let target = stores.get(store).ok_or_else(|| store_not_found(store))?
+            .read().map_err(|_| poisoned())?;
+        let mut names: BTreeSet<String> = target.foreign_keys.iter().map(|fk| fk.references.clone())
+            .chain(target.audit_stores().cloned())
+            .collect();
+        names.insert(store.to_string());
+        Ok(names)
+    }
+
+    /// Returns `store`, every store a deletion in it can cascade to and the stores their triggers audit into.
+    fn referencing_stores(&self, stores: &HashMap<String, RwLock<Arc<Store>>>, store: &str) -> io::Result<BTreeSet<String>> {
+        if !stores.contains_key(store) {
+            return Err(store_not_found(store));
+        }
+        let mut references = Vec::new();
+        for (name, lock) in stores {
+            let guard = lock.read().map_err(|_| poisoned())?;
+            references.extend(guard.foreign_keys.iter().map(|fk| (name.clone(), fk.references.clone())));
+        }
+
+        let mut names = BTreeSet::from([store.to_string()]);
+        loop {
+            let before = names.len();
+            for (name, referenced) in &references {
+                if names.contains(referenced) {
+                    names.insert(name.clone());
+                }
+            }
+            if names.len() == before {
+                break;
+            }
+        }
+
+        let mut audits = BTreeSet::new();
+        for name in &names {
+            audits.extend(stores[name].read().map_err(|_| poisoned())?.audit_stores().cloned());
+        }
+        names.extend(audits);
+        Ok(names)
+    }
+
+    /// Write-locks the named stores in name order and runs `f` on a database made of them.
+    ///
+    /// The caller holds the read lock of the store map, so foreign keys cannot change meanwhile.
+    /// The stores are put back even if `f` panics.
+    fn with_stores<R>(&self, stores: &HashMap<String, RwLock<Arc<Store>>>, names: &BTreeSet<String>, f: impl FnOnce(&mut Database) -> io::Result<R>) -> io::Result<R> {
+        let mut locks = Vec::new();
+        for name in names {
+            if let Some((name, lock)) = stores.get_key_value(name) {
+                locks.push((name, lock, lock.write().map_err(|_| poisoned())?));
+            }
+        }
+
+        let mut database = self.empty_database();
+        database.hooks = self.inner.hooks.read().map_err(|_| poisoned())?.clone();
+        if self.inner.changes.lock().map_err(|_| poisoned())?.is_observed() {
+            database.changes.capture();
+        }
+        for (name, _, guard) in locks.iter_mut() {
+            database.stores.insert(name.to_string(), unshare(mem::take(&mut **guard)));
+        }
+        let mut lent = LentStores { locks, database };
+        let result = f(&mut lent.database);
+
+        // Publish while the stores are still locked, so changes reach subscribers in the order they were made.
+        let events = lent.database.changes.take_captured();
+        if !events.is_empty() {
+            let mut changes = self.inner.changes.lock().map_err(|_| poisoned())?;
+            for event in events {
+                changes.publish(event);
+            }
+        }
+        result
+    }
+}
+
+/// The locks of a whole database, held while it is lent out.
+type DatabaseGuards<'a> = (
+    RwLockWriteGuard<'a, HashMap<String, RwLock<Arc<Store>>>>,
+    MutexGuard<'a, ChangeFeed>,
+    RwLockWriteGuard<'a, HashMap<String, Hook>>,
+    MutexGuard<'a, HashMap<String, TrashedStore>>,
+);
+
+/// The whole state of a `SharedDatabase` lent to a `Database` for one operation.
+///
+/// Dropping it puts the state back and releases the locks, also when the operation panicked; the
+/// poison the panic leaves on the locks is then cleared, as the state is back in place.
+struct LentDatabase<'a> {
+    shared: &'a Shared,                     // State the database is put back into.
+    guards: Option<DatabaseGuards<'a>>,     // Locks of the state, `None` once released.
+    database: Database,                     // Database the state is lent to.
+}
+
+impl Drop for LentDatabase<'_> {
+    fn drop(&mut self) {
+        let Some((mut stores, mut changes, mut hooks, mut trash)) = self.guards.take() else { return };
+        *changes = self.database.take_changes();
+        *hooks = mem::take(&mut self.database.hooks);
+        *trash = mem::take(&mut self.database.trash);
+        stores.extend(self.database.stores.drain().map(|(name, store)| (name, RwLock::new(Arc::new(store)))));
+        drop((stores, changes, hooks, trash));
+        self.shared.stores.clear_poison();
+        self.shared.changes.clear_poison();
+        self.shared.hooks.clear_poison();
+        self.shared.trash.clear_poison();
+    }
+}
+
+/// A write-locked store: its name, its lock and the guard holding the lock.
+type LockedStore<'a> = (&'a String, &'a RwLock<Arc<Store>>, RwLockWriteGuard<'a, Arc<Store>>);
+
+/// Stores taken out of their locks and lent to a `Database` for one operation.
+///
+/// Dropping it puts the stores back and releases their locks, also when the operation panicked; the
+/// poison the panic leaves on the locks is then cleared, as the stores are back in place.
+struct LentStores<'a> {
+    locks: Vec<LockedStore<'a>>,    // Locked stores, in name order.
+    database: Database,             // Database the stores are lent to.
+}
+
+impl Drop for LentStores<'_> {
+    fn drop(&mut self) {
+        for (name, lock, mut guard) in self.locks.drain(..) {
+            *guard = Arc::new(self.database.stores.remove(name.as_str()).unwrap_or_default());
+            drop(guard);
+            lock.clear_poison();
+        }
+    }
+}
+
+impl ReadSnapshot {
+    /// Runs `f` on a store as it was when the snapshot was taken.
+    ///
+    /// Reads judge expiry at the time the snapshot was taken, so records that expired since are still seen.
+    ///
+    /// # Errors
+    /// Returns an error if the store was not in the database when the snapshot was taken.
+    pub fn read<R>(&self, store: &str, f: impl FnOnce(&Store) -> R) -> io::Result<R> {
+        let target = self.stores.get(store).ok_or_else(|| store_not_found(store))?;
+        Ok(read_as_of(self.taken_at, || f(target)))
+    }
+
+    /// Writes the snapshot's stores as a binary snapshot, in the format of `Database::write_binary`,
+    /// which `Database::read_binary` loads. The trash is not part of a read snapshot, so none is written.
+    pub fn write_binary<W: Write>(&self, writer: W) -> io::Result<()> {
+        let stores = self.stores.iter().map(|(name, store)| (name, &**store)).collect();
+        write_snapshot(writer, stores, Vec::new())
+    }
+
+    /// Returns the names of the stores in the snapshot, sorted.
+    pub fn store_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.stores.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the time the snapshot was taken, in milliseconds since the Unix epoch.
+    pub fn taken_at(&self) -> u64 {
+        self.taken_at
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        let (stopped, condvar) = &*self.stop;
+        if let Ok(mut stopped) = stopped.lock() {
+            *stopped = true;
+        }
+        condvar.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,283 @@
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::time::Duration;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::shared::SharedDatabase;
+    use crate::memory::complex::QueryOperator;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::upsert::UpsertKey;
+    use crate::memory::triggers::{Trigger, TriggerAction, TriggerEvent, TriggerTiming};
+
+    const THREADS: i64 = 8;
+    const PER_THREAD: i64 = 250;
+
+    fn create_shared_database() -> SharedDatabase {
+        let mut db = Database::new("shared_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Cascade).unwrap();
+        SharedDatabase::new(db)
+    }
+
+    #[test]
+    fn test_concurrent_inserts_are_not_lost() {
+        let db = create_shared_database();
+
+        let writers: Vec<_> = (0..THREADS).map(|thread| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in 0..PER_THREAD {
+                    db.add_record("users", hashmap! { "n".to_string() => json!(thread * PER_THREAD + i) }).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let records = db.read("users", |users| users.get_all_records().unwrap()).unwrap();
+        assert_eq!(records.len() as i64, THREADS * PER_THREAD);
+        let ids: HashSet<i64> = records.keys().copied().collect();
+        assert_eq!(ids, (0..THREADS * PER_THREAD).collect());
+        let values: HashSet<i64> = records.values().map(|record| record["n"].as_i64().unwrap()).collect();
+        assert_eq!(values.len() as i64, THREADS * PER_THREAD);
+    }
+
+    #[test]
+    fn test_concurrent_read_modify_write_is_not_lost() {
+        let db = create_shared_database();
+        db.add_record("users", hashmap! { "visits".to_string() => json!(0) }).unwrap();
+
+        let writers: Vec<_> = (0..THREADS).map(|_| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for _ in 0..PER_THREAD {
+                    db.write("users", |users| {
+                        let visits = users.get_record(0).unwrap()["visits"].as_i64().unwrap();
+                        users.update_record(0, hashmap! { "visits".to_string() => json!(visits + 1) }).unwrap();
+                    }).unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let visits = db.read("users", |users| users.get_record(0).unwrap()["visits"].clone()).unwrap();
+        assert_eq!(visits, json!(THREADS * PER_THREAD));
+    }
+
+    #[test]
+    fn test_readers_see_consistent_records_while_writing() {
+        let db = create_shared_database();
+
+        let writer = {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in 0..PER_THREAD * 4 {
+                    db.add_record("users", hashmap! { "a".to_string() => json!(i), "b".to_string() => json!(i) }).unwrap();
+                }
+            })
+        };
+        let readers: Vec<_> = (0..4).map(|_| {
+            let db = db.clone();
+            thread::spawn(move || {
+                let mut last = 0;
+                while last < PER_THREAD * 4 {
+                    let matching = db.read("users", |users| users.query("a", QueryOperator::Ge, json!(0)).unwrap()).unwrap();
+                    assert!(matching.len() as i64 >= last, "a reader saw records disappear");
+                    assert!(matching.values().all(|record| record["a"] == record["b"]));
+                    last = matching.len() as i64;
+                }
+            })
+        }).collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_foreign_keys_hold_across_threads() {
+        let db = create_shared_database();
+        for i in 0..PER_THREAD {
+            db.add_record("users", hashmap! { "name".to_string() => json!(format!("user{}", i)) }).unwrap();
+        }
+
+        let inserter = {
+            let db = db.clone();
+            thread::spawn(move || {
+                let mut added = 0;
+                for i in 0..PER_THREAD {
+                    if db.add_record("orders", hashmap! { "user_id".to_string() => json!(i) }).is_ok() {
+                        added += 1;
+                    }
+                }
+                added
+            })
+        };
+        let deleter = {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in (0..PER_THREAD).rev() {
+                    db.delete_record("users", i).unwrap();
+                }
+            })
+        };
+        inserter.join().unwrap();
+        deleter.join().unwrap();
+
+        assert_eq!(db.read("users", |users| users.record_count()).unwrap(), 0);
+        // Every order either failed its reference check or was cascaded away with its user.
+        assert_eq!(db.read("orders", |orders| orders.record_count()).unwrap(), 0);
+        assert!(db.add_record("orders", hashmap! { "user_id".to_string() => json!(0) }).is_err());
+    }
+
+    #[test]
+    fn test_with_database_changes_stores() {
+        let db = create_shared_database();
+        db.with_database(|database| database.add_store("items".to_string(), HashSet::new())).unwrap().unwrap();
+
+        assert_eq!(db.store_names().unwrap(), vec!["items", "orders", "users"]);
+        assert!(db.read("missing", |store| store.record_count()).is_err());
+        assert!(db.add_record("missing", hashmap! {}).is_err());
+    }
+
+    #[test]
+    fn test_sweeper_purges_expired_records() {
+        let db = create_shared_database();
+        db.write("users", |users| users.add_record_with_ttl(hashmap! { "name".to_string() => json!("temp") }, Some(Duration::ZERO))).unwrap().unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("kept") }).unwrap();
+
+        let sweeper = db.spawn_sweeper(Duration::from_millis(5));
+        let mut remaining = 2;
+        for _ in 0..200 {
+            remaining = db.read("users", |users| users.record_count()).unwrap();
+            if remaining == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        drop(sweeper);
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn test_upsert_keeps_expired_records_that_are_still_referenced() {
+        let db = create_shared_database();
+        db.with_database(|database| {
+            database.add_record_with_ttl("users", hashmap! { "name".to_string() => json!("gone") }, Some(Duration::ZERO))
+        }).unwrap().unwrap();
+        db.with_database(|database| {
+            database.add_store("invoices".to_string(), HashSet::new())?;
+            database.add_foreign_key("invoices", "user_id", "users", ReferentialAction::Restrict)
+        }).unwrap().unwrap();
+        db.add_record("invoices", hashmap! { "user_id".to_string() => json!(0) }).unwrap();
+
+        assert!(db.upsert_record("users", &UpsertKey::Id(0), hashmap! { "name".to_string() => json!("new") }).is_err());
+        assert_eq!(db.read("invoices", |invoices| invoices.get_record(0).unwrap()["user_id"].clone()).unwrap(), json!(0));
+        assert!(db.read("users", |users| users.values.contains_key(&0)).unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_later_writes() {
+        let db = create_shared_database();
+        for name in ["alice", "bob", "carol"] {
+            db.add_record("users", hashmap! { "name".to_string() => json!(name) }).unwrap();
+        }
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(0) }).unwrap();
+
+        let snapshot = db.snapshot().unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("dave") }).unwrap();
+        db.update_record("users", 1, hashmap! { "name".to_string() => json!("robert") }).unwrap();
+        db.delete_record("users", 0).unwrap();
+        db.with_database(|database| database.delete_store("orders")).unwrap().unwrap();
+
+        let names: Vec<_> = snapshot.read("users", |users| users.sort_by("name", true).unwrap()).unwrap()
+            .into_iter().map(|(_, record)| record["name"].clone()).collect();
+        assert_eq!(names, vec![json!("alice"), json!("bob"), json!("carol")]);
+        assert_eq!(snapshot.read("orders", |orders| orders.record_count()).unwrap(), 1);
+        assert_eq!(snapshot.store_names(), vec!["orders", "users"]);
+
+        let live: Vec<_> = db.read("users", |users| users.sort_by("name", true).unwrap()).unwrap()
+            .into_iter().map(|(_, record)| record["name"].clone()).collect();
+        assert_eq!(live, vec![json!("carol"), json!("dave"), json!("robert")]);
+        assert_eq!(db.store_names().unwrap(), vec!["users"]);
+    }
+
+    #[test]
+    fn test_snapshot_reads_do_not_block_writers() {
+        let db = create_shared_database();
+        for i in 0..PER_THREAD {
+            db.add_record("users", hashmap! { "n".to_string() => json!(i) }).unwrap();
+        }
+        let snapshot = db.snapshot().unwrap();
+
+        let writer = {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in PER_THREAD..PER_THREAD * 4 {
+                    db.add_record("users", hashmap! { "n".to_string() => json!(i) }).unwrap();
+                }
+            })
+        };
+        // The snapshot stays open and in use for the whole time the writer runs.
+        while !writer.is_finished() {
+            let found = snapshot.read("users", |users| users.query("n", QueryOperator::Ge, json!(0)).unwrap().len()).unwrap();
+            assert_eq!(found as i64, PER_THREAD);
+        }
+        writer.join().unwrap();
+
+        let mut exported = Vec::new();
+        assert_eq!(snapshot.read("users", |users| users.write_ndjson(&mut exported)).unwrap().unwrap() as i64, PER_THREAD);
+        assert_eq!(db.read("users", |users| users.record_count()).unwrap() as i64, PER_THREAD * 4);
+        assert!(snapshot.taken_at() > 0);
+    }
+
+    #[test]
+    fn test_snapshot_judges_expiry_when_taken_and_exports_binary() {
+        let db = create_shared_database();
+        db.with_database(|database| {
+            database.add_record_with_ttl("users", hashmap! { "name".to_string() => json!("brief") }, Some(Duration::from_millis(30)))
+        }).unwrap().unwrap();
+        db.add_record("users", hashmap! { "name".to_string() => json!("lasting") }).unwrap();
+        let snapshot = db.snapshot().unwrap();
+        thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(db.read("users", |users| users.get_all_records().unwrap().len()).unwrap(), 1);
+        assert_eq!(snapshot.read("users", |users| users.get_all_records().unwrap().len()).unwrap(), 2);
+        assert!(snapshot.read("users", |users| users.get_record(0).is_ok()).unwrap());
+        assert!(snapshot.read("missing", |_| ()).is_err());
+
+        let mut bytes = Vec::new();
+        snapshot.write_binary(&mut bytes).unwrap();
+        let loaded = Database::read_binary("shared_db", &bytes[..]).unwrap();
+        assert!(loaded.get_store("orders").is_some());
+        assert_eq!(loaded.get_store("users").unwrap().values.len(), 2);
+    }
+
+    #[test]
+    fn test_stores_survive_a_panicking_operation() {
+        let db = create_shared_database();
+        let user = db.add_record("users", hashmap! { "name".to_string() => json!("ann") }).unwrap();
+        db.register_hook("explode", |_| panic!("hook failed")).unwrap();
+        db.write("orders", |orders| orders.add_trigger(Trigger {
+            name: "explode_on_add".to_string(), timing: TriggerTiming::Before, events: vec![TriggerEvent::Add],
+            action: TriggerAction::Call { hook: "explode".to_string() },
+        })).unwrap().unwrap();
+
+        assert!(catch_unwind(AssertUnwindSafe(|| db.add_record("orders", hashmap! { "user_id".to_string() => json!(user) }))).is_err());
+        assert_eq!(db.read("users", |users| users.record_count()).unwrap(), 1);
+        assert!(catch_unwind(AssertUnwindSafe(|| db.with_database(|_| panic!("operation failed")))).is_err());
+        assert_eq!(db.store_names().unwrap(), vec!["orders", "users"]);
+        assert_eq!(db.read("users", |users| users.record_count()).unwrap(), 1);
+        db.add_record("users", hashmap! { "name".to_string() => json!("bob") }).unwrap();
+    }
+}
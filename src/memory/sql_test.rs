@@ -2,7 +2,9 @@
 #[cfg(test)]
 mod tests {
     use std::fs;
-    use super::*;
+    use crate::memory::structure::Store;
+    use crate::memory::database::Database;
+    use crate::memory::test_dir::TempDir;
     use serde_json::{json};
     use std::collections::{HashMap, HashSet};
 
@@ -62,19 +64,19 @@ mod tests {
             ("age".to_string(), json!(28)),
         ]);
         let sql_update = store.to_sql_update("users", 1, &updates);
-        assert_eq!(sql_update, "UPDATE users SET name = 'Charlie', age = 28 WHERE id = 1;");
+        assert_eq!(sql_update, "UPDATE users SET age = 28, name = 'Charlie' WHERE id = 1;");
     }
 
     #[test]
     fn test_database_to_sql_create() {
-        let db = Database { name: "test_db".to_string(), stores: HashMap::new() };
+        let db = Database { name: "test_db".to_string(), stores: HashMap::new(), ..Default::default() };
         let sql_create = db.to_sql_create().unwrap();
         assert_eq!(sql_create, "CREATE DATABASE test_db;");
     }
 
     #[test]
     fn test_database_to_sql_drop() {
-        let db = Database { name: "test_db".to_string(), stores: HashMap::new() };
+        let db = Database { name: "test_db".to_string(), stores: HashMap::new(), ..Default::default() };
         let sql_drop = db.to_sql_drop().unwrap();
         assert_eq!(sql_drop, "DROP DATABASE test_db;");
     }
@@ -93,11 +95,13 @@ mod tests {
             name: "test_store".to_string(),
             attributes: vec!["name", "age"].into_iter().map(String::from).collect(),
             values: store_values,
+            ..Default::default()
         };
 
         let db = Database {
             name: "test_db".to_string(),
             stores: HashMap::from([("users".to_string(), store)]),
+            ..Default::default()
         };
 
         let sql_script = db.convert_to_sql().unwrap();
@@ -109,12 +113,10 @@ mod tests {
 
     #[test]
     fn test_generate_script() {
-        let db = Database { name: "test_db".to_string(), stores: HashMap::new() };
+        let dir = TempDir::new("generate_script");
+        let db = Database { name: "test_db".to_string(), dir: dir.path().to_path_buf(), ..Default::default() };
         db.generate_script().unwrap();
 
-        let file_path = "test_db.sql";
-        assert!(fs::metadata(file_path).is_ok());
-
-        fs::remove_file(file_path).unwrap(); // Clean up test file
+        assert!(fs::metadata(dir.file("test_db.sql")).is_ok());
     }
 }
@@ -3,10 +3,14 @@ use serde_json::Value;
 use std::io::{self, Write};
 use std::collections::HashMap;
 use super::structure::Store;
+use super::constraints::ForeignKey;
 use crate::memory::database::Database;
 
 impl Store {
-    /// Generates a SQL `CREATE TABLE` statement based on the store's attributes.
+    /// Generates a SQL `CREATE TABLE` statement based on the store's attributes and foreign keys.
+    ///
+    /// The primary key follows the store's `IdStrategy`: the `id` column for the counter, the key
+    /// attribute otherwise, with `id` kept unique for the foreign keys referencing the table.
     ///
     /// # Arguments
     ///
@@ -16,13 +20,29 @@ impl Store {
     ///
     /// A `Result<String, io::Error>` containing the SQL statement for creating the table.
     pub fn to_sql_create_table(&self, table_name: &str) -> io::Result<String> {
+        let key_attribute = self.id_strategy.key_attribute();
         let mut columns: Vec<String> = self.attributes.iter()
+            .filter(|attr| Some(attr.as_str()) != key_attribute)
             .map(|attr| format!("{} TEXT", attr))
             .collect();
         columns.sort();
+        match key_attribute {
+            None => columns.insert(0, "id INTEGER PRIMARY KEY".to_string()),
+            Some(attribute) => {
+                columns.insert(0, format!("{} {} PRIMARY KEY", attribute, self.id_strategy.sql_type()));
+                columns.insert(0, "id INTEGER NOT NULL UNIQUE".to_string());
+            }
+        }
+
+        let mut foreign_keys: Vec<&ForeignKey> = self.foreign_keys.iter().collect();
+        foreign_keys.sort_by(|a, b| a.attribute.cmp(&b.attribute));
+        columns.extend(foreign_keys.into_iter().map(|fk| format!(
+            "FOREIGN KEY ({}) REFERENCES {}(id) ON DELETE {}",
+            fk.attribute, fk.references, fk.on_delete.to_sql()
+        )));
 
         Ok(format!(
-            "CREATE TABLE {} (id INTEGER PRIMARY KEY, {});",
+            "CREATE TABLE {} ({});",
             table_name,
             columns.join(", ")
         ))
@@ -40,7 +60,7 @@ impl Store {
     pub fn to_sql_inserts(&self, table_name: &str) -> io::Result<Vec<String>> {
         let mut inserts = Vec::new();
 
-        for (id, data) in &self.values {
+        for (id, data) in self.live_values() {
             let mut sorted_columns: Vec<&str> = data.keys().map(|k| k.as_str()).collect();
             sorted_columns.sort();
 
@@ -105,7 +125,10 @@ impl Store {
     ///
     /// A `String` containing the SQL `UPDATE` statement.
     pub fn to_sql_update(&self, table_name: &str, id: i64, updates: &HashMap<String, Value>) -> String {
-        let set_statements: Vec<String> = updates.iter()
+        let mut sorted_updates: Vec<(&String, &Value)> = updates.iter().collect();
+        sorted_updates.sort_by(|a, b| a.0.cmp(b.0));
+
+        let set_statements: Vec<String> = sorted_updates.into_iter()
             .map(|(key, value)| match value {
                 Value::String(s) => format!("{} = '{}'", key, s.replace("'", "''")),
                 _ => format!("{} = {}", key, value),
@@ -146,7 +169,9 @@ impl Database {
         script += &self.to_sql_create()?;
         script += "\n";
 
-        for (name, store) in &self.stores {
+        // Referenced tables are created and filled before the tables referencing them.
+        for name in self.stores_in_dependency_order() {
+            let store = &self.stores[name];
             script += &store.to_sql_create_table(name)?;
             script += "\n";
 
@@ -172,18 +197,20 @@ impl Database {
     /// # Behavior
     ///
     /// This function:
-    /// 1. Creates a new `.sql` file named after the database.
+    /// 1. Creates a new `.sql` file named after the database, in its data directory.
     /// 2. Calls `convert_to_sql()` to generate SQL statements.
     /// 3. Writes the generated SQL script into the file.
     ///
     /// # Example
     ///
-    /// ```rust
-    /// let mut database = Database::new("my_database");
+    /// ```no_run
+    /// use rustbase::memory::database::Database;
+    ///
+    /// let database = Database::new("my_database").unwrap();
     /// database.generate_script().expect("Failed to generate SQL script");
     /// ```
     pub fn generate_script(&self) -> io::Result<()> {
-        let mut file = File::create(format!("{}.sql", self.name))?;
+        let mut file = File::create(self.dir.join(format!("{}.sql", self.name)))?;
         let script = self.convert_to_sql()?;
         file.write_all(script.as_bytes())?;
         Ok(())
@@ -1,12 +1,47 @@
-use serde_json;
-use std::fs::File;
+use std::fs::{self, File};
+use serde::Serialize;
 use std::collections::HashMap;
+use super::binary::MAGIC;
+use super::migration::{migrate_snapshot, SNAPSHOT_VERSION};
 use super::database::Database;
-use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use super::catalog::validate_name;
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use crate::memory::structure::Store;
+use crate::memory::trash::TrashedStore;
+
+/// The envelope of a JSON snapshot, recording the format version it was written at.
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    version: u32,                               // Snapshot format version.
+    stores: &'a HashMap<String, Store>,         // Stores of the database, keyed by name.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    trash: &'a HashMap<String, TrashedStore>,   // Dropped stores in the trash, keyed by name.
+}
+
+/// Enumeration representing the on-disk formats of a database snapshot.
+#[derive(PartialEq, Debug, Eq, Clone, Copy)]
+pub enum SnapshotFormat {
+    /// Human-readable JSON, stored in `<name>.json`
+    Json,
+    /// Compact checksummed binary, stored in `<name>.rbdb`
+    Binary,
+}
+
+impl SnapshotFormat {
+    /// Returns the snapshot file path of the database `name` in this format.
+    pub fn path(self, name: &str) -> String {
+        match self {
+            SnapshotFormat::Json => format!("{}.json", name),
+            SnapshotFormat::Binary => format!("{}.rbdb", name),
+        }
+    }
+}
 
 impl Database {
-    /// Loads the database from a JSON file if it exists.
+    /// Loads the database from its saved snapshot.
+    ///
+    /// A database is saved as either `<name>.json` or `<name>.rbdb`: saving in one format removes the other.
     ///
     /// # Arguments
     ///
@@ -15,14 +50,68 @@ impl Database {
     /// # Returns
     ///
     /// * `Ok(Database)` - A database instance loaded from the file.
-    /// * `Err(io::Error)` - If the file cannot be read or parsed.
+    /// * `Err(io::Error)` - If no snapshot exists, both do, or it cannot be read or parsed.
     pub fn load(name: &str) -> io::Result<Database> {
-        let mut file = File::open(format!("{}.json", name))?;
+        Database::load_in(Path::new(""), name)
+    }
+
+    /// Loads the database `name` from its snapshot in the directory `dir`.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error if `name` is not a valid database name, a `NotFound` error if no
+    /// snapshot exists and an `InvalidData` error if both a JSON and a binary snapshot exist, which a save
+    /// interrupted before removing the other format leaves behind: neither is picked over the other.
+    pub fn load_in(dir: &Path, name: &str) -> io::Result<Database> {
+        validate_name(name)?;
+        let existing: Vec<PathBuf> = [SnapshotFormat::Json, SnapshotFormat::Binary].into_iter()
+            .map(|format| dir.join(format.path(name)))
+            .filter(|path| path.is_file())
+            .collect();
+
+        match &existing[..] {
+            [path] => Database::load_from(name, path),
+            [] => Err(io::Error::new(io::ErrorKind::NotFound, format!("no snapshot found for database '{}'", name))),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "database '{}' has both a JSON and a binary snapshot; remove the stale one", name
+            ))),
+        }
+    }
+
+    /// Loads the database from a snapshot file, detecting whether it is JSON or binary from its content.
+    ///
+    /// Snapshots written by older versions are upgraded to the current format as they are loaded.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Database)` - A database instance named `name`, saved next to the file, loaded from the file.
+    /// * `Err(io::Error)` - If the file cannot be read or parsed.
+    pub fn load_from<P: AsRef<Path>>(name: &str, path: P) -> io::Result<Database> {
+        let path = path.as_ref();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut file = File::open(path)?;
+        let mut header = [0u8; 4];
+        let read = file.read(&mut header)?;
+        drop(file);
+
+        if read == header.len() && &header == MAGIC {
+            let mut database = Database::read_binary(name, BufReader::new(File::open(path)?))?;
+            database.dir = dir;
+            return Ok(database);
+        }
+
+        let mut file = File::open(path)?;
         let mut json = String::new();
         file.read_to_string(&mut json)?;
-        let map: HashMap<String, Store> = serde_json::from_str(&json)?;
+        let (stores, trash) = migrate_snapshot(serde_json::from_str(&json)?)?;
+
+        let mut database = Database { name: name.to_string(), stores, dir, trash, ..Default::default() };
+        database.rebuild_key_indexes();
+        Ok(database)
+    }
 
-        Ok(Database { name: name.to_string(), stores: map })
+    /// Returns the path of the database's snapshot in the given format, inside its data directory.
+    pub fn snapshot_path(&self, format: SnapshotFormat) -> PathBuf {
+        self.dir.join(format.path(&self.name))
     }
 
     /// Stores the current database values in a JSON file.
@@ -32,9 +121,39 @@ impl Database {
     /// * `Ok(())` - If the database is successfully stored.
     /// * `Err(io::Error)` - If the file cannot be created or written to.
     pub fn store(&mut self) -> io::Result<()> {
-        let json = serde_json::to_string(&self.stores)?;
-        let mut file = File::create(format!("{}.json", self.name))?;
-        file.write_all(json.as_bytes())?;
-        Ok(())
+        self.store_as(SnapshotFormat::Json)
+    }
+
+    /// Stores the current database values in a snapshot of the given format.
+    ///
+    /// Expired records, and trashed ones past their retention, are purged first, so they are never written.
+    /// Once the snapshot is written, a snapshot of the database in the other format is removed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the database is successfully stored.
+    /// * `Err(io::Error)` - If the file cannot be created or written to.
+    pub fn store_as(&mut self, format: SnapshotFormat) -> io::Result<()> {
+        validate_name(&self.name)?;
+        self.purge_expired();
+        self.purge_trash();
+        let path = self.snapshot_path(format);
+        match format {
+            SnapshotFormat::Json => {
+                let json = serde_json::to_string(&Snapshot { version: SNAPSHOT_VERSION, stores: &self.stores, trash: &self.trash })?;
+                let mut file = File::create(path)?;
+                file.write_all(json.as_bytes())?;
+            }
+            SnapshotFormat::Binary => self.write_binary(BufWriter::new(File::create(path)?))?,
+        }
+
+        let other = match format {
+            SnapshotFormat::Json => SnapshotFormat::Binary,
+            SnapshotFormat::Binary => SnapshotFormat::Json,
+        };
+        match fs::remove_file(self.snapshot_path(other)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
     }
 }
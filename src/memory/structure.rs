@@ -1,15 +1,53 @@
 use std::io;
 use serde_json::Value;
 use serde::{Deserialize, Serialize};
+use super::schema::Schema;
+use super::triggers::Trigger;
+use super::versioning::RecordMetadata;
+use super::history::HistoryEntry;
+use super::trash::TrashedRecord;
+use super::ids::IdStrategy;
+use super::constraints::ForeignKey;
 use std::collections::{HashMap, HashSet};
 
 /// A `Store` is a data structure similar to a table, representing a collection of records with dynamic attributes.
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Debug, Default, Deserialize, Serialize, Eq, PartialEq, Clone)]
 pub struct Store {
     pub(super) id: i64,                                                   // ID used to track the next record ID.
     pub name: String,                                          // Name of the store.
     pub(super) attributes: HashSet<String>,                    // Set of attributes that define the store.
+    #[serde(serialize_with = "super::binary::serialize_values")]
     pub(super) values: HashMap<i64, HashMap<String, Value>>,   // Store's records, keyed by their IDs.
+    #[serde(default)]
+    pub(super) foreign_keys: Vec<ForeignKey>,                  // Foreign keys declared on the store's attributes.
+    #[serde(default)]
+    pub(super) schema: Option<Schema>,                         // Schema locked onto the store, if any.
+    #[serde(default)]
+    pub(super) default_ttl_ms: Option<u64>,                    // Time-to-live of new records, in milliseconds.
+    #[serde(default)]
+    pub(super) expirations: HashMap<i64, u64>,                 // Expiration times (Unix milliseconds) of records with a TTL.
+    #[serde(default)]
+    pub(super) triggers: Vec<Trigger>,                         // Triggers run on record mutations, in order.
+    #[serde(default)]
+    pub(super) track_metadata: bool,                           // Whether records get creation/update times and versions.
+    #[serde(default)]
+    pub(super) metadata: HashMap<i64, RecordMetadata>,         // System fields of the records, when tracked.
+    #[serde(default)]
+    pub(super) keep_history: bool,                             // Whether prior versions of the records are kept.
+    #[serde(default)]
+    pub(super) history: HashMap<i64, Vec<HistoryEntry>>,       // Prior versions of each record, oldest first.
+    #[serde(default)]
+    pub(super) soft_delete: bool,                              // Whether deletions move records, and the store, to the trash.
+    #[serde(default)]
+    pub(super) trash_retention_ms: Option<u64>,                // Time trashed records are kept before being purged.
+    #[serde(default)]
+    pub(super) trash: HashMap<i64, TrashedRecord>,             // Deleted records awaiting restore or purge.
+    #[serde(default)]
+    pub(super) id_strategy: IdStrategy,                        // How records are identified.
+    #[serde(skip)]
+    pub(super) key_index: HashMap<String, i64>,                // Record IDs by key, for key strategies; rebuilt on load.
+    #[serde(skip)]
+    pub(super) trash_key_index: HashMap<String, i64>,          // IDs of trashed records by key, likewise.
 }
 
 impl Store {
@@ -20,10 +58,8 @@ impl Store {
     /// Returns a `Store` instance initialized with default values.
     pub fn new() -> io::Result<Store> {
         Ok(Store {
-            id: 0i64,
             name: "DEFAULT".to_string(),
-            attributes: HashSet::new(),
-            values: HashMap::new(),
+            ..Default::default()
         })
     }
 
@@ -38,10 +74,9 @@ impl Store {
     /// Returns a `Store` instance initialized with the given name and attributes.
     pub fn make_store(name: String, attributes: HashSet<String>) -> io::Result<Store> {
         Ok(Store {
-            id: 0i64,
             name,
             attributes,
-            values: HashMap::new(),
+            ..Default::default()
         })
     }
 
@@ -56,16 +91,31 @@ impl Store {
     ///
     /// # Errors
     ///
-    /// Returns an error if the record’s attributes are invalid.
+    /// Returns an error if the record’s attributes are invalid, it does not match the locked schema, or
+    /// it lacks a valid key or shares it with another record under the store's `IdStrategy`.
     pub fn add_record(&mut self, record: HashMap<String, Value>) -> io::Result<i64> {
         let record_id = self.id;
-        self.validate_attributes(record.keys().cloned().collect())?;
-        self.values.insert(record_id, record);
-        self.id += 1;
+        self.insert_at(record_id, record)?;
         Ok(record_id)
     }
 
-    /// Deletes a record from the store using its ID.
+    /// Adds a record under a free ID, keeping the counter ahead of it; see `add_record`.
+    pub(super) fn insert_at(&mut self, id: i64, mut record: HashMap<String, Value>) -> io::Result<()> {
+        self.assign_key(&mut record)?;
+        self.check_schema(&record)?;
+        self.validate_attributes(record.keys())?;
+        self.values.insert(id, record);
+        self.index_key(id);
+        self.set_expiration(id, self.default_ttl());
+        self.stamp_created(id);
+        self.id = self.id.max(id + 1);
+        Ok(())
+    }
+
+    /// Deletes a record from the store using its ID, moving it to the trash if the store has soft deletes.
+    ///
+    /// Foreign keys are not checked here: a store inside a `Database` should be changed through
+    /// `Database::delete_record`, which applies the referential actions and runs the triggers.
     ///
     /// # Parameters
     /// - `id`: The ID of the record to delete.
@@ -79,7 +129,7 @@ impl Store {
     /// Returns an error if the record is not found.
     pub fn delete_record(&mut self, id: i64) -> io::Result<()> {
         if self.values.contains_key(&id) {
-            self.values.remove(&id);
+            self.discard(id);
             Ok(())
         } else {
             Err(io::Error::new(io::ErrorKind::NotFound, "Record not found"))
@@ -88,6 +138,8 @@ impl Store {
 
     /// Updates an existing record by deleting the old one and adding the new one.
     ///
+    /// The record keeps its expiration time and system fields.
+    ///
     /// # Parameters
     /// - `id`: The ID of the record to update.
     /// - `record`: The new record data to replace the existing one.
@@ -98,17 +150,40 @@ impl Store {
     ///
     /// # Errors
     ///
-    /// Returns an error if the record is not found.
-    pub fn update_record(&mut self, id: i64, record: HashMap<String, Value>) -> io::Result<()> {
-        if self.values.contains_key(&id) {
-            self.delete_record(id)?;
+    /// Returns an error if the record is not found or has expired, does not match the locked schema or changes its key.
+    /// A record without the key attribute keeps its key.
+    pub fn update_record(&mut self, id: i64, mut record: HashMap<String, Value>) -> io::Result<()> {
+        self.keep_key(id, &mut record)?;
+        self.check_schema(&record)?;
+        if self.values.contains_key(&id) && !self.is_expired(id) {
+            let metadata = self.metadata.get(&id).copied();
+            let expires_at = self.expirations.get(&id).copied();
+            self.take_record(id);
             self.values.insert(id, record);
+            self.index_key(id);
+            if let Some(metadata) = metadata {
+                self.metadata.insert(id, metadata);
+            }
+            if let Some(expires_at) = expires_at {
+                self.expirations.insert(id, expires_at);
+            }
+            self.stamp_updated(id);
             Ok(())
         } else {
             Err(io::Error::new(io::ErrorKind::NotFound, "Record not found"))
         }
     }
 
+    /// Removes a record with its expiration and system fields, keeping it in the history first.
+    pub(super) fn take_record(&mut self, id: i64) -> Option<HashMap<String, Value>> {
+        self.remember(id);
+        self.unindex_key(id);
+        let record = self.values.remove(&id)?;
+        self.expirations.remove(&id);
+        self.metadata.remove(&id);
+        Some(record)
+    }
+
     /// Retrieves a record from the store by its ID.
     ///
     /// # Parameters
@@ -121,8 +196,8 @@ impl Store {
     /// # Errors
     ///
     /// Returns an error if the record is not found.
-    pub fn get_record(&mut self, id: i64) -> io::Result<&HashMap<String, Value>> {
-        if self.values.contains_key(&id) {
+    pub fn get_record(&self, id: i64) -> io::Result<&HashMap<String, Value>> {
+        if self.values.contains_key(&id) && !self.is_expired(id) {
             Ok(&self.values[&id])
         } else {
             Err(io::Error::new(io::ErrorKind::NotFound, "Record not found"))
@@ -134,10 +209,10 @@ impl Store {
     /// # Returns
     ///
     /// Returns a `HashMap<i64, HashMap<String, Box<dyn Any>>>` containing all records in the store.
-    pub fn get_all_records(&mut self) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
+    pub fn get_all_records(&self) -> io::Result<HashMap<i64, HashMap<String, Value>>> {
         let mut cloned_records = HashMap::new();
 
-        for (id, record) in self.values.iter() {
+        for (id, record) in self.live_values() {
             let mut cloned_record = HashMap::new();
             for (key, value) in record.iter() {
                 cloned_record.insert(key.clone(), value.clone());
@@ -150,13 +225,13 @@ impl Store {
     /// Validates the attributes of a record and adds any new attributes to the store.
     ///
     /// # Parameters
-    /// - `map_attributes`: A vector of attribute names to validate.
+    /// - `map_attributes`: The attribute names to validate, borrowed from the record.
     ///
     /// # Returns
     ///
     /// Returns `Ok(())` if the validation and insertion are successful.
-    fn validate_attributes(&mut self, map_attributes: Vec<String>) -> io::Result<()> {
-        map_attributes.iter().for_each(|item| {
+    fn validate_attributes<'a>(&mut self, map_attributes: impl IntoIterator<Item = &'a String>) -> io::Result<()> {
+        map_attributes.into_iter().for_each(|item| {
             if !self.attributes.contains(item) {
                 self.attributes.insert(item.clone());
             }
@@ -1,7 +1,7 @@
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use crate::memory::structure::Store;
     use std::collections::HashMap;
     use serde_json::Value;
 
@@ -0,0 +1,38 @@
+#![cfg(test)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A scratch directory of a test, so tests do not write to the working directory or to each other's files.
+///
+/// The directory is emptied when created and removed when dropped, even if the test fails.
+pub struct TempDir {
+    path: PathBuf,  // Path of the directory, unique to the test and the process.
+}
+
+impl TempDir {
+    /// Creates an empty directory named after the test.
+    pub fn new(name: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("rustbase_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of a file in the directory, as the string the import and export functions take.
+    pub fn file(&self, name: &str) -> String {
+        self.path.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
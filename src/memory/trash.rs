@@ -0,0 +1,196 @@
+use std::io;
+use serde_json::Value;
+use std::time::Duration;
+use std::collections::HashMap;
+use super::structure::Store;
+use super::database::Database;
+use super::ttl::now_millis;
+use super::changes::ChangeKind;
+use serde::{Deserialize, Serialize};
+use super::versioning::RecordMetadata;
+
+/// A record deleted from a store with soft deletes, kept until it is restored or purged.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct TrashedRecord {
+    pub deleted_at: u64,                        // Time of the deletion, in Unix milliseconds.
+    pub record: HashMap<String, Value>,         // Content of the record when it was deleted.
+    pub metadata: Option<RecordMetadata>,       // System fields of the record, when tracked.
+}
+
+/// A store dropped while it had soft deletes on, kept until it is restored or purged.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TrashedStore {
+    pub deleted_at: u64,    // Time of the drop, in Unix milliseconds.
+    pub store: Store,       // The store, with its records as they were.
+}
+
+/// Checks whether something deleted at `deleted_at` has outlived a retention period.
+fn outlived(deleted_at: u64, retention_ms: Option<u64>, now: u64) -> bool {
+    retention_ms.is_some_and(|retention| deleted_at.saturating_add(retention) <= now)
+}
+
+impl Store {
+    /// Checks whether deleting a record, or dropping the store, moves it to the trash.
+    pub fn soft_deletes(&self) -> bool {
+        self.soft_delete
+    }
+
+    /// Turns soft deletes on or off. Records already in the trash stay there either way.
+    pub fn set_soft_delete(&mut self, enabled: bool) {
+        self.soft_delete = enabled;
+    }
+
+    /// Returns how long deleted records, and the store once dropped, stay in the trash; `None` keeps them until `empty_trash`.
+    pub fn trash_retention(&self) -> Option<Duration> {
+        self.trash_retention_ms.map(Duration::from_millis)
+    }
+
+    /// Sets how long deleted records, and the store once dropped, stay in the trash before being purged.
+    pub fn set_trash_retention(&mut self, retention: Option<Duration>) {
+        self.trash_retention_ms = retention.map(|retention| retention.as_millis() as u64);
+    }
+
+    /// Returns the records in the trash, by ID.
+    pub fn trashed_records(&self) -> Vec<(i64, &TrashedRecord)> {
+        let mut trashed: Vec<(i64, &TrashedRecord)> = self.trash.iter().map(|(id, record)| (*id, record)).collect();
+        trashed.sort_by_key(|(id, _)| *id);
+        trashed
+    }
+
+    /// Removes a record, moving it to the trash if the store has soft deletes and it has not expired.
+    ///
+    /// # Returns
+    /// The removed record, or `None` if it did not exist.
+    pub(super) fn discard(&mut self, id: i64) -> Option<HashMap<String, Value>> {
+        let to_trash = self.soft_delete && !self.is_expired(id);
+        let metadata = self.metadata.get(&id).copied();
+        let record = self.take_record(id)?;
+        if to_trash {
+            self.trash.insert(id, TrashedRecord { deleted_at: now_millis(), record: record.clone(), metadata });
+            self.index_trashed_key(id);
+        }
+        Some(record)
+    }
+
+    /// Permanently removes every record in the trash.
+    ///
+    /// # Returns
+    /// The number of records removed.
+    pub fn empty_trash(&mut self) -> usize {
+        let count = self.trash.len();
+        self.trash.clear();
+        self.trash_key_index.clear();
+        count
+    }
+
+    /// Permanently removes the records that have been in the trash longer than the retention period.
+    fn purge_trash(&mut self, now: u64) -> usize {
+        let before = self.trash.len();
+        let retention = self.trash_retention_ms;
+        self.trash.retain(|_, trashed| !outlived(trashed.deleted_at, retention, now));
+        if self.trash.len() < before {
+            self.trash_key_index.retain(|_, id| self.trash.contains_key(id));
+        }
+        before - self.trash.len()
+    }
+}
+
+impl Database {
+    /// Returns the dropped stores in the trash, by name.
+    pub fn trashed_stores(&self) -> Vec<(&String, &TrashedStore)> {
+        let mut trashed: Vec<(&String, &TrashedStore)> = self.trash.iter().collect();
+        trashed.sort_by_key(|(name, _)| *name);
+        trashed
+    }
+
+    /// Moves a record out of the trash, back under its ID, as a new version if versions are tracked.
+    ///
+    /// The record is checked against the store's schema and foreign keys as they are now; records that
+    /// were cascaded away or had a reference nullified when it was deleted are not restored with it.
+    /// Triggers do not run; subscribers get an `Insert` event.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist or the record is not in its trash, and the
+    /// schema, key or foreign key error otherwise, leaving the record in the trash.
+    pub fn restore_record(&mut self, store: &str, id: i64) -> io::Result<()> {
+        let target = self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?;
+        let trashed = target.trash.get(&id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Record {} is not in the trash", id)))?;
+        target.check_schema(&trashed.record)?;
+        target.check_key_free(&trashed.record, Some(id))?;
+        self.check_references(store, &trashed.record)?;
+
+        let target = self.stores.get_mut(store).unwrap();
+        target.unindex_trashed_key(id);
+        let trashed = target.trash.remove(&id).unwrap();
+        let new = self.changes.is_observed().then(|| trashed.record.clone());
+        target.values.insert(id, trashed.record);
+        target.index_key(id);
+        if let Some(metadata) = trashed.metadata.filter(|_| target.track_metadata) {
+            target.metadata.insert(id, metadata);
+        }
+        target.stamp_updated(id);
+        self.record_changed(store, ChangeKind::Insert, id, None, new);
+        Ok(())
+    }
+
+    /// Moves a dropped store out of the trash.
+    ///
+    /// Foreign keys other stores had on it were dropped with it and are not restored.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store is not in the trash, an `AlreadyExists` error if a store
+    /// of that name exists, and an error if one of its records references a record that no longer exists.
+    pub fn restore_store(&mut self, name: &str) -> io::Result<()> {
+        if self.stores.contains_key(name) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Store '{}' already exists", name)));
+        }
+        let mut trashed = self.trash.remove(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' is not in the trash", name)))?;
+        trashed.store.rebuild_key_index();
+        self.stores.insert(name.to_string(), trashed.store);
+
+        let restored = &self.stores[name];
+        let check = restored.values.values().try_for_each(|record| self.check_references(name, record));
+        if let Err(e) = check {
+            let store = self.stores.remove(name).unwrap();
+            self.trash.insert(name.to_string(), TrashedStore { deleted_at: trashed.deleted_at, store });
+            return Err(e);
+        }
+        self.store_changed(name, ChangeKind::StoreCreated);
+        Ok(())
+    }
+
+    /// Moves a store with soft deletes to the trash, after applying the referential actions
+    /// of the foreign keys pointing at its records, as `delete_store` does.
+    pub(super) fn trash_store(&mut self, name: &str) -> io::Result<()> {
+        self.release_store_references(name, true)?;
+        if let Some(store) = self.stores.remove(name) {
+            self.trash.insert(name.to_string(), TrashedStore { deleted_at: now_millis(), store });
+            self.store_changed(name, ChangeKind::StoreDropped);
+        }
+        Ok(())
+    }
+
+    /// Permanently removes every trashed store and every record in the trash of every store.
+    ///
+    /// # Returns
+    /// The number of stores and records removed.
+    pub fn empty_trash(&mut self) -> usize {
+        let stores = self.trash.len();
+        self.trash.clear();
+        stores + self.stores.values_mut().map(Store::empty_trash).sum::<usize>()
+    }
+
+    /// Permanently removes the stores and records that have been in the trash longer than their retention period.
+    ///
+    /// # Returns
+    /// The number of stores and records removed.
+    pub fn purge_trash(&mut self) -> usize {
+        let now = now_millis();
+        let stores = self.trash.len();
+        self.trash.retain(|_, trashed| !outlived(trashed.deleted_at, trashed.store.trash_retention_ms, now));
+        stores - self.trash.len() + self.stores.values_mut().map(|store| store.purge_trash(now)).sum::<usize>()
+    }
+}
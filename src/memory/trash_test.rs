@@ -0,0 +1,144 @@
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::time::Duration;
+    use std::collections::HashSet;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::database::Database;
+    use crate::memory::changes::ChangeKind;
+    use crate::memory::shared::SharedDatabase;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Cascade).unwrap();
+        db.get_store_mut("users").unwrap().set_soft_delete(true);
+        db.get_store_mut("orders").unwrap().set_soft_delete(true);
+        let alice = db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        db.add_record("orders", hashmap! { "user_id".to_string() => json!(alice), "total".to_string() => json!(10) }).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_deleted_records_are_hidden_and_restorable() {
+        let mut db = create_test_database("trash_records_db");
+        let events = db.subscribe(Some("users"));
+        db.delete_record("users", 0).unwrap();
+
+        let users = db.get_store("users").unwrap();
+        assert!(users.get_record(0).is_err());
+        assert_eq!(users.record_count(), 0);
+        assert_eq!(users.trashed_records()[0].1.record["name"], json!("alice"));
+        // The cascaded order went to its own store's trash.
+        assert_eq!(db.get_store("orders").unwrap().trashed_records().len(), 1);
+        assert_eq!(events.try_recv().unwrap().kind, ChangeKind::Delete);
+
+        // The order cannot come back before the user it references.
+        assert!(db.restore_record("orders", 0).is_err());
+        db.restore_record("users", 0).unwrap();
+        db.restore_record("orders", 0).unwrap();
+        assert_eq!(db.get_store("users").unwrap().get_record(0).unwrap()["name"], json!("alice"));
+        assert_eq!(db.get_store("orders").unwrap().get_record(0).unwrap()["total"], json!(10));
+        assert_eq!(events.try_recv().unwrap().kind, ChangeKind::Insert);
+        assert!(db.restore_record("users", 0).is_err());
+    }
+
+    #[test]
+    fn test_restore_bumps_version_and_expired_records_skip_the_trash() {
+        let mut db = create_test_database("trash_versions_db");
+        db.get_store_mut("users").unwrap().set_metadata_tracking(true);
+        db.delete_record("users", 0).unwrap();
+        db.restore_record("users", 0).unwrap();
+        assert_eq!(db.get_store("users").unwrap().metadata(0).unwrap().version, 2);
+
+        db.add_record_with_ttl("users", hashmap! { "name".to_string() => json!("temp") }, Some(Duration::ZERO)).unwrap();
+        assert_eq!(db.purge_expired(), 1);
+        assert!(db.get_store("users").unwrap().trashed_records().is_empty());
+    }
+
+    #[test]
+    fn test_empty_trash_and_retention_purge_for_good() {
+        let mut db = create_test_database("trash_purge_db");
+        db.get_store_mut("orders").unwrap().set_trash_retention(Some(Duration::from_millis(1)));
+        db.delete_record("users", 0).unwrap();
+        thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(db.purge_trash(), 1);
+        assert!(db.get_store("orders").unwrap().trashed_records().is_empty());
+        assert_eq!(db.get_store("users").unwrap().trashed_records().len(), 1);
+        assert_eq!(db.empty_trash(), 1);
+        assert!(db.restore_record("users", 0).is_err());
+    }
+
+    #[test]
+    fn test_trashed_keys_stay_taken_until_purged() {
+        let mut db = create_test_database("trash_keys_db");
+        db.set_id_strategy("users", IdStrategy::NaturalKey { attribute: "name".to_string() }).unwrap();
+        db.delete_record("users", 0).unwrap();
+
+        assert_eq!(db.get_store("users").unwrap().record_id("alice"), Some(0));
+        assert!(db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).is_err());
+
+        let dir = TempDir::new("trash_keys");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        let mut db = Database::load_in(dir.path(), "trash_keys_db").unwrap();
+        assert_eq!(db.get_store("users").unwrap().record_id("alice"), Some(0));
+
+        db.restore_record("users", 0).unwrap();
+        db.delete_record("users", 0).unwrap();
+        assert_eq!(db.get_store_mut("users").unwrap().empty_trash(), 1);
+        assert_eq!(db.get_store("users").unwrap().record_id("alice"), None);
+        db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+    }
+
+    #[test]
+    fn test_dropped_store_goes_to_the_trash_and_is_saved() {
+        let mut db = create_test_database("trash_stores_db");
+        db.delete_store("users").unwrap();
+        assert!(db.get_store("users").is_none());
+        // The cascade still applied to the referencing store, and its foreign key is gone.
+        assert_eq!(db.get_store("orders").unwrap().record_count(), 0);
+        assert!(db.get_store("orders").unwrap().foreign_keys.is_empty());
+
+        let dir = TempDir::new("trash_stores");
+        db.dir = dir.path().to_path_buf();
+        for format in [SnapshotFormat::Json, SnapshotFormat::Binary] {
+            db.store_as(format).unwrap();
+            let path = db.snapshot_path(format);
+            let mut reloaded = Database::load_from("trash_stores_db", &path).unwrap();
+            assert_eq!(reloaded.trashed_stores().len(), 1);
+            reloaded.restore_store("users").unwrap();
+            assert_eq!(reloaded.get_store("users").unwrap().get_record(0).unwrap()["name"], json!("alice"));
+        }
+
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        assert!(db.restore_store("users").is_err());
+        assert_eq!(db.empty_trash(), 2);
+        assert!(db.trashed_stores().is_empty());
+    }
+
+    #[test]
+    fn test_shared_database_keeps_the_trash() {
+        let db = SharedDatabase::new(create_test_database("trash_shared_db"));
+        db.with_database(|database| database.delete_store("orders")).unwrap().unwrap();
+        db.delete_record("users", 0).unwrap();
+
+        db.with_database(|database| {
+            assert_eq!(database.trashed_stores().len(), 1);
+            // The trashed order references the trashed user, so the user comes back first.
+            assert!(database.restore_store("orders").is_err());
+            database.restore_record("users", 0).unwrap();
+            database.restore_store("orders").unwrap();
+        }).unwrap();
+        assert_eq!(db.read("orders", |orders| orders.record_count()).unwrap(), 1);
+        assert_eq!(db.read("users", |users| users.record_count()).unwrap(), 1);
+    }
+}
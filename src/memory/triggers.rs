@@ -0,0 +1,327 @@
+use std::io;
+use std::fmt;
+use std::sync::Arc;
+use std::error::Error;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use super::structure::Store;
+use super::database::Database;
+use super::ttl::now_millis;
+use super::changes::ChangeKind;
+use serde::{Deserialize, Serialize};
+use super::complex::{prepare_condition, record_matches, QueryOperator};
+
+/// Enumeration representing the record mutations a trigger runs on.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+pub enum TriggerEvent {
+    /// A record is added
+    Add,
+    /// A record is replaced
+    Update,
+    /// A record is deleted
+    Delete,
+}
+
+impl TriggerEvent {
+    /// Returns the name of the event, as written in audit entries.
+    pub fn name(self) -> &'static str {
+        match self {
+            TriggerEvent::Add => "add",
+            TriggerEvent::Update => "update",
+            TriggerEvent::Delete => "delete",
+        }
+    }
+}
+
+/// Enumeration representing when a trigger runs relative to the mutation.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+pub enum TriggerTiming {
+    /// Before the mutation; the trigger may reject it or change the new record
+    Before,
+    /// After the mutation succeeded
+    After,
+}
+
+/// Enumeration representing what a trigger does.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub enum TriggerAction {
+    /// Reject the mutation unless the record satisfies `attribute operator value`, as in `query`;
+    /// the new record is checked on add and update, the old one on delete
+    Check { attribute: String, operator: QueryOperator, value: Value },
+    /// Set an attribute of the new record to the current time, in Unix milliseconds
+    SetTimestamp { attribute: String },
+    /// Set an attribute of the new record to a value when it is missing or null
+    SetDefault { attribute: String, value: Value },
+    /// Add an entry describing the mutation to another store
+    Audit { store: String },
+    /// Run the hook registered under this name with `Database::register_hook`
+    Call { hook: String },
+}
+
+/// A named action run before or after some mutations of a store's records.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone)]
+pub struct Trigger {
+    pub name: String,                   // Name of the trigger, unique within its store.
+    pub timing: TriggerTiming,          // Whether it runs before or after the mutation.
+    pub events: Vec<TriggerEvent>,      // Mutations it runs on.
+    pub action: TriggerAction,          // What it does.
+}
+
+/// What a hook is told about the mutation it runs on.
+pub struct HookContext<'a> {
+    pub store: &'a str,                                 // Name of the store being changed.
+    pub event: TriggerEvent,                            // The mutation.
+    pub timing: TriggerTiming,                          // Whether the mutation has happened yet.
+    pub id: Option<i64>,                                // ID of the record, `None` before an add.
+    pub old: Option<&'a HashMap<String, Value>>,        // Record before the mutation, for updates and deletes.
+    pub new: Option<&'a mut HashMap<String, Value>>,    // Record being written; changes only count before the mutation.
+}
+
+/// A function run by `Call` triggers; returning an error before the mutation rejects it.
+pub type Hook = Arc<dyn Fn(&mut HookContext) -> io::Result<()> + Send + Sync>;
+
+/// The error of an `After` trigger that failed on a mutation which was made and kept.
+///
+/// It is wrapped in an `io::Error` of the trigger error's kind; use `AfterTriggerError::of` to tell it apart
+/// from the error of a mutation that was not made.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AfterTriggerError {
+    pub ids: Vec<i64>,          // IDs of the records the mutation was asked to write or delete.
+    pub kind: io::ErrorKind,    // Kind of the first trigger error.
+    pub message: String,        // Message of the first trigger error.
+}
+
+impl AfterTriggerError {
+    pub(super) fn new(ids: Vec<i64>, error: &io::Error) -> AfterTriggerError {
+        match AfterTriggerError::of(error) {
+            Some(failure) => AfterTriggerError { ids, ..failure.clone() },
+            None => AfterTriggerError { ids, kind: error.kind(), message: error.to_string() },
+        }
+    }
+
+    /// Returns the `AfterTriggerError` an error wraps, if the error only means `After` triggers failed.
+    pub fn of(error: &io::Error) -> Option<&AfterTriggerError> {
+        error.get_ref().and_then(|inner| inner.downcast_ref())
+    }
+}
+
+impl fmt::Display for AfterTriggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<String> = self.ids.iter().map(i64::to_string).collect();
+        let noun = if self.ids.len() == 1 { "record" } else { "records" };
+        write!(f, "{} {} changed, but an after trigger failed: {}", noun, ids.join(", "), self.message)
+    }
+}
+
+impl Error for AfterTriggerError {}
+
+impl From<AfterTriggerError> for io::Error {
+    fn from(error: AfterTriggerError) -> io::Error {
+        io::Error::new(error.kind, error)
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl Store {
+    /// Adds a trigger to the store. It is saved with the store, so it keeps applying after a reload.
+    ///
+    /// Triggers only run on mutations made through `Database` methods, in the order they were added.
+    /// `Delete` triggers also run on records deleted by a `Cascade` foreign key, but no trigger runs on
+    /// records nullified by a `SetNull` one or removed with a dropped store.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error if the name is taken, no event is given, the action cannot run
+    /// at this timing (`Check`, `SetTimestamp` and `SetDefault` run before, `Audit` after) or on these
+    /// events (`SetTimestamp` and `SetDefault` need a new record, so not on delete), or the condition is invalid.
+    pub fn add_trigger(&mut self, trigger: Trigger) -> io::Result<()> {
+        if trigger.name.is_empty() || self.triggers.iter().any(|existing| existing.name == trigger.name) {
+            return Err(invalid(format!("trigger '{}' already exists", trigger.name)));
+        }
+        if trigger.events.is_empty() {
+            return Err(invalid(format!("trigger '{}' has no event", trigger.name)));
+        }
+
+        let before = trigger.timing == TriggerTiming::Before;
+        match &trigger.action {
+            TriggerAction::Check { operator, value, .. } if before => {
+                prepare_condition(operator, value)?;
+            }
+            TriggerAction::SetTimestamp { .. } | TriggerAction::SetDefault { .. } if before => {
+                if trigger.events.contains(&TriggerEvent::Delete) {
+                    return Err(invalid(format!("trigger '{}' sets a field, which a delete has none of", trigger.name)));
+                }
+            }
+            TriggerAction::Audit { .. } if !before => {}
+            TriggerAction::Call { .. } => {}
+            _ => {
+                return Err(invalid(format!("trigger '{}' cannot run {}", trigger.name, if before { "before" } else { "after" })));
+            }
+        }
+
+        self.triggers.push(trigger);
+        Ok(())
+    }
+
+    /// Removes a trigger by name.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store has no such trigger.
+    pub fn drop_trigger(&mut self, name: &str) -> io::Result<()> {
+        let before = self.triggers.len();
+        self.triggers.retain(|trigger| trigger.name != name);
+        if self.triggers.len() == before {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("Trigger '{}' not found", name)));
+        }
+        Ok(())
+    }
+
+    /// Returns the store's triggers, in the order they run.
+    pub fn triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    /// Returns the stores the triggers write audit entries into.
+    pub(super) fn audit_stores(&self) -> impl Iterator<Item = &String> {
+        self.triggers.iter().filter_map(|trigger| match &trigger.action {
+            TriggerAction::Audit { store } => Some(store),
+            _ => None,
+        })
+    }
+}
+
+impl Database {
+    /// Registers a hook for `Call` triggers to run.
+    ///
+    /// Hooks are not saved: register them again after loading a database whose triggers call them.
+    /// A mutation that would run an unregistered hook fails.
+    pub fn register_hook(&mut self, name: &str, hook: impl Fn(&mut HookContext) -> io::Result<()> + Send + Sync + 'static) {
+        self.hooks.insert(name.to_string(), Arc::new(hook));
+    }
+
+    /// Checks whether a store has triggers running on `event`, so untriggered mutations skip them cheaply.
+    pub(super) fn has_triggers(&self, store: &str, event: TriggerEvent) -> bool {
+        self.stores.get(store).is_some_and(|target| target.triggers.iter().any(|trigger| trigger.events.contains(&event)))
+    }
+
+    /// Returns the triggers of a store running at `timing` on `event`.
+    fn triggers_for(&self, store: &str, timing: TriggerTiming, event: TriggerEvent) -> Vec<Trigger> {
+        self.stores.get(store).map_or_else(Vec::new, |target| target.triggers.iter()
+            .filter(|trigger| trigger.timing == timing && trigger.events.contains(&event))
+            .cloned()
+            .collect())
+    }
+
+    /// Runs the `Before` triggers of a mutation, after checking that every trigger it will run can.
+    ///
+    /// # Arguments
+    /// * `old` - The record before the mutation, for updates and deletes.
+    /// * `new` - The record being written, for adds and updates; triggers may change it.
+    ///
+    /// # Errors
+    /// Returns an error if a check fails, a hook rejects the mutation, an audit store does not exist or
+    /// a hook is not registered. Nothing has been changed then.
+    pub(super) fn run_before_triggers(&self, store: &str, event: TriggerEvent, id: Option<i64>, old: Option<&HashMap<String, Value>>, mut new: Option<&mut HashMap<String, Value>>) -> io::Result<()> {
+        let Some(target) = self.stores.get(store) else { return Ok(()) };
+        for trigger in target.triggers.iter().filter(|trigger| trigger.events.contains(&event)) {
+            match &trigger.action {
+                TriggerAction::Audit { store: audit } if !self.stores.contains_key(audit) => {
+                    return Err(io::Error::new(io::ErrorKind::NotFound, format!("audit store '{}' of trigger '{}' not found", audit, trigger.name)));
+                }
+                TriggerAction::Call { hook } if !self.hooks.contains_key(hook) => {
+                    return Err(io::Error::new(io::ErrorKind::NotFound, format!("hook '{}' of trigger '{}' is not registered", hook, trigger.name)));
+                }
+                _ => {}
+            }
+        }
+
+        for trigger in self.triggers_for(store, TriggerTiming::Before, event) {
+            match trigger.action {
+                TriggerAction::Check { attribute, operator, value } => {
+                    let Some(record) = new.as_deref().or(old) else { continue };
+                    let pattern = prepare_condition(&operator, &value)?;
+                    if !record_matches(record, &attribute, &operator, &value, pattern.as_ref()) {
+                        return Err(invalid(format!(
+                            "trigger '{}' rejected the record: {} {:?} {} does not hold", trigger.name, attribute, operator, value
+                        )));
+                    }
+                }
+                TriggerAction::SetTimestamp { attribute } => {
+                    if let Some(record) = new.as_deref_mut() {
+                        record.insert(attribute, json!(now_millis()));
+                    }
+                }
+                TriggerAction::SetDefault { attribute, value } => {
+                    if let Some(record) = new.as_deref_mut() {
+                        let current = record.entry(attribute).or_insert(Value::Null);
+                        if current.is_null() {
+                            *current = value;
+                        }
+                    }
+                }
+                TriggerAction::Call { hook } => {
+                    let mut context = HookContext { store, event, timing: TriggerTiming::Before, id, old, new: new.as_deref_mut() };
+                    (self.hooks[&hook])(&mut context)?;
+                }
+                TriggerAction::Audit { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the `After` triggers of a mutation that has been made, giving them the record as now stored.
+    ///
+    /// Audit entries are added straight to their store: they are checked against its schema but do not
+    /// run its own triggers, so audit stores cannot trigger each other endlessly.
+    ///
+    /// # Errors
+    /// Returns an `AfterTriggerError` with the first error of a hook or audit entry; the mutation itself is kept.
+    pub(super) fn run_after_triggers(&mut self, store: &str, event: TriggerEvent, id: i64, old: Option<&HashMap<String, Value>>) -> io::Result<()> {
+        self.after_triggers(store, event, id, old)
+            .map_err(|e| AfterTriggerError::new(vec![id], &e).into())
+    }
+
+    fn after_triggers(&mut self, store: &str, event: TriggerEvent, id: i64, old: Option<&HashMap<String, Value>>) -> io::Result<()> {
+        let triggers = self.triggers_for(store, TriggerTiming::After, event);
+        if triggers.is_empty() {
+            return Ok(());
+        }
+        let new = self.stores.get(store).and_then(|target| target.values.get(&id)).cloned();
+        let new = new.as_ref().filter(|_| event != TriggerEvent::Delete);
+
+        for trigger in triggers {
+            match trigger.action {
+                TriggerAction::Audit { store: audit } => {
+                    let mut entry = HashMap::from([
+                        ("store".to_string(), json!(store)),
+                        ("event".to_string(), json!(event.name())),
+                        ("record_id".to_string(), json!(id)),
+                        ("at".to_string(), json!(now_millis())),
+                    ]);
+                    if let Some(old) = old {
+                        entry.insert("old".to_string(), Value::Object(old.clone().into_iter().collect()));
+                    }
+                    if let Some(new) = new {
+                        entry.insert("new".to_string(), Value::Object(new.clone().into_iter().collect()));
+                    }
+                    let target = self.stores.get_mut(&audit)
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("audit store '{}' not found", audit)))?;
+                    let observed = self.changes.is_observed().then(|| entry.clone());
+                    let entry_id = target.add_record(entry)?;
+                    self.record_changed(&audit, ChangeKind::Insert, entry_id, None, observed);
+                }
+                TriggerAction::Call { hook } => {
+                    let hook = self.hooks.get(&hook).cloned()
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("hook '{}' is not registered", hook)))?;
+                    let mut written = new.cloned();
+                    let mut context = HookContext { store, event, timing: TriggerTiming::After, id: Some(id), old, new: written.as_mut() };
+                    hook(&mut context)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
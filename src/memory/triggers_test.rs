@@ -0,0 +1,223 @@
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use crate::memory::database::Database;
+    use crate::memory::shared::SharedDatabase;
+    use crate::memory::complex::QueryOperator;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::triggers::{AfterTriggerError, Trigger, TriggerAction, TriggerEvent, TriggerTiming};
+    use crate::memory::test_dir::TempDir;
+
+    fn trigger(name: &str, timing: TriggerTiming, events: &[TriggerEvent], action: TriggerAction) -> Trigger {
+        Trigger { name: name.to_string(), timing, events: events.to_vec(), action }
+    }
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("products".to_string(), HashSet::new()).unwrap();
+        db.add_store("audit".to_string(), HashSet::new()).unwrap();
+        let products = db.get_store_mut("products").unwrap();
+        products.add_trigger(trigger("positive_price", TriggerTiming::Before, &[TriggerEvent::Add, TriggerEvent::Update], TriggerAction::Check {
+            attribute: "price".to_string(), operator: QueryOperator::Gt, value: json!(0),
+        })).unwrap();
+        products.add_trigger(trigger("stock_default", TriggerTiming::Before, &[TriggerEvent::Add], TriggerAction::SetDefault {
+            attribute: "stock".to_string(), value: json!(0),
+        })).unwrap();
+        products.add_trigger(trigger("touch", TriggerTiming::Before, &[TriggerEvent::Add, TriggerEvent::Update], TriggerAction::SetTimestamp {
+            attribute: "updated_at".to_string(),
+        })).unwrap();
+        products.add_trigger(trigger("audit_all", TriggerTiming::After, &[TriggerEvent::Add, TriggerEvent::Update, TriggerEvent::Delete], TriggerAction::Audit {
+            store: "audit".to_string(),
+        })).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_before_triggers_reject_and_fill_records() {
+        let mut db = create_test_database("triggers_db");
+
+        assert!(db.add_record("products", hashmap! { "price".to_string() => json!(-1) }).is_err());
+        assert_eq!(db.get_store("products").unwrap().record_count(), 0);
+
+        let id = db.add_record("products", hashmap! { "price".to_string() => json!(5) }).unwrap();
+        let record = db.get_store("products").unwrap().get_record(id).unwrap().clone();
+        assert_eq!(record["stock"], json!(0));
+        assert!(record["updated_at"].as_u64().unwrap() > 0);
+
+        let error = db.update_record("products", id, hashmap! { "price".to_string() => json!(0) }).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(db.get_store("products").unwrap().get_record(id).unwrap()["price"], json!(5));
+    }
+
+    #[test]
+    fn test_after_triggers_write_audit_entries() {
+        let mut db = create_test_database("triggers_db");
+        let id = db.add_record("products", hashmap! { "price".to_string() => json!(5) }).unwrap();
+        db.update_record("products", id, hashmap! { "price".to_string() => json!(7) }).unwrap();
+        db.delete_record("products", id).unwrap();
+
+        let audit = db.get_store("audit").unwrap();
+        let events: Vec<_> = (0..3).map(|entry_id| audit.get_record(entry_id).unwrap()["event"].clone()).collect();
+        assert_eq!(events, vec![json!("add"), json!("update"), json!("delete")]);
+        let update = audit.get_record(1).unwrap();
+        assert_eq!(update["store"], json!("products"));
+        assert_eq!(update["old"]["price"], json!(5));
+        assert_eq!(update["new"]["price"], json!(7));
+        assert!(audit.get_record(2).unwrap().get("new").is_none());
+    }
+
+    #[test]
+    fn test_before_delete_check_and_missing_audit_store() {
+        let mut db = create_test_database("triggers_db");
+        db.get_store_mut("products").unwrap().add_trigger(trigger("only_archived", TriggerTiming::Before, &[TriggerEvent::Delete], TriggerAction::Check {
+            attribute: "archived".to_string(), operator: QueryOperator::Eq, value: json!(true),
+        })).unwrap();
+        let id = db.add_record("products", hashmap! { "price".to_string() => json!(5) }).unwrap();
+        assert!(db.delete_record("products", id).is_err());
+
+        db.delete_store("audit").unwrap();
+        let error = db.add_record("products", hashmap! { "price".to_string() => json!(5) }).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(db.get_store("products").unwrap().record_count(), 1);
+    }
+
+    #[test]
+    fn test_purging_expired_records_skips_before_delete_triggers() {
+        let mut db = create_test_database("triggers_purge_db");
+        db.get_store_mut("products").unwrap().add_trigger(trigger("only_archived", TriggerTiming::Before, &[TriggerEvent::Delete], TriggerAction::Check {
+            attribute: "archived".to_string(), operator: QueryOperator::Eq, value: json!(true),
+        })).unwrap();
+        db.add_record_with_ttl("products", hashmap! { "price".to_string() => json!(5) }, Some(Duration::ZERO)).unwrap();
+        let audited = db.get_store("audit").unwrap().record_count();
+
+        assert_eq!(db.purge_expired(), 1);
+        assert_eq!(db.get_store("audit").unwrap().record_count(), audited + 1);
+    }
+
+    #[test]
+    fn test_after_trigger_errors_keep_the_mutation() {
+        let mut db = create_test_database("triggers_db");
+        db.register_hook("fail", |_| Err(io::Error::other("notification failed")));
+        db.get_store_mut("products").unwrap().add_trigger(trigger("notify", TriggerTiming::After, &[TriggerEvent::Add, TriggerEvent::Delete], TriggerAction::Call {
+            hook: "fail".to_string(),
+        })).unwrap();
+
+        let error = db.add_record("products", hashmap! { "price".to_string() => json!(5) }).unwrap_err();
+        let failure = AfterTriggerError::of(&error).unwrap();
+        assert_eq!(failure.ids, vec![0]);
+        assert_eq!(failure.message, "notification failed");
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        assert_eq!(db.get_store("products").unwrap().record_count(), 1);
+
+        let error = db.delete_record("products", 0).unwrap_err();
+        assert_eq!(AfterTriggerError::of(&error).unwrap().ids, vec![0]);
+        assert_eq!(db.get_store("products").unwrap().record_count(), 0);
+        assert_eq!(db.get_store("audit").unwrap().record_count(), 2);
+
+        let error = db.add_record("products", hashmap! { "price".to_string() => json!(-1) }).unwrap_err();
+        assert!(AfterTriggerError::of(&error).is_none());
+    }
+
+    #[test]
+    fn test_cascaded_deletions_run_delete_triggers() {
+        let mut db = create_test_database("triggers_db");
+        db.add_store("reviews".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("reviews", "product_id", "products", ReferentialAction::Cascade).unwrap();
+        let reviews = db.get_store_mut("reviews").unwrap();
+        reviews.add_trigger(trigger("audit_reviews", TriggerTiming::After, &[TriggerEvent::Delete], TriggerAction::Audit {
+            store: "audit".to_string(),
+        })).unwrap();
+        reviews.add_trigger(trigger("keep_pinned", TriggerTiming::Before, &[TriggerEvent::Delete], TriggerAction::Check {
+            attribute: "pinned".to_string(), operator: QueryOperator::Eq, value: json!(false),
+        })).unwrap();
+
+        let lamp = db.add_record("products", hashmap! { "price".to_string() => json!(5) }).unwrap();
+        let desk = db.add_record("products", hashmap! { "price".to_string() => json!(9) }).unwrap();
+        db.add_record("reviews", hashmap! { "product_id".to_string() => json!(lamp), "pinned".to_string() => json!(false) }).unwrap();
+        db.add_record("reviews", hashmap! { "product_id".to_string() => json!(desk), "pinned".to_string() => json!(true) }).unwrap();
+
+        db.delete_record("products", lamp).unwrap();
+        let audit = db.get_store("audit").unwrap();
+        let stores: Vec<_> = (2..4).map(|entry_id| audit.get_record(entry_id).unwrap()["store"].clone()).collect();
+        assert_eq!(stores, vec![json!("products"), json!("reviews")]);
+        assert_eq!(db.get_store("reviews").unwrap().record_count(), 1);
+
+        assert!(db.delete_record("products", desk).is_err());
+        assert_eq!(db.get_store("products").unwrap().record_count(), 1);
+        assert_eq!(db.get_store("reviews").unwrap().record_count(), 1);
+    }
+
+    #[test]
+    fn test_invalid_triggers_are_refused() {
+        let mut db = create_test_database("triggers_db");
+        let products = db.get_store_mut("products").unwrap();
+
+        assert!(products.add_trigger(trigger("touch", TriggerTiming::Before, &[TriggerEvent::Add], TriggerAction::SetTimestamp { attribute: "x".to_string() })).is_err());
+        assert!(products.add_trigger(trigger("late_check", TriggerTiming::After, &[TriggerEvent::Add], TriggerAction::Check {
+            attribute: "x".to_string(), operator: QueryOperator::Eq, value: json!(1),
+        })).is_err());
+        assert!(products.add_trigger(trigger("early_audit", TriggerTiming::Before, &[TriggerEvent::Add], TriggerAction::Audit { store: "audit".to_string() })).is_err());
+        assert!(products.add_trigger(trigger("stamp_delete", TriggerTiming::Before, &[TriggerEvent::Delete], TriggerAction::SetTimestamp { attribute: "x".to_string() })).is_err());
+        assert!(products.add_trigger(trigger("no_event", TriggerTiming::Before, &[], TriggerAction::Call { hook: "h".to_string() })).is_err());
+
+        assert!(products.drop_trigger("touch").is_ok());
+        assert!(products.drop_trigger("touch").is_err());
+        assert_eq!(products.triggers().len(), 3);
+    }
+
+    #[test]
+    fn test_hooks_run_and_must_be_registered_after_reload() {
+        let mut db = create_test_database("triggers_hooks_db");
+        db.get_store_mut("products").unwrap().add_trigger(trigger("uppercase", TriggerTiming::Before, &[TriggerEvent::Add], TriggerAction::Call {
+            hook: "uppercase_name".to_string(),
+        })).unwrap();
+        let uppercase = |context: &mut crate::memory::triggers::HookContext| -> io::Result<()> {
+            let record = context.new.as_deref_mut().unwrap();
+            let name = record.get("name").and_then(|name| name.as_str())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "name is required"))?
+                .to_uppercase();
+            record.insert("name".to_string(), json!(name));
+            Ok(())
+        };
+
+        assert!(db.add_record("products", hashmap! { "price".to_string() => json!(1) }).is_err());
+        db.register_hook("uppercase_name", uppercase);
+        assert!(db.add_record("products", hashmap! { "price".to_string() => json!(1) }).is_err());
+        let id = db.add_record("products", hashmap! { "price".to_string() => json!(1), "name".to_string() => json!("lamp") }).unwrap();
+        assert_eq!(db.get_store("products").unwrap().get_record(id).unwrap()["name"], json!("LAMP"));
+
+        let dir = TempDir::new("triggers_hooks");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Json).unwrap();
+        let mut reloaded = Database::load_in(dir.path(), "triggers_hooks_db").unwrap();
+        assert_eq!(reloaded.get_store("products").unwrap().triggers(), db.get_store("products").unwrap().triggers());
+
+        assert!(reloaded.add_record("products", hashmap! { "price".to_string() => json!(1), "name".to_string() => json!("desk") }).is_err());
+        reloaded.register_hook("uppercase_name", uppercase);
+        let id = reloaded.add_record("products", hashmap! { "price".to_string() => json!(1), "name".to_string() => json!("desk") }).unwrap();
+        assert_eq!(reloaded.get_store("products").unwrap().get_record(id).unwrap()["name"], json!("DESK"));
+    }
+
+    #[test]
+    fn test_shared_database_runs_triggers() {
+        let db = SharedDatabase::new(create_test_database("triggers_db"));
+        db.register_hook("no_toys", |context| match context.new.as_deref().and_then(|record| record.get("kind")) {
+            Some(kind) if kind == "toy" => Err(io::Error::new(io::ErrorKind::InvalidInput, "no toys")),
+            _ => Ok(()),
+        }).unwrap();
+        db.write("products", |products| products.add_trigger(trigger("no_toys", TriggerTiming::Before, &[TriggerEvent::Add], TriggerAction::Call {
+            hook: "no_toys".to_string(),
+        }))).unwrap().unwrap();
+
+        assert!(db.add_record("products", hashmap! { "price".to_string() => json!(1), "kind".to_string() => json!("toy") }).is_err());
+        let id = db.add_record("products", hashmap! { "price".to_string() => json!(1), "kind".to_string() => json!("book") }).unwrap();
+        db.delete_record("products", id).unwrap();
+        assert_eq!(db.read("audit", |audit| audit.record_count()).unwrap(), 2);
+    }
+}
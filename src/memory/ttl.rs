@@ -0,0 +1,174 @@
+use std::io;
+use std::cell::Cell;
+use serde_json::Value;
+use super::structure::Store;
+use super::database::Database;
+use super::changes::ChangeKind;
+use super::triggers::TriggerEvent;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    /// Time reads on this thread judge expiry at while a snapshot is read, `None` for the current time.
+    static READ_TIME: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+pub(super) fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Returns the time reads judge expiry at: the current time, unless reading as of an earlier time.
+fn read_time() -> u64 {
+    READ_TIME.get().unwrap_or_else(now_millis)
+}
+
+/// Runs `f` with store reads on this thread judging expiry as of `time`, so records that expired
+/// since then are still seen.
+pub(super) fn read_as_of<R>(time: u64, f: impl FnOnce() -> R) -> R {
+    /// Restores the previous read time, also if `f` panics.
+    struct Restore(Option<u64>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            READ_TIME.set(self.0);
+        }
+    }
+
+    let _restore = Restore(READ_TIME.replace(Some(time)));
+    f()
+}
+
+impl Store {
+    /// Returns the time-to-live given to records added without an explicit one.
+    pub fn default_ttl(&self) -> Option<Duration> {
+        self.default_ttl_ms.map(Duration::from_millis)
+    }
+
+    /// Sets the time-to-live given to records added without an explicit one; `None` keeps them forever.
+    ///
+    /// Records already in the store keep their current expiration.
+    pub fn set_default_ttl(&mut self, ttl: Option<Duration>) {
+        self.default_ttl_ms = ttl.map(|ttl| ttl.as_millis() as u64);
+    }
+
+    /// Makes a record expire once `ttl` has elapsed, or never if `ttl` is `None`.
+    pub(super) fn set_expiration(&mut self, id: i64, ttl: Option<Duration>) {
+        match ttl {
+            Some(ttl) => self.expirations.insert(id, now_millis().saturating_add(ttl.as_millis() as u64)),
+            None => self.expirations.remove(&id),
+        };
+    }
+
+    /// Adds a record that expires once `ttl` has elapsed.
+    ///
+    /// # Arguments
+    /// * `record` - The record to add.
+    /// * `ttl` - The record's time-to-live; `None` applies the store's default TTL.
+    ///
+    /// # Returns
+    /// The ID of the newly added record.
+    pub fn add_record_with_ttl(&mut self, record: HashMap<String, Value>, ttl: Option<Duration>) -> io::Result<i64> {
+        let id = self.add_record(record)?;
+        if ttl.is_some() {
+            self.set_expiration(id, ttl);
+        }
+        Ok(id)
+    }
+
+    /// Returns how long a live record has left before it expires, or `None` if it never expires.
+    pub fn time_to_live(&self, id: i64) -> Option<Duration> {
+        self.expirations.get(&id).map(|expires_at| Duration::from_millis(expires_at.saturating_sub(read_time())))
+    }
+
+    /// Checks whether a record has expired. Expired records are hidden from reads until they are purged.
+    pub fn is_expired(&self, id: i64) -> bool {
+        self.expirations.get(&id).is_some_and(|expires_at| *expires_at <= read_time())
+    }
+
+    /// Iterates over the records that have not expired.
+    pub(super) fn live_values(&self) -> impl Iterator<Item = (&i64, &HashMap<String, Value>)> {
+        let now = read_time();
+        self.values.iter()
+            .filter(move |(id, _)| self.expirations.get(id).is_none_or(|expires_at| *expires_at > now))
+    }
+
+    /// Returns the IDs of the expired records, sorted.
+    pub(super) fn expired_ids(&self) -> Vec<i64> {
+        let now = now_millis();
+        let mut ids: Vec<i64> = self.expirations.iter()
+            .filter(|(_, expires_at)| **expires_at <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        ids
+    }
+}
+
+impl Database {
+    /// Adds a record with a time-to-live to a store after running its `Before` triggers and checking it
+    /// against its foreign keys, then runs its `After` triggers.
+    ///
+    /// # Arguments
+    /// * `store` - The name of the store.
+    /// * `record` - The record to add.
+    /// * `ttl` - The record's time-to-live; `None` applies the store's default TTL.
+    ///
+    /// # Returns
+    /// The ID of the newly added record.
+    pub fn add_record_with_ttl(&mut self, store: &str, mut record: HashMap<String, Value>, ttl: Option<Duration>) -> io::Result<i64> {
+        if self.has_triggers(store, TriggerEvent::Add) {
+            self.run_before_triggers(store, TriggerEvent::Add, None, None, Some(&mut record))?;
+        }
+        self.check_references(store, &record)?;
+        let new = self.changes.is_observed().then(|| record.clone());
+        let id = self.stores.get_mut(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?
+            .add_record_with_ttl(record, ttl)?;
+        self.record_changed(store, ChangeKind::Insert, id, None, new);
+        self.run_after_triggers(store, TriggerEvent::Add, id, None)?;
+        Ok(id)
+    }
+
+    /// Physically removes every expired record, applying the referential actions of the foreign keys pointing at them.
+    ///
+    /// Records still referenced through a `Restrict` foreign key are kept, hidden, until the reference goes away.
+    /// Only the `After` `Delete` triggers run: an expired record is already gone to readers, so a `Before`
+    /// trigger could not keep it, and one that rejects the deletion would keep it stored forever.
+    ///
+    /// # Returns
+    /// The number of records removed, including cascaded deletions.
+    pub fn purge_expired(&mut self) -> usize {
+        let mut removed = 0;
+        let mut names: Vec<String> = self.stores.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let Some(store) = self.stores.get_mut(&name) else { continue };
+            store.expirations.retain(|id, _| store.values.contains_key(id));
+            let expired = store.expired_ids();
+            if expired.is_empty() {
+                continue;
+            }
+            if let Ok(count) = self.purge_records(&name, &expired) {
+                removed += count;
+                continue;
+            }
+            // A restricted reference blocks the batch; remove the records that can go one by one.
+            for id in expired {
+                removed += self.purge_records(&name, &[id]).unwrap_or(0);
+            }
+        }
+        removed
+    }
+
+    /// Physically removes expired records of a store as `purge_expired` does, without their `Before` triggers.
+    /// Records whose `After` triggers failed are removed all the same.
+    ///
+    /// # Returns
+    /// The number of records removed, including cascaded deletions.
+    ///
+    /// # Errors
+    /// Returns the errors of `delete_records` other than an `AfterTriggerError`; nothing is removed then.
+    pub(super) fn purge_records(&mut self, store: &str, ids: &[i64]) -> io::Result<usize> {
+        self.remove_records(store, ids, false).map(|(removed, _)| removed)
+    }
+}
@@ -0,0 +1,133 @@
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::time::Duration;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::complex::QueryOperator;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::upsert::{UpsertKey, Upserted};
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("sessions".to_string(), HashSet::new()).unwrap();
+        db.add_record("sessions", hashmap! { "user".to_string() => json!("alice") }).unwrap();
+        db.add_record_with_ttl("sessions", hashmap! { "user".to_string() => json!("bob") }, Some(Duration::ZERO)).unwrap();
+        db.add_record_with_ttl("sessions", hashmap! { "user".to_string() => json!("carol") }, Some(Duration::from_secs(3600))).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_expired_records_are_hidden_from_reads() {
+        let mut db = create_test_database("ttl_db");
+        let sessions = db.get_store_mut("sessions").unwrap();
+
+        assert!(sessions.is_expired(1));
+        assert!(sessions.get_record(1).is_err());
+        assert!(sessions.get_record(2).is_ok());
+        assert_eq!(sessions.get_all_records().unwrap().len(), 2);
+        assert!(sessions.filter("user", "bob").unwrap().is_empty());
+        assert!(sessions.query("user", QueryOperator::Eq, json!("bob")).unwrap().is_empty());
+        assert_eq!(sessions.sort_by("user", true).unwrap().len(), 2);
+
+        assert!(sessions.time_to_live(0).is_none());
+        assert!(sessions.time_to_live(2).unwrap() > Duration::from_secs(3500));
+
+        // Still stored until purged.
+        assert_eq!(sessions.values.len(), 3);
+        assert_eq!(db.purge_expired(), 1);
+        assert_eq!(db.get_store("sessions").unwrap().values.len(), 2);
+        assert_eq!(db.purge_expired(), 0);
+    }
+
+    #[test]
+    fn test_updates_keep_expiration_and_skip_expired_records() {
+        let mut db = create_test_database("ttl_update_db");
+        db.update_record("sessions", 2, hashmap! { "user".to_string() => json!("carol2") }).unwrap();
+        assert!(db.get_store("sessions").unwrap().time_to_live(2).unwrap() > Duration::from_secs(3500));
+
+        let error = db.update_record("sessions", 1, hashmap! { "user".to_string() => json!("bob2") }).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        let upserted = db.upsert_record("sessions", &UpsertKey::Id(1), hashmap! { "user".to_string() => json!("bob3") }).unwrap();
+        assert_eq!(upserted, Upserted::Inserted(1));
+        let sessions = db.get_store("sessions").unwrap();
+        assert_eq!(sessions.get_record(1).unwrap()["user"], json!("bob3"));
+        assert!(sessions.time_to_live(1).is_none());
+    }
+
+    #[test]
+    fn test_default_ttl() {
+        let mut db = create_test_database("ttl_default_db");
+        let sessions = db.get_store_mut("sessions").unwrap();
+        sessions.set_default_ttl(Some(Duration::from_millis(30)));
+        let short = sessions.add_record(hashmap! { "user".to_string() => json!("dave") }).unwrap();
+        let long = sessions.add_record_with_ttl(hashmap! { "user".to_string() => json!("erin") }, Some(Duration::from_secs(60))).unwrap();
+        sessions.set_default_ttl(None);
+        let forever = sessions.add_record(hashmap! { "user".to_string() => json!("frank") }).unwrap();
+
+        thread::sleep(Duration::from_millis(60));
+        assert!(sessions.is_expired(short));
+        assert!(!sessions.is_expired(long));
+        assert!(sessions.time_to_live(forever).is_none());
+    }
+
+    #[test]
+    fn test_expirations_survive_save_and_load() {
+        let mut db = create_test_database("ttl_save_db");
+        db.get_store_mut("sessions").unwrap().set_default_ttl(Some(Duration::from_secs(60)));
+        let dir = TempDir::new("ttl_save");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Binary).unwrap();
+        let loaded = Database::load_in(dir.path(), "ttl_save_db").unwrap();
+
+        // The expired record was purged on save; the others keep their expiration.
+        let sessions = loaded.get_store("sessions").unwrap();
+        assert_eq!(sessions.values.len(), 2);
+        assert!(sessions.time_to_live(0).is_none());
+        assert!(sessions.time_to_live(2).unwrap() > Duration::from_secs(3500));
+        assert_eq!(sessions.default_ttl(), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_purge_applies_referential_actions() {
+        let mut db = create_test_database("ttl_fk_db");
+        db.add_store("carts".to_string(), HashSet::new()).unwrap();
+        db.add_store("audits".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("carts", "session", "sessions", ReferentialAction::Cascade).unwrap();
+        db.add_foreign_key("audits", "session", "sessions", ReferentialAction::Restrict).unwrap();
+        db.add_record("carts", hashmap! { "session".to_string() => json!(1) }).unwrap();
+        db.add_record_with_ttl("sessions", hashmap! { "user".to_string() => json!("gina") }, Some(Duration::ZERO)).unwrap();
+        db.add_record("audits", hashmap! { "session".to_string() => json!(3) }).unwrap();
+
+        // Session 1 goes with its cart; session 3 is still referenced and stays, hidden.
+        assert_eq!(db.purge_expired(), 2);
+        assert!(db.get_store("carts").unwrap().values.is_empty());
+        let sessions = db.get_store("sessions").unwrap();
+        assert!(sessions.values.contains_key(&3) && sessions.is_expired(3));
+    }
+
+    #[test]
+    fn test_upsert_purges_only_the_expired_record_in_its_way() {
+        let mut db = create_test_database("ttl_upsert_db");
+        db.add_store("audits".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("audits", "session", "sessions", ReferentialAction::Restrict).unwrap();
+        db.add_record_with_ttl("sessions", hashmap! { "user".to_string() => json!("dave") }, Some(Duration::ZERO)).unwrap();
+        db.add_record("audits", hashmap! { "session".to_string() => json!(3) }).unwrap();
+
+        // Session 3 is still referenced, so it cannot make way and the audit is not orphaned.
+        let error = db.upsert_record("sessions", &UpsertKey::Id(3), hashmap! { "user".to_string() => json!("erin") }).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(db.get_store("sessions").unwrap().values.contains_key(&3));
+
+        // Upserting at session 1 purges it but leaves the other expired session alone.
+        db.upsert_record("sessions", &UpsertKey::Id(1), hashmap! { "user".to_string() => json!("bob2") }).unwrap();
+        let sessions = db.get_store("sessions").unwrap();
+        assert_eq!(sessions.get_record(1).unwrap()["user"], json!("bob2"));
+        assert!(sessions.values.contains_key(&3) && sessions.is_expired(3));
+    }
+}
@@ -0,0 +1,151 @@
+use std::io;
+use serde_json::Value;
+use std::collections::HashMap;
+use super::ids::key_of;
+use super::structure::Store;
+use super::database::Database;
+use super::changes::ChangeKind;
+use super::triggers::TriggerEvent;
+
+/// Enumeration representing how an upsert finds the record it replaces.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum UpsertKey {
+    /// The record with this ID, which a new record is inserted at if there is none
+    Id(i64),
+    /// The record holding the same value in this attribute, which the upserted record must carry
+    Attribute(String),
+}
+
+/// Enumeration representing what an upsert did.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Upserted {
+    /// No record matched, the record was added under this ID
+    Inserted(i64),
+    /// The record with this ID was replaced
+    Updated(i64),
+}
+
+impl Upserted {
+    /// Returns the ID of the record inserted or updated.
+    pub fn id(&self) -> i64 {
+        match self {
+            Upserted::Inserted(id) | Upserted::Updated(id) => *id,
+        }
+    }
+}
+
+impl Store {
+    /// Adds a record under a caller-chosen ID, moving the store's counter past it so later records do not collide.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error if the ID is negative or the last possible one, an `AlreadyExists`
+    /// error if a record, even one in the trash or only in the history, has this ID, and the errors of `add_record`.
+    pub fn insert_record_at(&mut self, id: i64, record: HashMap<String, Value>) -> io::Result<()> {
+        self.check_id_free(id)?;
+        self.insert_at(id, record)
+    }
+
+    /// Checks that a record could be inserted at `id`.
+    fn check_id_free(&self, id: i64) -> io::Result<()> {
+        if !(0..i64::MAX).contains(&id) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("record ID {} is out of range", id)));
+        }
+        if self.values.contains_key(&id) || self.trash.contains_key(&id) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Record {} already exists", id)));
+        }
+        // A new record under the ID would inherit the prior versions of the deleted one.
+        if self.history.contains_key(&id) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Record {} was deleted but its history is kept", id)));
+        }
+        Ok(())
+    }
+
+    /// Finds the record holding a value in an attribute, which must identify at most one record.
+    ///
+    /// The key attribute of the store's `IdStrategy` is looked up in its index; other attributes are
+    /// compared against every live record.
+    ///
+    /// # Returns
+    /// The ID of the record, or `None` if no record holds the value.
+    ///
+    /// # Errors
+    /// Returns an `InvalidInput` error if several records hold the value.
+    pub fn find_unique(&self, attribute: &str, value: &Value) -> io::Result<Option<i64>> {
+        if self.id_strategy.key_attribute() == Some(attribute) {
+            return Ok(key_of(value).and_then(|key| self.key_index.get(&key).copied()).filter(|id| !self.is_expired(*id)));
+        }
+        let mut ids: Vec<i64> = self.live_values()
+            .filter(|(_, record)| record.get(attribute) == Some(value))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+        match ids[..] {
+            [] => Ok(None),
+            [id] => Ok(Some(id)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("'{}' is not unique: records {:?} hold {}", attribute, ids, value))),
+        }
+    }
+}
+
+impl Database {
+    /// Adds a record to a store under a caller-chosen ID, with the triggers, foreign key checks and
+    /// change events of `add_record`; see `Store::insert_record_at`.
+    ///
+    /// # Errors
+    /// Returns the errors of `Store::insert_record_at` and `add_record`; nothing is changed then.
+    pub fn insert_record_at(&mut self, store: &str, id: i64, mut record: HashMap<String, Value>) -> io::Result<()> {
+        self.store_mut(store)?.check_id_free(id)?;
+        if self.has_triggers(store, TriggerEvent::Add) {
+            self.run_before_triggers(store, TriggerEvent::Add, None, None, Some(&mut record))?;
+        }
+        self.check_references(store, &record)?;
+        let new = self.changes.is_observed().then(|| record.clone());
+        self.store_mut(store)?.insert_record_at(id, record)?;
+        self.record_changed(store, ChangeKind::Insert, id, None, new);
+        self.run_after_triggers(store, TriggerEvent::Add, id, None)
+    }
+
+    /// Replaces the record a key finds, or adds the record if there is none.
+    ///
+    /// Replacing goes through `update_record` and adding through `insert_record_at` or `add_record`, so
+    /// triggers, foreign keys and change events apply as they do there. Expired records count as missing:
+    /// an expired record holding the ID or key is purged first, so it can be taken again.
+    ///
+    /// # Arguments
+    /// * `store` - The name of the store.
+    /// * `key` - How to find the record to replace.
+    /// * `record` - The new content of the record.
+    ///
+    /// # Returns
+    /// Whether the record was inserted or updated, with its ID.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the store does not exist, an `InvalidInput` error if the record
+    /// lacks the attribute it is upserted by or several records match, a `PermissionDenied` error if an
+    /// expired record in the way is still referenced through a `Restrict` foreign key, and the errors of the write.
+    pub fn upsert_record(&mut self, store: &str, key: &UpsertKey, record: HashMap<String, Value>) -> io::Result<Upserted> {
+        let target = self.store_mut(store)?;
+        let (existing, holder) = match key {
+            UpsertKey::Id(id) => ((target.values.contains_key(id) && !target.is_expired(*id)).then_some(*id), Some(*id)),
+            UpsertKey::Attribute(attribute) => {
+                let value = record.get(attribute).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("the record has no '{}' to upsert by", attribute)))?;
+                let holder = (target.id_strategy.key_attribute() == Some(attribute))
+                    .then(|| key_of(value).and_then(|key| target.key_index.get(&key).copied()))
+                    .flatten();
+                (target.find_unique(attribute, value)?, holder)
+            }
+        };
+        // An expired record is gone even before it is purged: purge it, so its ID or key can be taken again.
+        if let Some(expired) = holder.filter(|id| target.values.contains_key(id) && target.is_expired(*id)) {
+            self.purge_records(store, &[expired])?;
+        }
+
+        match (existing, key) {
+            (Some(id), _) => self.update_record(store, id, record).map(|()| Upserted::Updated(id)),
+            (None, UpsertKey::Id(id)) => self.insert_record_at(store, *id, record).map(|()| Upserted::Inserted(*id)),
+            (None, UpsertKey::Attribute(_)) => self.add_record(store, record).map(Upserted::Inserted),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::shared::SharedDatabase;
+    use crate::memory::ids::IdStrategy;
+    use crate::memory::upsert::{UpsertKey, Upserted};
+    use crate::memory::constraints::ReferentialAction;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db
+    }
+
+    fn email_key() -> UpsertKey {
+        UpsertKey::Attribute("email".to_string())
+    }
+
+    #[test]
+    fn test_insert_at_keeps_the_counter_ahead() {
+        let mut db = create_test_database("upsert_insert_at_db");
+        db.insert_record_at("users", 10, hashmap! { "name".to_string() => json!("Ann") }).unwrap();
+        assert_eq!(db.add_record("users", hashmap! { "name".to_string() => json!("Bob") }).unwrap(), 11);
+
+        db.insert_record_at("users", 3, hashmap! { "name".to_string() => json!("Cid") }).unwrap();
+        assert_eq!(db.add_record("users", hashmap! { "name".to_string() => json!("Dee") }).unwrap(), 12);
+        assert_eq!(db.get_store("users").unwrap().get_record(3).unwrap()["name"], json!("Cid"));
+
+        assert!(db.insert_record_at("users", 10, hashmap! { "name".to_string() => json!("Eve") }).is_err());
+        assert!(db.insert_record_at("users", -1, hashmap! { "name".to_string() => json!("Eve") }).is_err());
+
+        // The prior versions of a deleted record keep its ID taken.
+        db.get_store_mut("users").unwrap().set_history(true);
+        db.update_record("users", 3, hashmap! { "name".to_string() => json!("Cyd") }).unwrap();
+        db.delete_record("users", 3).unwrap();
+        assert!(db.insert_record_at("users", 3, hashmap! { "name".to_string() => json!("Eve") }).is_err());
+        assert!(db.upsert_record("users", &UpsertKey::Id(3), hashmap! { "name".to_string() => json!("Eve") }).is_err());
+        assert_eq!(db.get_store("users").unwrap().record_history(3).len(), 2);
+    }
+
+    #[test]
+    fn test_upsert_by_id() {
+        let mut db = create_test_database("upsert_id_db");
+        let inserted = db.upsert_record("users", &UpsertKey::Id(5), hashmap! { "name".to_string() => json!("Ann") }).unwrap();
+        assert_eq!(inserted, Upserted::Inserted(5));
+
+        let updated = db.upsert_record("users", &UpsertKey::Id(5), hashmap! { "name".to_string() => json!("Anna") }).unwrap();
+        assert_eq!(updated, Upserted::Updated(5));
+        let users = db.get_store("users").unwrap();
+        assert_eq!(users.get_record(5).unwrap()["name"], json!("Anna"));
+        assert_eq!(users.get_all_records().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_upsert_by_attribute() {
+        let mut db = create_test_database("upsert_attribute_db");
+        let first = db.upsert_record("users", &email_key(), hashmap! { "email".to_string() => json!("a@x.io"), "name".to_string() => json!("Ann") }).unwrap();
+        let second = db.upsert_record("users", &email_key(), hashmap! { "email".to_string() => json!("a@x.io"), "name".to_string() => json!("Anna") }).unwrap();
+        assert_eq!(second, Upserted::Updated(first.id()));
+        assert_eq!(db.get_store("users").unwrap().get_record(first.id()).unwrap()["name"], json!("Anna"));
+
+        assert!(db.upsert_record("users", &email_key(), hashmap! { "name".to_string() => json!("nobody") }).is_err());
+        db.add_record("users", hashmap! { "email".to_string() => json!("a@x.io") }).unwrap();
+        assert!(db.upsert_record("users", &email_key(), hashmap! { "email".to_string() => json!("a@x.io") }).is_err());
+    }
+
+    #[test]
+    fn test_upsert_by_natural_key_and_foreign_keys() {
+        let mut db = create_test_database("upsert_key_db");
+        db.get_store_mut("users").unwrap().set_id_strategy(IdStrategy::NaturalKey { attribute: "login".to_string() }).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::Restrict).unwrap();
+
+        let login = UpsertKey::Attribute("login".to_string());
+        let ann = db.upsert_record("users", &login, hashmap! { "login".to_string() => json!("ann") }).unwrap().id();
+        let again = db.upsert_record("users", &login, hashmap! { "login".to_string() => json!("ann"), "age".to_string() => json!(30) }).unwrap();
+        assert_eq!(again, Upserted::Updated(ann));
+
+        assert!(db.upsert_record("orders", &UpsertKey::Id(1), hashmap! { "user_id".to_string() => json!(99) }).is_err());
+        assert!(db.get_store("orders").unwrap().get_record(1).is_err());
+        db.upsert_record("orders", &UpsertKey::Id(1), hashmap! { "user_id".to_string() => json!(ann) }).unwrap();
+    }
+
+    #[test]
+    fn test_shared_upserts_insert_once() {
+        let shared = SharedDatabase::new(create_test_database("upsert_shared_db"));
+        let handles: Vec<_> = (0..8).map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.upsert_record("users", &email_key(), hashmap! { "email".to_string() => json!("a@x.io") }).unwrap())
+        }).collect();
+        let inserted = handles.into_iter().map(|handle| handle.join().unwrap())
+            .filter(|upserted| matches!(upserted, Upserted::Inserted(_)))
+            .count();
+
+        assert_eq!(inserted, 1);
+        assert_eq!(shared.read("users", |users| users.get_all_records().unwrap().len()).unwrap(), 1);
+    }
+}
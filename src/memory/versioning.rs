@@ -0,0 +1,125 @@
+use std::io;
+use std::fmt;
+use std::error::Error;
+use serde_json::Value;
+use std::collections::HashMap;
+use super::structure::Store;
+use super::database::Database;
+use super::ttl::now_millis;
+use serde::{Deserialize, Serialize};
+
+/// System fields of a record, maintained when its store tracks them.
+#[derive(Debug, Deserialize, Serialize, Eq, PartialEq, Clone, Copy)]
+pub struct RecordMetadata {
+    pub created_at: u64,    // Time the record was added, in Unix milliseconds.
+    pub updated_at: u64,    // Time the record last changed, in Unix milliseconds.
+    pub version: u64,       // Starts at 1 and goes up by one on every change.
+}
+
+/// The error of an update that expected another version of the record.
+///
+/// It is wrapped in an `io::Error` of kind `Other`; use `get_ref` and `downcast_ref` to inspect it.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct VersionConflict {
+    pub id: i64,            // ID of the record.
+    pub expected: u64,      // Version the caller read.
+    pub actual: u64,        // Version the record has now.
+}
+
+impl fmt::Display for VersionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record {} is at version {}, not {}", self.id, self.actual, self.expected)
+    }
+}
+
+impl Error for VersionConflict {}
+
+impl Store {
+    /// Checks whether the store maintains creation time, modification time and version of its records.
+    pub fn tracks_metadata(&self) -> bool {
+        self.track_metadata
+    }
+
+    /// Starts or stops maintaining the system fields of the store's records.
+    ///
+    /// Records already in the store are stamped as created now, at version 1, when tracking starts;
+    /// their system fields, and the history that depends on them, are discarded when it stops.
+    pub fn set_metadata_tracking(&mut self, enabled: bool) {
+        if self.track_metadata == enabled {
+            return;
+        }
+        if !enabled {
+            self.set_history(false);
+        }
+        self.track_metadata = enabled;
+        self.metadata.clear();
+        if enabled {
+            let now = now_millis();
+            for id in self.values.keys() {
+                self.metadata.insert(*id, RecordMetadata { created_at: now, updated_at: now, version: 1 });
+            }
+        }
+    }
+
+    /// Returns the system fields of a record, or `None` if the store does not track them or the record does not exist.
+    pub fn metadata(&self, id: i64) -> Option<RecordMetadata> {
+        self.metadata.get(&id).copied()
+    }
+
+    /// Records that a record was just added.
+    pub(super) fn stamp_created(&mut self, id: i64) {
+        if self.track_metadata {
+            let now = now_millis();
+            self.metadata.insert(id, RecordMetadata { created_at: now, updated_at: now, version: 1 });
+        }
+    }
+
+    /// Records that a record just changed, bumping its version.
+    pub(super) fn stamp_updated(&mut self, id: i64) {
+        if self.track_metadata {
+            let now = now_millis();
+            self.metadata.entry(id)
+                .and_modify(|metadata| {
+                    metadata.updated_at = now.max(metadata.updated_at);
+                    metadata.version += 1;
+                })
+                .or_insert(RecordMetadata { created_at: now, updated_at: now, version: 1 });
+        }
+    }
+
+    /// Checks that a record is at the version the caller expects.
+    ///
+    /// # Errors
+    /// Returns a `NotFound` error if the record does not exist, an `Unsupported` error if the store
+    /// does not track versions, and a `VersionConflict` if the versions differ.
+    pub fn check_version(&self, id: i64, expected: u64) -> io::Result<()> {
+        if !self.values.contains_key(&id) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "Record not found"));
+        }
+        if !self.track_metadata {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("store '{}' does not track record versions", self.name)));
+        }
+        let actual = self.metadata.get(&id).map_or(0, |metadata| metadata.version);
+        if actual != expected {
+            return Err(io::Error::other(VersionConflict { id, expected, actual }));
+        }
+        Ok(())
+    }
+}
+
+impl Database {
+    /// Replaces a record only if it is still at the version the caller read, as `update_record` does otherwise.
+    ///
+    /// # Returns
+    /// The record's new version.
+    ///
+    /// # Errors
+    /// Returns the errors of `Store::check_version` and `update_record`; nothing is changed then.
+    pub fn update_record_if_version(&mut self, store: &str, id: i64, expected_version: u64, record: HashMap<String, Value>) -> io::Result<u64> {
+        self.stores.get(store)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Store '{}' not found", store)))?
+            .check_version(id, expected_version)?;
+        self.update_record(store, id, record)?;
+        Ok(self.stores[store].metadata(id).map_or(0, |metadata| metadata.version))
+    }
+}
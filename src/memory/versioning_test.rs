@@ -0,0 +1,102 @@
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use maplit::hashmap;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use crate::memory::database::Database;
+    use crate::memory::shared::SharedDatabase;
+    use crate::memory::storage::SnapshotFormat;
+    use crate::memory::versioning::VersionConflict;
+    use crate::memory::constraints::ReferentialAction;
+    use crate::memory::test_dir::TempDir;
+
+    fn create_test_database(name: &str) -> Database {
+        let mut db = Database::new(name).unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        db.add_store("orders".to_string(), HashSet::new()).unwrap();
+        db.get_store_mut("users").unwrap().set_metadata_tracking(true);
+        db.get_store_mut("orders").unwrap().set_metadata_tracking(true);
+        db
+    }
+
+    #[test]
+    fn test_add_and_update_maintain_metadata() {
+        let mut db = create_test_database("versioning_stamps_db");
+        let id = db.add_record("users", hashmap! { "name".to_string() => json!("alice") }).unwrap();
+        let created = db.get_store("users").unwrap().metadata(id).unwrap();
+        assert_eq!(created.version, 1);
+        assert_eq!(created.created_at, created.updated_at);
+
+        db.update_record("users", id, hashmap! { "name".to_string() => json!("alicia") }).unwrap();
+        db.update_record("users", id, hashmap! { "name".to_string() => json!("ali") }).unwrap();
+        let updated = db.get_store("users").unwrap().metadata(id).unwrap();
+        assert_eq!(updated.version, 3);
+        assert_eq!(updated.created_at, created.created_at);
+        assert!(updated.updated_at >= created.updated_at);
+
+        db.delete_record("users", id).unwrap();
+        assert!(db.get_store("users").unwrap().metadata(id).is_none());
+    }
+
+    #[test]
+    fn test_tracking_can_be_turned_on_and_off() {
+        let mut db = Database::new("versioning_toggle_db").unwrap();
+        db.add_store("users".to_string(), HashSet::new()).unwrap();
+        let id = db.add_record("users", hashmap! { "name".to_string() => json!("bob") }).unwrap();
+        assert!(db.get_store("users").unwrap().metadata(id).is_none());
+        assert!(db.update_record_if_version("users", id, 1, hashmap! {}).is_err());
+
+        db.get_store_mut("users").unwrap().set_metadata_tracking(true);
+        assert_eq!(db.get_store("users").unwrap().metadata(id).unwrap().version, 1);
+        db.get_store_mut("users").unwrap().set_metadata_tracking(false);
+        assert!(db.get_store("users").unwrap().metadata(id).is_none());
+    }
+
+    #[test]
+    fn test_update_with_stale_version_is_rejected() {
+        let mut db = create_test_database("versioning_conflict_db");
+        let id = db.add_record("users", hashmap! { "name".to_string() => json!("carol") }).unwrap();
+
+        assert_eq!(db.update_record_if_version("users", id, 1, hashmap! { "name".to_string() => json!("caroline") }).unwrap(), 2);
+        let error = db.update_record_if_version("users", id, 1, hashmap! { "name".to_string() => json!("lost") }).unwrap_err();
+        let conflict = error.get_ref().and_then(|inner| inner.downcast_ref::<VersionConflict>()).unwrap();
+        assert_eq!(*conflict, VersionConflict { id, expected: 1, actual: 2 });
+        assert_eq!(db.get_store("users").unwrap().get_record(id).unwrap()["name"], json!("caroline"));
+        assert!(db.update_record_if_version("users", 42, 1, hashmap! {}).is_err());
+    }
+
+    #[test]
+    fn test_set_null_bumps_version_and_metadata_is_saved() {
+        let mut db = create_test_database("versioning_saved_db");
+        db.add_foreign_key("orders", "user_id", "users", ReferentialAction::SetNull).unwrap();
+        let user = db.add_record("users", hashmap! { "name".to_string() => json!("dave") }).unwrap();
+        let order = db.add_record("orders", hashmap! { "user_id".to_string() => json!(user) }).unwrap();
+        db.delete_record("users", user).unwrap();
+        assert_eq!(db.get_store("orders").unwrap().metadata(order).unwrap().version, 2);
+
+        let dir = TempDir::new("versioning_saved");
+        db.dir = dir.path().to_path_buf();
+        db.store_as(SnapshotFormat::Json).unwrap();
+        let reloaded = Database::load_in(dir.path(), "versioning_saved_db").unwrap();
+        let orders = reloaded.get_store("orders").unwrap();
+        assert!(orders.tracks_metadata());
+        assert_eq!(orders.metadata(order), db.get_store("orders").unwrap().metadata(order));
+    }
+
+    #[test]
+    fn test_only_one_concurrent_writer_wins_a_version() {
+        let db = SharedDatabase::new(create_test_database("versioning_shared_db"));
+        let id = db.add_record("users", hashmap! { "visits".to_string() => json!(0) }).unwrap();
+
+        let writers: Vec<_> = (0..8).map(|thread| {
+            let db = db.clone();
+            thread::spawn(move || db.update_record_if_version("users", id, 1, hashmap! { "visits".to_string() => json!(thread) }).is_ok())
+        }).collect();
+        let winners = writers.into_iter().map(|writer| writer.join().unwrap()).filter(|won| *won).count();
+
+        assert_eq!(winners, 1);
+        assert_eq!(db.read("users", |users| users.metadata(id).unwrap().version).unwrap(), 2);
+    }
+}